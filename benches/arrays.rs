@@ -0,0 +1,70 @@
+//! Compares writing large array tags using the bulk array writers of the fixed-size encodings
+//! with writing them one element at a time.
+//!
+//! Run using `cargo bench --bench arrays`. Prints the average time of a single write of the tree
+//! for every encoding, both in bulk and per element.
+use std::hint::black_box;
+
+use bytes::BufMut;
+use zuri_nbt::encode::{self, Writer};
+use zuri_nbt::encoding::{BigEndian, LittleEndian};
+use zuri_nbt::{tag, NBTTag};
+
+mod common;
+
+const ITERATIONS: u32 = 200;
+
+/// A writer that only implements the required methods of [Writer], so that arrays are written
+/// one element at a time by the default methods.
+struct PerElement<W>(W);
+
+impl<W: Writer> Writer for PerElement<W> {
+    fn write_i16(&mut self, buf: &mut impl BufMut, x: i16) -> encode::Res {
+        self.0.write_i16(buf, x)
+    }
+
+    fn write_i32(&mut self, buf: &mut impl BufMut, x: i32) -> encode::Res {
+        self.0.write_i32(buf, x)
+    }
+
+    fn write_i64(&mut self, buf: &mut impl BufMut, x: i64) -> encode::Res {
+        self.0.write_i64(buf, x)
+    }
+
+    fn write_f32(&mut self, buf: &mut impl BufMut, x: f32) -> encode::Res {
+        self.0.write_f32(buf, x)
+    }
+
+    fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> encode::Res {
+        self.0.write_f64(buf, x)
+    }
+}
+
+/// Builds a tree resembling the block states and height maps of a chunk.
+fn tree() -> NBTTag {
+    NBTTag::Compound(
+        tag::Compound::builder()
+            .with_long_array("BlockStates", (0..65536).collect::<Vec<_>>())
+            .with_int_array("Biomes", (0..65536).collect::<Vec<_>>())
+            .build(),
+    )
+}
+
+/// Times writing the tree with the writer and with the same writer writing one element at a time.
+fn compare<W: Writer + Clone>(name: &str, nbt: &NBTTag, w: W) {
+    let bulk = common::time(ITERATIONS, || {
+        black_box(nbt.to_bytes(&mut w.clone()).unwrap());
+    });
+    let per_element = common::time(ITERATIONS, || {
+        black_box(nbt.to_bytes(&mut PerElement(w.clone())).unwrap());
+    });
+    println!("{name}:");
+    println!("  bulk:        {bulk:?}");
+    println!("  per element: {per_element:?}");
+}
+
+fn main() {
+    let nbt = tree();
+    compare("big endian", &nbt, BigEndian::default());
+    compare("little endian", &nbt, LittleEndian::default());
+}
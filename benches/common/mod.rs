@@ -0,0 +1,88 @@
+//! Helpers shared by the benchmarks. No benchmarking framework is available, so measurements are
+//! taken using simple loops.
+// Not every benchmark uses every helper.
+#![allow(dead_code)]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Returns the average time taken by the closure over the provided amount of iterations.
+pub fn time(iterations: u32, mut f: impl FnMut()) -> Duration {
+    // Warm up the allocator and caches first.
+    for _ in 0..iterations / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+/// An allocator that counts the allocations made through it, and keeps track of the amount of
+/// bytes allocated. It only counts allocations when a benchmark registers it using
+/// `#[global_allocator]`.
+pub struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Records that the amount of bytes allocated grew by `size`.
+fn grow(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        grow(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        if new_size > layout.size() {
+            grow(new_size - layout.size());
+        } else {
+            ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Returns the amount of allocations and reallocations made by a single call to the closure.
+pub fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// The memory used by a closure, in bytes.
+pub struct Memory {
+    /// The highest amount of memory that was allocated at once while the closure ran.
+    pub peak: usize,
+    /// The amount of memory that was still allocated after the closure returned, which is the
+    /// memory retained by its result.
+    pub retained: usize,
+}
+
+/// Runs the closure once, returning its result, the time it took and the memory it used.
+pub fn memory<T>(f: impl FnOnce() -> T) -> (T, Duration, Memory) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let memory = Memory {
+        peak: PEAK.load(Ordering::Relaxed) - before,
+        retained: ALLOCATED.load(Ordering::Relaxed) - before,
+    };
+    (result, elapsed, memory)
+}
@@ -0,0 +1,85 @@
+//! Compares the memory used while reading a region's worth of chunks with repetitive palettes
+//! into regular tags, into shared tags, and into shared tags with deduplicated subtrees.
+//!
+//! Run using `cargo bench --bench dedup`. Prints the time taken to read all chunks, the peak
+//! amount of memory allocated while reading them, and the amount of memory retained by the tags
+//! and interner afterwards.
+use std::hint::black_box;
+
+use zuri_nbt::encoding::BigEndian;
+use zuri_nbt::shared::{Interner, SharedNBTTag};
+use zuri_nbt::{tag, NBTTag};
+
+mod common;
+
+#[global_allocator]
+static GLOBAL: common::Counting = common::Counting;
+
+const CHUNKS: i32 = 1024;
+
+/// Builds a chunk section resembling those of Minecraft: Java Edition, of which the palette is
+/// the same for most chunks.
+fn chunk(x: i32) -> Vec<u8> {
+    let palette: Vec<_> = ["air", "stone", "dirt", "grass_block", "bedrock"]
+        .iter()
+        .map(|name| {
+            tag::Compound::builder()
+                .with_string("Name", format!("minecraft:{name}"))
+                .build()
+        })
+        .collect();
+    NBTTag::Compound(
+        tag::Compound::builder()
+            .with_int("xPos", x)
+            .with_int("zPos", 0)
+            .with_compound(
+                "block_states",
+                tag::Compound::builder()
+                    .with_list("palette", palette)
+                    .with_long_array("data", vec![0x1111_1111_1111_1111; 16]),
+            )
+            .build(),
+    )
+    .to_bytes(&mut BigEndian::default())
+    .unwrap()
+}
+
+/// Reads all chunks, and prints the time taken and the memory used by it.
+fn report<T>(name: &str, read: impl FnOnce() -> T) {
+    let (result, time, memory) = common::memory(read);
+    black_box(&result);
+    println!(
+        "{name:<18} {time:?}, {} bytes peak, {} bytes retained",
+        memory.peak, memory.retained
+    );
+}
+
+fn main() {
+    let chunks: Vec<_> = (0..CHUNKS).map(chunk).collect();
+    let read_interned = |mut interner: Interner| {
+        let tags: Vec<_> = chunks
+            .iter()
+            .map(|c| {
+                SharedNBTTag::read_interned(
+                    &mut c.as_slice(),
+                    &mut BigEndian::default(),
+                    &mut interner,
+                )
+                .unwrap()
+            })
+            .collect();
+        (interner, tags)
+    };
+
+    println!("{CHUNKS} chunks");
+    report("regular tags:", || {
+        chunks
+            .iter()
+            .map(|c| NBTTag::read(&mut c.as_slice(), &mut BigEndian::default()).unwrap())
+            .collect::<Vec<_>>()
+    });
+    report("shared tags:", || read_interned(Interner::default()));
+    report("deduplicated tags:", || {
+        read_interned(Interner::deduplicating())
+    });
+}
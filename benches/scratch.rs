@@ -0,0 +1,67 @@
+//! Compares writing many small trees into a reused network buffer directly, through a new vector
+//! each time and through a reused scratch vector.
+//!
+//! Run using `cargo bench --bench scratch`. Prints the average time of a single write and the
+//! amount of allocations it makes, once the buffers have grown to the size of the data.
+use std::hint::black_box;
+
+use bytes::BufMut;
+use zuri_nbt::encoding::NetworkLittleEndian;
+use zuri_nbt::{tag, NBTTag};
+
+mod common;
+
+#[global_allocator]
+static GLOBAL: common::Counting = common::Counting;
+
+const WRITES: u32 = 100_000;
+
+/// Builds a tree resembling the NBT in a block entity data packet.
+fn tree() -> NBTTag {
+    NBTTag::Compound(
+        tag::Compound::builder()
+            .with_string("id", "Sign")
+            .with_int("x", 12)
+            .with_int("y", 64)
+            .with_int("z", -3)
+            .with_string("Text", "Welcome to the server!")
+            .build(),
+    )
+}
+
+/// Prints the average time taken by a single write, and the amount of allocations it makes.
+fn report(name: &str, mut write: impl FnMut()) {
+    let time = common::time(WRITES, &mut write);
+    let allocations = common::allocations(write);
+    println!("{name:<15} {time:?}, {allocations} allocations");
+}
+
+fn main() {
+    let nbt = tree();
+    let mut network = Vec::new();
+    let mut scratch = Vec::new();
+
+    println!("{WRITES} writes");
+    report("direct:", || {
+        network.clear();
+        nbt.write(&mut network, &mut NetworkLittleEndian::default())
+            .unwrap();
+        black_box(&network);
+    });
+    report("new vector:", || {
+        network.clear();
+        let bytes = nbt.to_bytes(&mut NetworkLittleEndian::default()).unwrap();
+        network.put_slice(&bytes);
+        black_box(&network);
+    });
+    report("scratch vector:", || {
+        network.clear();
+        nbt.write_with_scratch(
+            &mut network,
+            &mut NetworkLittleEndian::default(),
+            &mut scratch,
+        )
+        .unwrap();
+        black_box(&network);
+    });
+}
@@ -0,0 +1,52 @@
+//! Compares writing a large tree into a growing vector with writing it into a vector that has
+//! been preallocated using the length of previous output.
+//!
+//! Run using `cargo bench --bench write`. Prints the average time of a single write of the tree
+//! for both vectors.
+use std::hint::black_box;
+
+use zuri_nbt::encoding::LittleEndian;
+use zuri_nbt::{tag, NBTTag};
+
+mod common;
+
+const ITERATIONS: u32 = 200;
+
+/// Builds a tree resembling a chunk of block entities.
+fn tree() -> NBTTag {
+    let entities = (0..2000)
+        .map(|i| {
+            tag::Compound::builder()
+                .with_string("id", "minecraft:chest")
+                .with_int("x", i)
+                .with_int("y", 64)
+                .with_int("z", -i)
+                .with_int_array("data", (0..32).collect::<Vec<_>>())
+                .build()
+        })
+        .collect::<Vec<_>>();
+    NBTTag::Compound(
+        tag::Compound::builder()
+            .with_list("block_entities", entities)
+            .build(),
+    )
+}
+
+fn main() {
+    let nbt = tree();
+    let len = nbt.to_bytes(&mut LittleEndian::default()).unwrap().len();
+
+    let growing = common::time(ITERATIONS, || {
+        black_box(nbt.to_bytes(&mut LittleEndian::default()).unwrap());
+    });
+    let preallocated = common::time(ITERATIONS, || {
+        black_box(
+            nbt.to_bytes_with_capacity(&mut LittleEndian::default(), len)
+                .unwrap(),
+        );
+    });
+
+    println!("writing {len} bytes");
+    println!("growing vector:      {growing:?}");
+    println!("preallocated vector: {preallocated:?}");
+}
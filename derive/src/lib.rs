@@ -0,0 +1,168 @@
+//! Derive macros for the `zuri_nbt` crate.
+//!
+//! This crate should not be used directly. Enable the `derive` feature of `zuri_nbt` instead,
+//! which re-exports the macros in the `zuri_nbt::derive` module along with the traits they
+//! implement.
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, LitStr};
+
+/// Derives `NbtSerialize` for a struct with named fields, writing each field as an entry in a
+/// compound. See the `zuri_nbt::derive` module for the supported attributes.
+#[proc_macro_derive(NbtSerialize, attributes(nbt))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_serialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `NbtDeserialize` for a struct with named fields, reading each field from an entry in
+/// a compound. See the `zuri_nbt::derive` module for the supported attributes.
+#[proc_macro_derive(NbtDeserialize, attributes(nbt))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_deserialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A struct field along with the options set through its `#[nbt(...)]` attributes.
+struct Field {
+    ident: Ident,
+    /// The key of the compound entry that the field is stored in.
+    key: LitStr,
+    /// The tag type that an integer field is stored as, if overridden.
+    width: Option<Ident>,
+}
+
+/// Collects the fields of a struct with named fields, parsing their attributes.
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "only structs with named fields are supported",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            ))
+        }
+    };
+
+    fields
+        .iter()
+        .map(|field| {
+            // Named fields always have an identifier.
+            let ident = field.ident.clone().unwrap();
+            let mut key = LitStr::new(&ident.to_string(), ident.span());
+            let mut width = None;
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("nbt"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        key = meta.value()?.parse()?;
+                        return Ok(());
+                    }
+                    let variant = match meta.path.get_ident().map(Ident::to_string).as_deref() {
+                        Some("byte") => "Byte",
+                        Some("short") => "Short",
+                        Some("int") => "Int",
+                        Some("long") => "Long",
+                        _ => return Err(meta.error("unknown nbt attribute")),
+                    };
+                    if width.is_some() {
+                        return Err(meta.error("only one integer width may be specified"));
+                    }
+                    width = Some(Ident::new(variant, Span::call_site()));
+                    Ok(())
+                })?;
+            }
+            Ok(Field { ident, key, width })
+        })
+        .collect()
+}
+
+fn expand_serialize(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let entries = fields(input)?.into_iter().map(|field| {
+        let Field { ident, key, width } = field;
+        match width {
+            Some(width) => quote! {
+                map.insert(
+                    #key.to_string(),
+                    ::zuri_nbt::derive::__private::int_to_nbt(
+                        &self.#ident,
+                        ::zuri_nbt::NBTTagType::#width,
+                    ),
+                );
+            },
+            None => quote! {
+                if let Some(v) = ::zuri_nbt::derive::NbtSerialize::to_nbt_field(&self.#ident) {
+                    map.insert(#key.to_string(), v);
+                }
+            },
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::zuri_nbt::derive::NbtSerialize for #name #ty_generics #where_clause {
+            fn to_nbt(&self) -> ::zuri_nbt::NBTTag {
+                let mut map = ::std::collections::HashMap::new();
+                #(#entries)*
+                ::zuri_nbt::NBTTag::Compound(map.into())
+            }
+        }
+    })
+}
+
+fn expand_deserialize(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = fields(input)?.into_iter().map(|field| {
+        let Field { ident, key, width } = field;
+        match width {
+            Some(width) => quote! {
+                #ident: ::zuri_nbt::derive::__private::int_field(
+                    compound,
+                    #key,
+                    ::zuri_nbt::NBTTagType::#width,
+                )?,
+            },
+            None => quote! {
+                #ident: ::zuri_nbt::derive::__private::field(compound, #key)?,
+            },
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::zuri_nbt::derive::NbtDeserialize
+            for #name #ty_generics #where_clause
+        {
+            fn from_nbt(
+                tag: &::zuri_nbt::NBTTag,
+            ) -> ::std::result::Result<
+                Self,
+                ::zuri_nbt::err::ErrorPath<::zuri_nbt::err::TypeError>,
+            > {
+                let compound = ::zuri_nbt::derive::__private::compound(tag)?;
+                ::std::result::Result::Ok(Self {
+                    #(#fields)*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,281 @@
+//! Generative round-trip tests, which write randomly generated tag trees with every encoding and
+//! check that reading them produces the same tree.
+//!
+//! No property testing framework is available, so a small deterministic random number generator
+//! and a greedy shrinker are used instead. Failing trees are shrunk to a minimal failing case
+//! before being reported.
+use crate::encoding::AnyEncoding;
+use crate::{tag, NBTTag, NBTTagType};
+
+/// The amount of trees generated for every encoding.
+const CASES: u64 = 500;
+/// The maximum nesting depth of generated trees.
+const MAX_DEPTH: usize = 4;
+
+/// A xorshift random number generator, seeded per case so that failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero.
+        Self(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns a length that is usually small, but occasionally large.
+    fn len(&mut self) -> usize {
+        match self.below(10) {
+            0 => 0,
+            1 => self.below(300),
+            _ => self.below(6),
+        }
+    }
+}
+
+/// A type that can be randomly generated.
+trait Arbitrary: Sized {
+    fn arbitrary(rng: &mut Rng, depth: usize) -> Self;
+}
+
+impl Arbitrary for String {
+    fn arbitrary(rng: &mut Rng, _depth: usize) -> Self {
+        (0..rng.len())
+            .map(|_| match rng.below(4) {
+                0 => char::from(rng.below(128) as u8),
+                // Characters that are encoded differently in modified UTF-8.
+                1 => ['\0', '\u{10000}', '\u{1F600}', '\u{FFFF}'][rng.below(4)],
+                _ => char::from_u32(rng.below(0xD800) as u32).unwrap(),
+            })
+            .collect()
+    }
+}
+
+impl Arbitrary for f64 {
+    fn arbitrary(rng: &mut Rng, _depth: usize) -> Self {
+        // NaN is never equal to itself, so it is replaced to be able to compare trees.
+        let v = f64::from_bits(rng.next());
+        if v.is_nan() {
+            0.0
+        } else {
+            v
+        }
+    }
+}
+
+/// Generates a tag of a specific type, with containers only containing other containers while the
+/// maximum depth has not been reached.
+type Generator = fn(&mut Rng, usize) -> NBTTag;
+
+/// The tag types that are generated, with their generators. Container types come last, so that
+/// they can be left out once the maximum depth is reached. Custom tags can only be read with a
+/// registered handler, so they are never generated.
+const TYPES: [(NBTTagType, Generator); 12] = [
+    (NBTTagType::Byte, |rng, _| {
+        NBTTag::Byte((rng.next() as u8).into())
+    }),
+    (NBTTagType::Short, |rng, _| {
+        NBTTag::Short((rng.next() as i16).into())
+    }),
+    (NBTTagType::Int, |rng, _| {
+        NBTTag::Int((rng.next() as i32).into())
+    }),
+    (NBTTagType::Long, |rng, _| {
+        NBTTag::Long((rng.next() as i64).into())
+    }),
+    (NBTTagType::Float, |rng, depth| {
+        NBTTag::Float((f64::arbitrary(rng, depth) as f32).into())
+    }),
+    (NBTTagType::Double, |rng, depth| {
+        NBTTag::Double(f64::arbitrary(rng, depth).into())
+    }),
+    (NBTTagType::String, |rng, depth| {
+        NBTTag::String(String::arbitrary(rng, depth).into())
+    }),
+    (NBTTagType::ByteArray, |rng, _| {
+        NBTTag::ByteArray(
+            (0..rng.len())
+                .map(|_| rng.next() as u8)
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }),
+    (NBTTagType::IntArray, |rng, _| {
+        NBTTag::IntArray(
+            (0..rng.len())
+                .map(|_| rng.next() as i32)
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }),
+    (NBTTagType::LongArray, |rng, _| {
+        NBTTag::LongArray(
+            (0..rng.len())
+                .map(|_| rng.next() as i64)
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }),
+    (NBTTagType::Compound, |rng, depth| {
+        NBTTag::Compound(tag::Compound::arbitrary(rng, depth))
+    }),
+    (NBTTagType::List, |rng, depth| {
+        NBTTag::List(tag::List::arbitrary(rng, depth))
+    }),
+];
+
+/// Returns a random tag type with its generator, which is only a container type while the maximum
+/// depth has not been reached.
+fn arbitrary_type(rng: &mut Rng, depth: usize) -> (NBTTagType, Generator) {
+    if depth >= MAX_DEPTH {
+        TYPES[rng.below(TYPES.len() - 2)]
+    } else {
+        TYPES[rng.below(TYPES.len())]
+    }
+}
+
+impl Arbitrary for tag::Compound {
+    fn arbitrary(rng: &mut Rng, depth: usize) -> Self {
+        (0..rng.below(6))
+            .map(|_| {
+                let (_, generate) = arbitrary_type(rng, depth + 1);
+                (String::arbitrary(rng, depth), generate(rng, depth + 1))
+            })
+            .collect::<std::collections::HashMap<_, _>>()
+            .into()
+    }
+}
+
+impl Arbitrary for tag::List {
+    /// Generates a list in which all elements have the same type, including empty lists with any
+    /// element type.
+    fn arbitrary(rng: &mut Rng, depth: usize) -> Self {
+        let (tag_type, generate) = arbitrary_type(rng, depth + 1);
+        let elements = (0..rng.below(6))
+            .map(|_| generate(rng, depth + 1))
+            .collect();
+        tag::List::with_type(tag_type.id(), elements).unwrap()
+    }
+}
+
+impl Arbitrary for NBTTag {
+    fn arbitrary(rng: &mut Rng, depth: usize) -> Self {
+        let (_, generate) = arbitrary_type(rng, depth);
+        generate(rng, depth)
+    }
+}
+
+/// Returns trees that are one step smaller than the tree, by removing an entry or element,
+/// replacing a container with one of its children or emptying a string or array.
+fn shrink(nbt: &NBTTag) -> Vec<NBTTag> {
+    let mut smaller = Vec::new();
+    match nbt {
+        NBTTag::Compound(c) => {
+            for (key, value) in c.iter() {
+                smaller.push(value.clone());
+                let mut removed = c.clone();
+                removed.remove(key);
+                smaller.push(NBTTag::Compound(removed));
+                for value in shrink(value) {
+                    let mut replaced = c.clone();
+                    replaced.insert(key.clone(), value);
+                    smaller.push(NBTTag::Compound(replaced));
+                }
+            }
+        }
+        NBTTag::List(l) => {
+            for (i, value) in l.iter().enumerate() {
+                smaller.push(value.clone());
+                let mut removed = l.clone();
+                removed.remove(i);
+                smaller.push(NBTTag::List(removed));
+                for value in shrink(value) {
+                    // Shrinking may change the type of a container, which would make the list
+                    // invalid.
+                    if value.tag_id() == l.element_type() {
+                        let mut replaced = l.clone();
+                        replaced[i] = value;
+                        smaller.push(NBTTag::List(replaced));
+                    }
+                }
+            }
+        }
+        NBTTag::String(s) if !s.is_empty() => smaller.push(NBTTag::String("".into())),
+        NBTTag::ByteArray(v) if !v.is_empty() => smaller.push(NBTTag::ByteArray(vec![].into())),
+        NBTTag::IntArray(v) if !v.is_empty() => smaller.push(NBTTag::IntArray(vec![].into())),
+        NBTTag::LongArray(v) if !v.is_empty() => smaller.push(NBTTag::LongArray(vec![].into())),
+        _ => {}
+    }
+    smaller
+}
+
+/// Shrinks a tree for which the property does not hold, until no smaller tree fails.
+fn minimize(mut nbt: NBTTag, fails: impl Fn(&NBTTag) -> bool) -> NBTTag {
+    while let Some(smaller) = shrink(&nbt).into_iter().find(|v| fails(v)) {
+        nbt = smaller;
+    }
+    nbt
+}
+
+/// Returns true if the tree does not round-trip using the encoding.
+fn round_trip_fails(nbt: &NBTTag, encoding: &AnyEncoding) -> bool {
+    let mut encoding = encoding.clone();
+    match nbt.to_bytes(&mut encoding) {
+        Ok(buf) => NBTTag::read_slice(&buf, &mut encoding).ok().as_ref() != Some(nbt),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arbitrary::{minimize, round_trip_fails, Arbitrary, Rng, CASES};
+    use crate::encoding::{AnyEncoding, BigEndian, LittleEndian, NetworkLittleEndian};
+    use crate::{tag, NBTTag};
+
+    #[test]
+    fn test_round_trip() {
+        for encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            for seed in 0..CASES {
+                let nbt = NBTTag::arbitrary(&mut Rng::new(seed), 0);
+                if round_trip_fails(&nbt, &encoding) {
+                    let minimal = minimize(nbt, |v| round_trip_fails(v, &encoding));
+                    panic!(
+                        "{encoding:?} does not round-trip seed {seed}, minimal case: {minimal:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("a", vec![tag::Int(1), tag::Int(2)])
+                .with_string("b", "Zuri")
+                .with_int_array("c", vec![3])
+                .build(),
+        );
+        // Find the smallest tree that still contains the int 2.
+        let minimal = minimize(nbt, |v| {
+            v.flatten()
+                .iter()
+                .any(|(_, v)| matches!(v, NBTTag::Int(i) if i.0 == 2))
+        });
+        assert_eq!(minimal, NBTTag::Int(2.into()));
+    }
+}
@@ -0,0 +1,272 @@
+//! Buffer adapters that compute a CRC32 checksum over the bytes passing through them.
+//!
+//! [ChecksumBuf] wraps a [Buf] that NBT data is read from, and [ChecksumBufMut] wraps a [BufMut]
+//! that NBT data is written to. Both work with any encoding, and expose the checksum and the
+//! amount of bytes that passed through them afterwards. This allows storing NBT data along with a
+//! checksum to verify its integrity.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::{tag, NBTTag};
+//! # use zuri_nbt::checksum::{ChecksumBuf, ChecksumBufMut};
+//! # use zuri_nbt::encoding::BigEndian;
+//! let nbt = NBTTag::Compound(tag::Compound::builder().with_int("a", 1).build());
+//! let mut buf = ChecksumBufMut::new(Vec::new());
+//! nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+//! let checksum = buf.checksum();
+//! let data = buf.into_inner();
+//!
+//! let mut buf = ChecksumBuf::new(data.as_slice());
+//! NBTTag::read(&mut buf, &mut BigEndian::default()).unwrap();
+//! assert_eq!(buf.checksum(), checksum);
+//! ```
+use bytes::buf::UninitSlice;
+use bytes::{Buf, BufMut};
+
+/// The lookup table for the reflected CRC32 polynomial used by zlib, PNG and gzip.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// A running CRC32 checksum, as used by zlib, PNG and gzip.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Creates a new checksum over no bytes.
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Updates the checksum with the provided bytes.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.state =
+                CRC32_TABLE[((self.state ^ *b as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Returns the checksum over all bytes passed to [Self::update] so far.
+    pub fn sum(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// A [Buf] that computes a CRC32 checksum over all bytes read from the wrapped buffer.
+#[derive(Debug, Clone)]
+pub struct ChecksumBuf<B> {
+    inner: B,
+    crc: Crc32,
+    count: usize,
+}
+
+impl<B: Buf> ChecksumBuf<B> {
+    /// Wraps the provided buffer.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            crc: Crc32::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns the checksum over all bytes read so far.
+    pub fn checksum(&self) -> u32 {
+        self.crc.sum()
+    }
+
+    /// Returns the amount of bytes read so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns a reference to the wrapped buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Returns the wrapped buffer.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Buf> Buf for ChecksumBuf<B> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.inner.chunk()
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        self.count += cnt;
+        // The bytes being skipped may span multiple chunks of the wrapped buffer.
+        while cnt > 0 {
+            let chunk = self.inner.chunk();
+            let n = cnt.min(chunk.len());
+            if n == 0 {
+                // Let the wrapped buffer decide how to handle advancing past its end.
+                break;
+            }
+            self.crc.update(&chunk[..n]);
+            self.inner.advance(n);
+            cnt -= n;
+        }
+        self.inner.advance(cnt);
+    }
+}
+
+/// A [BufMut] that computes a CRC32 checksum over all bytes written to the wrapped buffer.
+#[derive(Debug, Clone)]
+pub struct ChecksumBufMut<B> {
+    inner: B,
+    crc: Crc32,
+    count: usize,
+}
+
+impl<B: BufMut> ChecksumBufMut<B> {
+    /// Wraps the provided buffer.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            crc: Crc32::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns the checksum over all bytes written so far.
+    pub fn checksum(&self) -> u32 {
+        self.crc.sum()
+    }
+
+    /// Returns the amount of bytes written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns a reference to the wrapped buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Returns the wrapped buffer.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+// SAFETY: All methods forward to the wrapped buffer, which upholds the contract of the trait.
+unsafe impl<B: BufMut> BufMut for ChecksumBufMut<B> {
+    fn remaining_mut(&self) -> usize {
+        self.inner.remaining_mut()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        if cnt > 0 {
+            let chunk = self.inner.chunk_mut();
+            let cnt = cnt.min(chunk.len());
+            // SAFETY: The caller guarantees that the first `cnt` bytes of the chunk returned by
+            // `chunk_mut` have been initialized. The chunk does not change until it is advanced.
+            let written = std::slice::from_raw_parts(chunk.as_mut_ptr(), cnt);
+            self.crc.update(written);
+        }
+        self.count += cnt;
+        self.inner.advance_mut(cnt);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        self.inner.chunk_mut()
+    }
+
+    fn put_slice(&mut self, src: &[u8]) {
+        self.crc.update(src);
+        self.count += src.len();
+        self.inner.put_slice(src);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Buf, BufMut};
+
+    use crate::checksum::{ChecksumBuf, ChecksumBufMut, Crc32};
+    use crate::encoding::{BigEndian, NetworkLittleEndian};
+    use crate::{tag, NBTTag};
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        assert_eq!(crc.sum(), 0);
+        crc.update(b"12345");
+        crc.update(b"6789");
+        assert_eq!(crc.sum(), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_checksum_bufs() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_int_array("data", vec![1, 2, 3])
+                .build(),
+        );
+        let mut buf = ChecksumBufMut::new(Vec::new());
+        nbt.write(&mut buf, &mut NetworkLittleEndian::default())
+            .unwrap();
+        buf.put_u8(0xFF);
+        let mut crc = Crc32::new();
+        crc.update(buf.get_ref());
+        assert_eq!(buf.checksum(), crc.sum());
+        assert_eq!(buf.count(), buf.get_ref().len());
+
+        let data = buf.into_inner();
+        let mut buf = ChecksumBuf::new(data.as_slice());
+        let read = NBTTag::read(&mut buf, &mut NetworkLittleEndian::default()).unwrap();
+        assert_eq!(read, nbt);
+        buf.advance(1);
+        assert_eq!(buf.checksum(), crc.sum());
+        assert_eq!(buf.count(), data.len());
+    }
+
+    #[test]
+    fn test_checksum_buf_chained() {
+        let mut full = Vec::new();
+        NBTTag::Compound(tag::Compound::builder().with_long("a", 7).build())
+            .write(&mut full, &mut BigEndian::default())
+            .unwrap();
+        let mut crc = Crc32::new();
+        crc.update(&full);
+
+        // Reads spanning multiple chunks are included in the checksum.
+        let (a, b) = full.split_at(5);
+        let mut buf = ChecksumBuf::new(a.chain(b));
+        NBTTag::read(&mut buf, &mut BigEndian::default()).unwrap();
+        assert_eq!(buf.checksum(), crc.sum());
+    }
+}
@@ -0,0 +1,223 @@
+//! Typed access to the item components used by Minecraft: Java Edition since 1.20.5.
+//!
+//! Items store their data in a compound of components, keyed by namespaced ids such as
+//! `minecraft:custom_name`. [ItemComponents] reads a handful of common components from such a
+//! compound into typed fields. Components that are not covered are left in the tag tree, and can
+//! still be accessed through it:
+//!  - `minecraft:custom_name` and `minecraft:lore`, which contain [Text].
+//!  - `minecraft:enchantments`, in both the format with a `levels` compound used before 1.21.5
+//!    and the flat format used afterwards.
+//!  - `minecraft:damage` and `minecraft:unbreakable`.
+//!
+//! Requires the `components` feature.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::components::ItemComponents;
+//! let item: zuri_nbt::NBTTag = r#"{
+//!     id: "minecraft:diamond_sword",
+//!     count: 1,
+//!     components: {
+//!         "minecraft:damage": 12,
+//!         "minecraft:enchantments": {levels: {"minecraft:sharpness": 5}},
+//!     },
+//! }"#
+//! .parse()
+//! .unwrap();
+//! let components = ItemComponents::from_item(&item).unwrap();
+//! assert_eq!(components.damage, Some(12));
+//! assert_eq!(components.enchantments[0].level, 5);
+//! ```
+use std::collections::HashMap;
+
+use crate::convert::{self, FromNbt};
+use crate::err::{NbtConvertError, PathPart};
+use crate::{tag, NBTTag};
+
+/// The key of the compound containing the components of an item.
+pub const COMPONENTS_KEY: &str = "components";
+
+/// A text component, as stored in the `minecraft:custom_name` and `minecraft:lore` components.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Text {
+    /// A text component stored as a string. Before 1.21.5 the string contains the text component
+    /// as JSON, while it contains plain text afterwards.
+    String(String),
+    /// A text component stored as NBT, used since 1.21.5.
+    Nbt(NBTTag),
+}
+
+/// An enchantment on an item, as stored in the `minecraft:enchantments` component.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Enchantment {
+    /// The id of the enchantment, such as `minecraft:sharpness`.
+    pub id: String,
+    /// The level of the enchantment.
+    pub level: i32,
+}
+
+/// The common components of an item. See the [module documentation](self) for the supported
+/// components.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemComponents {
+    /// The `minecraft:custom_name` component.
+    pub custom_name: Option<Text>,
+    /// The lines of the `minecraft:lore` component. Empty if the component is missing.
+    pub lore: Vec<Text>,
+    /// The `minecraft:enchantments` component, sorted by id. Empty if the component is missing.
+    pub enchantments: Vec<Enchantment>,
+    /// The `minecraft:damage` component.
+    pub damage: Option<i32>,
+    /// Whether the `minecraft:unbreakable` component is present.
+    pub unbreakable: bool,
+}
+
+impl ItemComponents {
+    /// Reads the components of an item, which are stored in the [COMPONENTS_KEY] compound. An
+    /// item without components results in the default value.
+    ///
+    /// Returns an error if the item or any of the supported components has an unexpected type,
+    /// containing the path to the offending tag.
+    pub fn from_item(item: &NBTTag) -> Result<Self, NbtConvertError> {
+        let item = convert::compound(item)?;
+        match item.get(COMPONENTS_KEY) {
+            Some(components) => convert::compound(components)
+                .and_then(Self::from_compound)
+                .map_err(|err| err.prepend(PathPart::MapKey(COMPONENTS_KEY.to_string()))),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads a compound of components. See [Self::from_item].
+    pub fn from_compound(components: &tag::Compound) -> Result<Self, NbtConvertError> {
+        let enchantments: Option<Enchantments> =
+            convert::optional_field(components, "minecraft:enchantments")?;
+        Ok(Self {
+            custom_name: convert::optional_field(components, "minecraft:custom_name")?,
+            lore: convert::optional_field(components, "minecraft:lore")?.unwrap_or_default(),
+            enchantments: enchantments.map(|v| v.0).unwrap_or_default(),
+            damage: convert::optional_field(components, "minecraft:damage")?,
+            unbreakable: components.contains_key("minecraft:unbreakable"),
+        })
+    }
+}
+
+/// Reads a text component stored either as a string, or as a compound or list.
+impl FromNbt for Text {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        match tag {
+            NBTTag::Compound(_) | NBTTag::List(_) => Ok(Text::Nbt(tag.clone())),
+            _ => String::from_nbt(tag).map(Text::String),
+        }
+    }
+}
+
+/// The enchantments in the `minecraft:enchantments` component, sorted by id.
+struct Enchantments(Vec<Enchantment>);
+
+impl FromNbt for Enchantments {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        let component = convert::compound(tag)?;
+        // Before 1.21.5, the levels were stored in a nested compound next to other options.
+        let levels: HashMap<String, i32> = match convert::optional_field(component, "levels")? {
+            Some(levels) => levels,
+            None => HashMap::from_nbt(tag)?,
+        };
+        let mut enchantments: Vec<_> = levels
+            .into_iter()
+            .map(|(id, level)| Enchantment { id, level })
+            .collect();
+        enchantments.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(Self(enchantments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{Enchantment, ItemComponents, Text};
+    use crate::err::TypeError;
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_item_components() {
+        let item: NBTTag = r#"{
+            id: "minecraft:diamond_sword",
+            count: 1,
+            components: {
+                "minecraft:custom_name": {text: "Zuri", italic: 0b},
+                "minecraft:lore": ['"first"', '{"text":"second"}'],
+                "minecraft:enchantments": {
+                    levels: {"minecraft:sharpness": 5, "minecraft:looting": 3},
+                    show_in_tooltip: 0b,
+                },
+                "minecraft:damage": 12,
+                "minecraft:unbreakable": {},
+            },
+        }"#
+        .parse()
+        .unwrap();
+        let components = ItemComponents::from_item(&item).unwrap();
+        assert_eq!(
+            components,
+            ItemComponents {
+                custom_name: Some(Text::Nbt(
+                    tag::Compound::builder()
+                        .with_string("text", "Zuri")
+                        .with_byte("italic", 0)
+                        .build()
+                        .into()
+                )),
+                lore: vec![
+                    Text::String("\"first\"".to_string()),
+                    Text::String("{\"text\":\"second\"}".to_string()),
+                ],
+                enchantments: vec![
+                    Enchantment {
+                        id: "minecraft:looting".to_string(),
+                        level: 3
+                    },
+                    Enchantment {
+                        id: "minecraft:sharpness".to_string(),
+                        level: 5
+                    },
+                ],
+                damage: Some(12),
+                unbreakable: true,
+            }
+        );
+
+        // The flat enchantment format used since 1.21.5.
+        let item: NBTTag = "{components:{\"minecraft:enchantments\":{\"minecraft:mending\":1}}}"
+            .parse()
+            .unwrap();
+        let components = ItemComponents::from_item(&item).unwrap();
+        assert_eq!(components.enchantments[0].id, "minecraft:mending");
+        assert!(!components.unbreakable);
+
+        let item: NBTTag = "{id:\"minecraft:stone\"}".parse().unwrap();
+        assert_eq!(
+            ItemComponents::from_item(&item).unwrap(),
+            ItemComponents::default()
+        );
+    }
+
+    #[test]
+    fn test_item_components_errors() {
+        let item: NBTTag = "{components:{\"minecraft:lore\":[1,2]}}".parse().unwrap();
+        let err = ItemComponents::from_item(&item).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::String, NBTTagType::Int)
+        );
+        assert_eq!(err.path.to_string(), "components.minecraft:lore[0]");
+
+        let item: NBTTag = "{components:{\"minecraft:enchantments\":{levels:{a:1b}}}}"
+            .parse()
+            .unwrap();
+        let err = ItemComponents::from_item(&item).unwrap_err();
+        assert_eq!(
+            err.path.to_string(),
+            "components.minecraft:enchantments.levels.a"
+        );
+    }
+}
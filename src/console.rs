@@ -0,0 +1,193 @@
+//! Reading of the save files used by Minecraft: Legacy Console Edition.
+//!
+//! Legacy Console Edition stores an entire world in a single `savegame.dat` archive. The NBT files
+//! in the archive, such as `level.dat`, use the [BigEndian] encoding. The archive itself differs
+//! from the formats used by the other editions in the following ways:
+//!  - The archive is compressed as a whole. The compression algorithm depends on the console
+//!    (LZX on the Xbox 360, zlib on most others) and is not handled by this crate: the data
+//!    passed to [ConsoleSave::parse] must already be decompressed.
+//!  - The archive starts with a 12-byte header containing the offset of the file index and the
+//!    number of files in it, followed by the oldest compatible and current save versions.
+//!  - Each entry in the file index is 144 bytes long: a file name of 64 big endian UTF-16 code
+//!    units padded with zeroes, followed by the length and offset of the file and a timestamp.
+//!  - All integers in the header and file index are big endian.
+//!
+//! ## Example
+//! ```no_run
+//! # use zuri_nbt::console::ConsoleSave;
+//! # let decompressed: Vec<u8> = Vec::new();
+//! let save = ConsoleSave::parse(&decompressed).expect("invalid save");
+//! let level = save.read_nbt("level.dat").expect("missing level.dat");
+//! ```
+use crate::decode::Res;
+use crate::encoding::BigEndian;
+use crate::err::{ErrorPath, PathPart, ReadError};
+use crate::NBTTag;
+
+/// The size of the header at the start of an archive.
+const HEADER_LEN: usize = 12;
+/// The size of a single entry in the file index of an archive.
+const ENTRY_LEN: usize = 144;
+/// The size of the file name in an entry in the file index.
+const NAME_LEN: usize = 128;
+
+/// A decompressed Legacy Console Edition `savegame.dat` archive.
+#[derive(Debug, Clone)]
+pub struct ConsoleSave<'a> {
+    /// The oldest save version that is able to read the archive.
+    pub oldest_version: u16,
+    /// The save version that the archive was written with.
+    pub version: u16,
+    files: Vec<ConsoleFile<'a>>,
+}
+
+/// A single file in a [ConsoleSave].
+#[derive(Debug, Clone)]
+pub struct ConsoleFile<'a> {
+    /// The name of the file, such as `level.dat` or `r.0.0.mcr`.
+    pub name: String,
+    /// The time at which the file was last modified, as stored by the console.
+    pub timestamp: u64,
+    /// The contents of the file.
+    pub data: &'a [u8],
+}
+
+impl<'a> ConsoleSave<'a> {
+    /// Parses the header and file index of a decompressed archive.
+    ///
+    /// The contents of the files are not read. Returns an error if the header, file index or any
+    /// of the files lie outside the data.
+    pub fn parse(data: &'a [u8]) -> Res<Self> {
+        let header = data
+            .get(..HEADER_LEN)
+            .ok_or_else(|| ErrorPath::new(ReadError::UnexpectedEOF))?;
+        let index_offset = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let file_count = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+
+        let index = file_count
+            .checked_mul(ENTRY_LEN)
+            .and_then(|len| data.get(index_offset..index_offset.checked_add(len)?))
+            .ok_or_else(|| ErrorPath::new(ReadError::UnexpectedEOF))?;
+        let files = index
+            .chunks_exact(ENTRY_LEN)
+            .enumerate()
+            .map(|(i, entry)| {
+                ConsoleFile::parse(data, entry).map_err(|err| err.prepend(PathPart::Element(i)))
+            })
+            .collect::<Res<_>>()?;
+
+        Ok(Self {
+            oldest_version: u16::from_be_bytes(header[8..10].try_into().unwrap()),
+            version: u16::from_be_bytes(header[10..12].try_into().unwrap()),
+            files,
+        })
+    }
+
+    /// Returns all files in the archive, in the order of the file index.
+    pub fn files(&self) -> &[ConsoleFile<'a>] {
+        &self.files
+    }
+
+    /// Returns the file in the archive with the provided name, if present.
+    pub fn file(&self, name: &str) -> Option<&ConsoleFile<'a>> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// Reads the NBT data in the file with the provided name, if present.
+    pub fn read_nbt(&self, name: &str) -> Option<Res<NBTTag>> {
+        self.file(name).map(|f| f.read_nbt())
+    }
+}
+
+impl<'a> ConsoleFile<'a> {
+    /// Parses an entry in the file index of an archive.
+    fn parse(data: &'a [u8], entry: &[u8]) -> Res<Self> {
+        let units: Vec<u16> = entry[..NAME_LEN]
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        let name = String::from_utf16(&units)
+            .map_err(|_| ErrorPath::new(ReadError::Custom("invalid file name".to_string())))?;
+
+        let len = u32::from_be_bytes(entry[NAME_LEN..NAME_LEN + 4].try_into().unwrap()) as usize;
+        let offset =
+            u32::from_be_bytes(entry[NAME_LEN + 4..NAME_LEN + 8].try_into().unwrap()) as usize;
+        let data = offset
+            .checked_add(len)
+            .and_then(|end| data.get(offset..end))
+            .ok_or_else(|| ErrorPath::new(ReadError::UnexpectedEOF))?;
+
+        Ok(Self {
+            name,
+            timestamp: u64::from_be_bytes(entry[NAME_LEN + 8..ENTRY_LEN].try_into().unwrap()),
+            data,
+        })
+    }
+
+    /// Reads the contents of the file as NBT data using the [BigEndian] encoding.
+    pub fn read_nbt(&self) -> Res<NBTTag> {
+        NBTTag::read(&mut &*self.data, &mut BigEndian::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::console::ConsoleSave;
+    use crate::encoding::BigEndian;
+    use crate::err::ReadError;
+    use crate::{tag, NBTTag};
+
+    /// Builds an archive containing the provided files.
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0; 12];
+        let mut index = Vec::new();
+        for (name, contents) in files {
+            let mut entry = vec![0; 128];
+            for (i, unit) in name.encode_utf16().enumerate() {
+                entry[i * 2..i * 2 + 2].copy_from_slice(&unit.to_be_bytes());
+            }
+            entry.extend((contents.len() as u32).to_be_bytes());
+            entry.extend((data.len() as u32).to_be_bytes());
+            entry.extend(1700000000u64.to_be_bytes());
+            index.extend(entry);
+            data.extend(*contents);
+        }
+        let index_offset = data.len() as u32;
+        data[0..4].copy_from_slice(&index_offset.to_be_bytes());
+        data[4..8].copy_from_slice(&(files.len() as u32).to_be_bytes());
+        data[8..10].copy_from_slice(&2u16.to_be_bytes());
+        data[10..12].copy_from_slice(&9u16.to_be_bytes());
+        data.extend(index);
+        data
+    }
+
+    #[test]
+    fn test_parse() {
+        let level = NBTTag::Compound(tag::Compound::builder().with_long("RandomSeed", 7).build());
+        let mut level_bytes = Vec::new();
+        level
+            .write(&mut level_bytes, &mut BigEndian::default())
+            .unwrap();
+        let data = archive(&[("r.0.0.mcr", &[1, 2, 3]), ("level.dat", &level_bytes)]);
+
+        let save = ConsoleSave::parse(&data).unwrap();
+        assert_eq!(save.oldest_version, 2);
+        assert_eq!(save.version, 9);
+        assert_eq!(save.files().len(), 2);
+
+        let region = save.file("r.0.0.mcr").unwrap();
+        assert_eq!(region.data, [1, 2, 3]);
+        assert_eq!(region.timestamp, 1700000000);
+        assert_eq!(save.read_nbt("level.dat").unwrap().unwrap(), level);
+        assert!(save.read_nbt("missing.dat").is_none());
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let data = archive(&[("level.dat", &[10, 0, 0, 0])]);
+        let err = ConsoleSave::parse(&data[..data.len() - 1]).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        assert!(ConsoleSave::parse(&[0; 4]).is_err());
+    }
+}
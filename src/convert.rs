@@ -0,0 +1,387 @@
+//! Hand-written conversion from NBT data into rust types using the [FromNbt] trait.
+//!
+//! This is a middle ground between accessing the tag tree directly and the `serde` or `derive`
+//! features: conversions are implemented by hand, while the helpers in this module keep track of
+//! the path to the offending tag when a conversion fails. The trait is implemented for the
+//! primitive types, [String], the array tags, [NBTTag] itself, [Vec]s of convertible types,
+//! which are read from list tags, [HashMap]s of convertible types, which are read from compound
+//! tags, and [Option]s of convertible types. The trait can also be derived for structs using the
+//! `derive` feature, under the name `NbtDeserialize`.
+//!
+//! Values can also be borrowed from a tag using the [FromNbtRef] trait, which is what
+//! [tag::Compound::get_as] uses to look up a single entry of a compound.
+//!
+//! ## Example
+//! ```
+//! use zuri_nbt::convert::{self, FromNbt};
+//! use zuri_nbt::err::NbtConvertError;
+//! use zuri_nbt::{tag, NBTTag};
+//!
+//! struct Player {
+//!     name: String,
+//!     scores: Vec<i32>,
+//! }
+//!
+//! impl FromNbt for Player {
+//!     fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+//!         let compound = convert::compound(tag)?;
+//!         Ok(Self {
+//!             name: convert::field(compound, "Name")?,
+//!             scores: convert::optional_field(compound, "Scores")?.unwrap_or_default(),
+//!         })
+//!     }
+//! }
+//!
+//! let nbt = NBTTag::Compound(tag::Compound::builder().with_string("Name", "Zuri").build());
+//! let player = Player::from_nbt(&nbt).unwrap();
+//! assert_eq!(player.name, "Zuri");
+//! assert!(player.scores.is_empty());
+//! ```
+use std::collections::HashMap;
+
+use crate::err::{ErrorPath, NbtConvertError, PathPart, TypeError};
+use crate::{tag, NBTTag, NBTTagType};
+
+/// A type that can be created from a tag.
+pub trait FromNbt: Sized {
+    /// Creates the value from a tag, returning an error if the tag does not have the expected
+    /// structure. The path of the error should point to the offending tag.
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError>;
+
+    /// Returns the value used by [field] if the entry is missing, or [None] if the entry is
+    /// required. By default, all entries are required.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+impl FromNbt for NBTTag {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        Ok(tag.clone())
+    }
+}
+
+/// Reads the elements of a list tag. Array tags are not accepted: use [tag::ByteArray],
+/// [tag::IntArray] or [tag::LongArray] to read those.
+impl<T: FromNbt> FromNbt for Vec<T> {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        let NBTTag::List(list) = tag else {
+            return Err(unexpected(NBTTagType::List, tag));
+        };
+        list.iter()
+            .enumerate()
+            .map(|(i, v)| T::from_nbt(v).map_err(|err| err.prepend(PathPart::Element(i))))
+            .collect()
+    }
+}
+
+/// Reads the entries of a compound tag, all of which must be convertible to the same type.
+impl<T: FromNbt> FromNbt for HashMap<String, T> {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        compound(tag)?
+            .iter()
+            .map(|(k, v)| {
+                T::from_nbt(v)
+                    .map(|v| (k.clone(), v))
+                    .map_err(|err| err.prepend(PathPart::MapKey(k.clone())))
+            })
+            .collect()
+    }
+}
+
+/// Reads the value of a tag that may be missing. Used as the type of a field, a missing entry is
+/// read as [None] rather than causing [TypeError::MissingTag].
+impl<T: FromNbt> FromNbt for Option<T> {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        T::from_nbt(tag).map(Some)
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl NBTTag {
+    /// Converts a compound tag of which all values have the same type into a map, such as a
+    /// compound of scores into a `HashMap<String, i32>`.
+    ///
+    /// Returns an error if the tag is not a compound, or if any of the values could not be
+    /// converted, containing the key of the offending value.
+    pub fn to_map_of<T: FromNbt>(&self) -> Result<HashMap<String, T>, NbtConvertError> {
+        HashMap::from_nbt(self)
+    }
+}
+
+/// A type that can be borrowed from a tag, such as a number or a reference to a string or to the
+/// contents of a container tag. Used by [tag::Compound::get_as].
+pub trait FromNbtRef<'a>: Sized {
+    /// Returns the value in a tag, or an error if the tag does not have the expected type.
+    fn from_nbt_ref(tag: &'a NBTTag) -> Result<Self, NbtConvertError>;
+}
+
+macro_rules! impl_from_nbt_ref {
+    ($(($typ:ty, $variant:ident, $from:expr)$(,)?)*) => {
+        $(impl_from_nbt_ref!($typ, $variant, $from);)*
+    };
+    ($typ:ty, $variant:ident, $from:expr) => {
+        impl<'a> FromNbtRef<'a> for $typ {
+            fn from_nbt_ref(tag: &'a NBTTag) -> Result<Self, NbtConvertError> {
+                match tag {
+                    NBTTag::$variant(v) => Ok(($from)(v)),
+                    _ => Err(unexpected(NBTTagType::$variant, tag)),
+                }
+            }
+        }
+    };
+}
+
+impl_from_nbt_ref!(
+    (bool, Byte, |v: &tag::Byte| v.0 != 0),
+    (u8, Byte, |v: &tag::Byte| v.0),
+    (i8, Byte, |v: &tag::Byte| v.as_i8()),
+    (i16, Short, |v: &tag::Short| v.0),
+    (i32, Int, |v: &tag::Int| v.0),
+    (i64, Long, |v: &tag::Long| v.0),
+    (f32, Float, |v: &tag::Float| v.0),
+    (f64, Double, |v: &tag::Double| v.0),
+    (String, String, |v: &tag::String| v.0.clone()),
+    (&'a str, String, |v: &'a tag::String| v.0.as_str()),
+    (&'a tag::Compound, Compound, |v| v),
+    (&'a tag::List, List, |v| v),
+    (&'a tag::ByteArray, ByteArray, |v| v),
+    (&'a tag::IntArray, IntArray, |v| v),
+    (&'a tag::LongArray, LongArray, |v| v),
+);
+
+macro_rules! impl_from_nbt {
+    (clone: $($typ:ty),* $(,)?) => {$(
+        impl FromNbt for $typ {
+            fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+                <&Self>::from_nbt_ref(tag).cloned()
+            }
+        }
+    )*};
+    ($($typ:ty),* $(,)?) => {$(
+        impl FromNbt for $typ {
+            fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+                Self::from_nbt_ref(tag)
+            }
+        }
+    )*};
+}
+
+// The values are converted using the borrowing conversions above.
+impl_from_nbt!(bool, u8, i8, i16, i32, i64, f32, f64, String);
+impl_from_nbt!(clone: tag::Compound, tag::ByteArray, tag::IntArray, tag::LongArray);
+
+impl tag::Compound {
+    /// Returns the value of the entry with the provided key, or [None] if the entry is missing or
+    /// of another type:
+    /// ```
+    /// use zuri_nbt::tag;
+    ///
+    /// let compound = tag::Compound::builder().with_int("DataVersion", 3465).build();
+    /// assert_eq!(compound.get_as::<i32>("DataVersion"), Some(3465));
+    /// assert_eq!(compound.get_as::<&str>("DataVersion"), None);
+    /// ```
+    ///
+    /// Use [Self::get_as_result] to find out why the value could not be returned.
+    pub fn get_as<'a, T: FromNbtRef<'a>>(&'a self, key: &str) -> Option<T> {
+        self.get(key).and_then(|tag| T::from_nbt_ref(tag).ok())
+    }
+
+    /// Returns the value of the entry with the provided key. Returns [TypeError::MissingTag] if
+    /// the entry is missing, and [TypeError::UnexpectedTag] if it is of another type, both with
+    /// the key as the path.
+    pub fn get_as_result<'a, T: FromNbtRef<'a>>(&'a self, key: &str) -> Result<T, NbtConvertError> {
+        let path = || PathPart::MapKey(key.to_string());
+        let tag = self
+            .get(key)
+            .ok_or_else(|| ErrorPath::new(TypeError::MissingTag).prepend(path()))?;
+        T::from_nbt_ref(tag).map_err(|err| err.prepend(path()))
+    }
+}
+
+/// Returns the compound in a tag, or an error if the tag is of another type.
+pub fn compound(tag: &NBTTag) -> Result<&tag::Compound, NbtConvertError> {
+    match tag {
+        NBTTag::Compound(v) => Ok(v),
+        _ => Err(unexpected(NBTTagType::Compound, tag)),
+    }
+}
+
+/// Converts the entry with the provided key of a compound, prepending the key to the path of any
+/// error. Returns [TypeError::MissingTag] if the entry is missing, unless the type provides a
+/// value for it using [FromNbt::from_missing].
+pub fn field<T: FromNbt>(compound: &tag::Compound, key: &str) -> Result<T, NbtConvertError> {
+    match compound.get(key) {
+        Some(v) => T::from_nbt(v),
+        None => T::from_missing().ok_or_else(|| ErrorPath::new(TypeError::MissingTag)),
+    }
+    .map_err(|err| err.prepend(PathPart::MapKey(key.to_string())))
+}
+
+/// Converts the entry with the provided key of a compound if it is present, prepending the key to
+/// the path of any error.
+pub fn optional_field<T: FromNbt>(
+    compound: &tag::Compound,
+    key: &str,
+) -> Result<Option<T>, NbtConvertError> {
+    compound
+        .get(key)
+        .map(T::from_nbt)
+        .transpose()
+        .map_err(|err| err.prepend(PathPart::MapKey(key.to_string())))
+}
+
+fn unexpected(expected: NBTTagType, found: &NBTTag) -> NbtConvertError {
+    ErrorPath::new(TypeError::UnexpectedTag(expected, found.tag_type()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::convert::{self, FromNbt};
+    use crate::err::{NbtConvertError, TypeError};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        id: String,
+        count: i8,
+        damage: Option<i16>,
+    }
+
+    impl FromNbt for Item {
+        fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+            let compound = convert::compound(tag)?;
+            Ok(Self {
+                id: convert::field(compound, "id")?,
+                count: convert::field(compound, "Count")?,
+                damage: convert::optional_field(compound, "Damage")?,
+            })
+        }
+    }
+
+    fn item(id: &str, count: u8) -> tag::Compound {
+        tag::Compound::builder()
+            .with_string("id", id)
+            .with_byte("Count", count)
+            .build()
+    }
+
+    #[test]
+    fn test_from_nbt() {
+        let mut sword = item("minecraft:iron_sword", 1);
+        sword.insert("Damage".to_string(), NBTTag::Short(20.into()));
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("Inventory", vec![item("minecraft:stone", 255), sword])
+                .build(),
+        );
+        let compound = convert::compound(&nbt).unwrap();
+        let items: Vec<Item> = convert::field(compound, "Inventory").unwrap();
+        assert_eq!(
+            items,
+            [
+                Item {
+                    id: "minecraft:stone".to_string(),
+                    count: -1,
+                    damage: None,
+                },
+                Item {
+                    id: "minecraft:iron_sword".to_string(),
+                    count: 1,
+                    damage: Some(20),
+                },
+            ]
+        );
+        assert_eq!(NBTTag::from_nbt(&nbt), Ok(nbt.clone()));
+        assert_eq!(
+            convert::field::<Option<i32>>(compound, "Selected"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_to_map_of() {
+        let scores: NBTTag = "{Zuri:12,Steve:-3}".parse().unwrap();
+        let map = scores.to_map_of::<i32>().unwrap();
+        assert_eq!(
+            map,
+            HashMap::from([("Zuri".to_string(), 12), ("Steve".to_string(), -3)])
+        );
+
+        let mixed: NBTTag = "{Zuri:12,Steve:\"-3\"}".parse().unwrap();
+        let err = mixed.to_map_of::<i32>().unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Int, NBTTagType::String)
+        );
+        assert_eq!(err.path.to_string(), "Steve");
+
+        let err = NBTTag::Int(1.into()).to_map_of::<i32>().unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Compound, NBTTagType::Int)
+        );
+    }
+
+    #[test]
+    fn test_get_as() {
+        let compound = tag::Compound::builder()
+            .with_int("DataVersion", 3465)
+            .with_string("id", "minecraft:stone")
+            .with_long_array("Heightmap", vec![1, 2])
+            .build();
+
+        assert_eq!(compound.get_as::<i32>("DataVersion"), Some(3465));
+        assert_eq!(compound.get_as::<&str>("id"), Some("minecraft:stone"));
+        assert_eq!(
+            compound.get_as::<String>("id"),
+            Some("minecraft:stone".to_string())
+        );
+        let heightmap = compound.get_as::<&tag::LongArray>("Heightmap").unwrap();
+        assert_eq!(heightmap.0, [1, 2]);
+
+        assert_eq!(compound.get_as::<i64>("DataVersion"), None);
+        let err = compound.get_as_result::<i64>("DataVersion").unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Long, NBTTagType::Int)
+        );
+        assert_eq!(err.path.to_string(), "DataVersion");
+
+        assert_eq!(compound.get_as::<&tag::Compound>("Level"), None);
+        let err = compound
+            .get_as_result::<&tag::Compound>("Level")
+            .unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.to_string(), "Level");
+    }
+
+    #[test]
+    fn test_from_nbt_errors() {
+        let mut invalid = item("minecraft:stone", 1);
+        invalid.insert("Damage".to_string(), NBTTag::Int(20.into()));
+        let nbt = NBTTag::List(vec![item("minecraft:stone", 1), invalid].into());
+        let err = Vec::<Item>::from_nbt(&nbt).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Short, NBTTagType::Int)
+        );
+        assert_eq!(err.path.to_string(), "[1].Damage");
+
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_string("id", "a").build());
+        let err = Item::from_nbt(&nbt).unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.to_string(), "Count");
+
+        let err = Vec::<i32>::from_nbt(&NBTTag::IntArray(vec![1].into())).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::List, NBTTagType::IntArray)
+        );
+    }
+}
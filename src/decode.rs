@@ -1,11 +1,24 @@
 //! See [Reader].
-use crate::err::{ErrorPath, PathPart, ReadError};
+use crate::err::{ErrorPath, Path, PathPart, ReadError};
+use crate::half;
 use bytes::Buf;
 use std::mem;
 
 /// A short notation for the result type used in the [Reader].
 pub type Res<T> = Result<T, ErrorPath<ReadError>>;
 
+/// A function that reads the payload of a custom tag, returning the bytes stored in its
+/// [tag::Custom](crate::tag::Custom). See [Reader::custom_tag_reader].
+pub type CustomTagReader = dyn Fn(&mut dyn Buf) -> Result<Vec<u8>, ReadError> + Send + Sync;
+
+/// The maximum depth to which compound and list tags may be nested while reading, unless a reader
+/// sets another limit. This is the same limit as used by Minecraft: Java Edition. See
+/// [Reader::max_depth].
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// The amount of array elements read between two checks of [Reader::cancelled].
+const CANCEL_CHECK_INTERVAL: usize = 1024;
+
 /// A trait that can be implemented to alter how basic NBT types are read.
 ///
 /// All the implemented methods must not panic.
@@ -28,8 +41,86 @@ pub trait Reader {
     /// Reads a 64-bit floating point number.
     fn f64(&mut self, buf: &mut impl Buf) -> Res<f64>;
 
+    /// Reads a 128-bit signed integer stored as two 64-bit signed integers, as written by
+    /// [Writer::write_i128](crate::encode::Writer::write_i128). The most significant half is read
+    /// first, and both halves are read using [Self::i64].
+    ///
+    /// This is not an NBT type, but a convention for storing 128-bit values, such as in a pair of
+    /// long tags or a long array of two elements.
+    fn i128(&mut self, buf: &mut impl Buf) -> Res<i128> {
+        let high = self.i64(buf)?;
+        let low = self.i64(buf)?;
+        Ok(((high as i128) << 64) | (low as u64 as i128))
+    }
+
+    /// Reads a half-precision floating point number stored as the bits of a 16-bit signed
+    /// integer, as written by [Writer::write_f16](crate::encode::Writer::write_f16). The bits are
+    /// read using [Self::i16].
+    ///
+    /// This is not an NBT type, but a convention for storing floating point numbers compactly in
+    /// short tags. The conversion into an `f32` is exact.
+    fn f16(&mut self, buf: &mut impl Buf) -> Res<f32> {
+        Ok(half::to_f32(self.i16(buf)? as u16))
+    }
+
+    /// Returns the maximum amount of entries a compound tag may contain while reading.
+    ///
+    /// By default, compound tags may contain any amount of entries.
+    fn max_compound_entries(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the maximum depth to which compound and list tags may be nested while reading,
+    /// where the root tag is at depth one. Reading more deeply nested tags fails with
+    /// [ReadError::TooDeep].
+    ///
+    /// Tags are read without recursion, so deeply nested data cannot overflow the stack and the
+    /// limit is purely a policy choice. By default, the limit is [DEFAULT_MAX_DEPTH].
+    fn max_depth(&self) -> usize {
+        DEFAULT_MAX_DEPTH
+    }
+
+    /// Returns the maximum length in bytes of strings, including the names of tags, while reading.
+    /// Longer strings fail with [ReadError::SeqLengthViolation] before any memory is allocated
+    /// for them.
+    ///
+    /// By default, strings are only limited by the maximum length the encoding can represent.
+    fn max_string_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns whether a string with a negative length is read as an empty string instead of
+    /// failing with [ReadError::SeqLengthViolation]. Only applies to encodings that store the
+    /// length of a string as a signed 16-bit integer.
+    ///
+    /// By default, negative string lengths are rejected.
+    fn lenient_string_lengths(&self) -> bool {
+        false
+    }
+
+    /// Returns whether reading should be aborted. Checked periodically while reading lists,
+    /// compounds and arrays, which then fail with [ReadError::Cancelled].
+    ///
+    /// By default, reading is never cancelled.
+    fn cancelled(&self) -> bool {
+        false
+    }
+
+    /// Returns an error if reading has been cancelled. See [Self::cancelled].
+    fn check_cancelled(&self) -> Res<()> {
+        if self.cancelled() {
+            return Err(ErrorPath::new(ReadError::Cancelled));
+        }
+        Ok(())
+    }
+
     /// Reads the NBT `end` tag, which indicates the end of a compound tag.
+    ///
+    /// Returns [ReadError::MissingEndTag] if the buffer is empty.
     fn end(&mut self, buf: &mut impl Buf) -> Res<()> {
+        if !buf.has_remaining() {
+            return Err(ErrorPath::new(ReadError::MissingEndTag));
+        }
         let t = self.u8(buf)?;
         if t != 0 {
             return Err(ErrorPath::new(ReadError::UnexpectedTag(
@@ -40,29 +131,106 @@ pub trait Reader {
         Ok(())
     }
 
-    /// Reads a variable-length string.
-    fn string(&mut self, buf: &mut impl Buf) -> Res<String> {
+    /// Reads a fixed amount of bytes at once. Used for the contents of strings and byte arrays.
+    fn bytes(&mut self, buf: &mut impl Buf, len: usize) -> Res<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.bytes_into(buf, len, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a fixed amount of bytes at once into the provided vector, replacing its contents.
+    ///
+    /// The allocation of the vector is reused, which avoids allocating for every read when many
+    /// small tags are read in a loop.
+    fn bytes_into(&mut self, buf: &mut impl Buf, len: usize, out: &mut Vec<u8>) -> Res<()> {
+        if buf.remaining() < len {
+            return Err(ErrorPath::new_with_path(
+                ReadError::UnexpectedEOF,
+                Path::from_single(PathPart::Element(buf.remaining())),
+            ));
+        }
+        out.clear();
+        out.resize(len, 0);
+        buf.copy_to_slice(out);
+        Ok(())
+    }
+
+    /// Returns whether the lengths of strings and arrays are read as 64-bit integers. See the
+    /// `extended_lengths` option of the encodings in [crate::encoding].
+    ///
+    /// By default, the standard lengths are read.
+    fn reads_extended_lengths(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the root compound tag may end at the end of the buffer instead of with an
+    /// `END` tag. See the `omit_root_end` option of the encodings in [crate::encoding].
+    ///
+    /// By default, the `END` tag is required.
+    fn reads_omitted_root_end(&self) -> bool {
+        false
+    }
+
+    /// Returns the function used to read the payload of tags with the provided id, which is not
+    /// the id of a standard tag type. See the `custom_tag` option of the encodings in
+    /// [crate::encoding].
+    ///
+    /// By default, no custom tags are supported, and reading them results in
+    /// [ReadError::UnknownTagType].
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        let _ = id;
+        None
+    }
+
+    /// Reads the length prefix of a variable-length string.
+    fn string_len(&mut self, buf: &mut impl Buf) -> Res<usize> {
+        if self.reads_extended_lengths() {
+            return extended_len(self, buf);
+        }
         let len = self.i16(buf)?;
         if len < 0 {
+            if self.lenient_string_lengths() {
+                return Ok(0);
+            }
             return Err(ErrorPath::new(ReadError::SeqLengthViolation(
                 i16::MAX as usize,
                 len as usize,
             )));
         }
+        Ok(len as usize)
+    }
 
-        let mut str_buf = Vec::with_capacity(len as usize);
-        for i in 0..len {
-            str_buf.push(
-                self.u8(buf)
-                    .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
-            );
-        }
+    /// Reads a variable-length string.
+    fn string(&mut self, buf: &mut impl Buf) -> Res<String> {
+        let len = checked_string_len(self, buf)?;
+        let str_buf = self.bytes(buf, len)?;
+        self.decode_string(str_buf)
+    }
 
-        String::from_utf8(str_buf).map_err(|err| ErrorPath::new(ReadError::from(err)))
+    /// Reads a variable-length string into the provided string, replacing its contents.
+    ///
+    /// The allocation of the string is reused where the string encoding allows it, like with
+    /// [Self::bytes_into]. The string is left empty if an error occurs.
+    fn string_into(&mut self, buf: &mut impl Buf, out: &mut String) -> Res<()> {
+        let len = checked_string_len(self, buf)?;
+        let mut bytes = mem::take(out).into_bytes();
+        self.bytes_into(buf, len, &mut bytes)?;
+        *out = self.decode_string(bytes)?;
+        Ok(())
     }
 
-    /// Reads variable-length array of 8-bit unsigned integers.
-    fn u8_vec(&mut self, buf: &mut impl Buf) -> Res<Vec<u8>> {
+    /// Converts the bytes of a string read by [Self::string] into a string.
+    ///
+    /// By default, the bytes are expected to be valid UTF-8.
+    fn decode_string(&mut self, bytes: Vec<u8>) -> Res<String> {
+        String::from_utf8(bytes).map_err(|err| ErrorPath::new(ReadError::from(err)))
+    }
+
+    /// Reads the length prefix of a variable-length array.
+    fn array_len(&mut self, buf: &mut impl Buf) -> Res<usize> {
+        if self.reads_extended_lengths() {
+            return extended_len(self, buf);
+        }
         let len = self.i32(buf)?;
         if len < 0 {
             return Err(ErrorPath::new(ReadError::SeqLengthViolation(
@@ -70,33 +238,44 @@ pub trait Reader {
                 len as usize,
             )));
         }
+        Ok(len as usize)
+    }
 
-        let mut vec_buf = Vec::with_capacity(len as usize);
-        for i in 0..len {
-            vec_buf.push(
-                self.u8(buf)
-                    .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
-            );
-        }
+    /// Reads variable-length array of 8-bit unsigned integers.
+    fn u8_vec(&mut self, buf: &mut impl Buf) -> Res<Vec<u8>> {
+        let mut vec = Vec::new();
+        self.u8_vec_into(buf, &mut vec)?;
+        Ok(vec)
+    }
 
-        Ok(vec_buf)
+    /// Reads variable-length array of 8-bit unsigned integers into the provided vector, replacing
+    /// its contents. The allocation of the vector is reused, like with [Self::bytes_into].
+    fn u8_vec_into(&mut self, buf: &mut impl Buf, out: &mut Vec<u8>) -> Res<()> {
+        let len = self.array_len(buf)?;
+        self.check_cancelled()?;
+        self.bytes_into(buf, len, out)
+    }
+
+    /// Reads variable-length array of 8-bit signed integers, the way Minecraft: Java Edition
+    /// interprets byte arrays. The array is read using [Self::u8_vec], keeping the bits of every
+    /// element as-is.
+    fn i8_vec(&mut self, buf: &mut impl Buf) -> Res<Vec<i8>> {
+        Ok(self.u8_vec(buf)?.into_iter().map(|v| v as i8).collect())
     }
 
     /// Reads variable-length array of 32-bit signed integers.
     fn i32_vec(&mut self, buf: &mut impl Buf) -> Res<Vec<i32>> {
-        let len = self.i32(buf)?;
-        if len < 0 {
-            return Err(ErrorPath::new(ReadError::SeqLengthViolation(
-                i32::MAX as usize,
-                len as usize,
-            )));
-        }
-
-        let mut vec_buf = Vec::with_capacity(len as usize);
+        let len = self.array_len(buf)?;
+        // Every element takes up at least one byte, which bounds the allocation for lengths that
+        // exceed the data.
+        let mut vec_buf = Vec::with_capacity(len.min(buf.remaining()));
         for i in 0..len {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                self.check_cancelled()?;
+            }
             vec_buf.push(
                 self.i32(buf)
-                    .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
+                    .map_err(|err| err.prepend(PathPart::Element(i)))?,
             );
         }
 
@@ -105,22 +284,132 @@ pub trait Reader {
 
     /// Reads variable-length array of 64-bit signed integers.
     fn i64_vec(&mut self, buf: &mut impl Buf) -> Res<Vec<i64>> {
-        let len = self.i32(buf)?;
-        if len < 0 {
-            return Err(ErrorPath::new(ReadError::SeqLengthViolation(
-                i32::MAX as usize,
-                len as usize,
-            )));
-        }
-
-        let mut vec_buf = Vec::with_capacity(len as usize);
+        let len = self.array_len(buf)?;
+        // Every element takes up at least one byte, which bounds the allocation for lengths that
+        // exceed the data.
+        let mut vec_buf = Vec::with_capacity(len.min(buf.remaining()));
         for i in 0..len {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                self.check_cancelled()?;
+            }
             vec_buf.push(
                 self.i64(buf)
-                    .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
+                    .map_err(|err| err.prepend(PathPart::Element(i)))?,
             );
         }
 
         Ok(vec_buf)
     }
 }
+
+/// Reads the payload of a list tag, calling `f` to read each of its elements.
+///
+/// The element type and length of the list are read first and validated in the same way as when
+/// reading an [NBTTag](crate::NBTTag). `f` is then called once per element with the element type,
+/// and is expected to read exactly one element payload, such as by using
+/// [NBTTag::read_typed](crate::NBTTag::read_typed) or the methods of the [Reader]. The index of the
+/// element is prepended to the path of any error returned by `f`.
+///
+/// This allows reading lists directly into custom types, without first reading them into a tag.
+pub fn read_list_with<R: Reader, B: Buf, T>(
+    r: &mut R,
+    buf: &mut B,
+    mut f: impl FnMut(&mut R, &mut B, u8) -> Res<T>,
+) -> Res<Vec<T>> {
+    let (element_type, len) = crate::NBTTag::read_list_header(buf, r)?;
+    let mut elements = Vec::with_capacity(len.min(buf.remaining()));
+    for i in 0..len {
+        if i % CANCEL_CHECK_INTERVAL == 0 {
+            r.check_cancelled()?;
+        }
+        elements.push(f(r, buf, element_type).map_err(|err| err.prepend(PathPart::Element(i)))?);
+    }
+    Ok(elements)
+}
+
+/// Reads the length of a string, checking it against [Reader::max_string_len].
+fn checked_string_len(r: &mut (impl Reader + ?Sized), buf: &mut impl Buf) -> Res<usize> {
+    let len = r.string_len(buf)?;
+    match r.max_string_len() {
+        Some(max) if len > max => Err(ErrorPath::new(ReadError::SeqLengthViolation(max, len))),
+        _ => Ok(len),
+    }
+}
+
+/// Reads a 64-bit length prefix, as used when [Reader::reads_extended_lengths] is enabled.
+pub(crate) fn extended_len(r: &mut (impl Reader + ?Sized), buf: &mut impl Buf) -> Res<usize> {
+    let len = r.i64(buf)?;
+    usize::try_from(len).map_err(|_| {
+        ErrorPath::new(ReadError::SeqLengthViolation(
+            i64::MAX as usize,
+            len as usize,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::{read_list_with, Reader};
+    use crate::encoding::BigEndian;
+    use crate::err::{PathPart, ReadError};
+    use crate::{tag, NBTTag};
+
+    /// Accumulates the ints in a list without storing them.
+    #[derive(Default)]
+    struct Sum {
+        total: i64,
+        count: usize,
+    }
+
+    #[test]
+    fn test_read_list_with() {
+        let list = NBTTag::List(vec![tag::Int(3), tag::Int(-1), tag::Int(40)].into());
+        let mut buf = Vec::new();
+        list.write_payload(&mut buf, &mut BigEndian::default())
+            .unwrap();
+
+        let mut sum = Sum::default();
+        let mut r = BigEndian::default();
+        let elements = read_list_with(&mut r, &mut buf.as_slice(), |r, buf, element_type| {
+            assert_eq!(element_type, 3);
+            sum.total += r.i32(buf)? as i64;
+            sum.count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(elements.len(), 3);
+        assert_eq!((sum.total, sum.count), (42, 3));
+
+        // Elements can also be read as tags.
+        let elements = read_list_with(&mut r, &mut buf.as_slice(), |r, buf, element_type| {
+            NBTTag::read_typed(buf, element_type, r)
+        })
+        .unwrap();
+        assert_eq!(NBTTag::List(elements.into()), list);
+    }
+
+    #[test]
+    fn test_read_list_with_errors() {
+        let list = NBTTag::List(vec![tag::Int(1), tag::Int(2), tag::Int(3)].into());
+        let mut buf = Vec::new();
+        list.write_payload(&mut buf, &mut BigEndian::default())
+            .unwrap();
+
+        let err = read_list_with(
+            &mut BigEndian::default(),
+            &mut &buf[..buf.len() - 1],
+            |r, buf, _| r.i32(buf),
+        )
+        .unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        assert_eq!(err.path.0, [PathPart::Element(2)]);
+
+        let err = read_list_with(
+            &mut BigEndian::default(),
+            &mut [3, 0xFF, 0xFF, 0xFF, 0xFF].as_slice(),
+            |r, buf, _| r.i32(buf),
+        )
+        .unwrap_err();
+        assert!(matches!(err.inner, ReadError::SeqLengthViolation(_, _)));
+    }
+}
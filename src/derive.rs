@@ -0,0 +1,298 @@
+//! Conversion between rust types and NBT data using the [NbtSerialize] and [NbtDeserialize]
+//! derive macros. Requires the `derive` feature.
+//!
+//! [NbtDeserialize] is the same trait as [crate::convert::FromNbt], so derived types can be used
+//! with the helpers in [crate::convert], and the other way around.
+//!
+//! Deriving the traits for a struct with named fields converts it to and from a compound tag, with
+//! an entry for every field. This is a lightweight alternative to the `serde` feature that gives
+//! control over the tag types that integers are stored as. The following attributes are supported
+//! on fields:
+//!  - `#[nbt(rename = "...")]` stores the field under a different key, such as `Health`.
+//!  - `#[nbt(byte)]`, `#[nbt(short)]`, `#[nbt(int)]` and `#[nbt(long)]` store an integer or
+//!    boolean field as the provided tag type instead of the default for its type. Values that do
+//!    not fit are truncated when writing, and bytes and shorts are sign-extended when reading.
+//!
+//! Fields of type [Option] are omitted from the compound if they are [None], and are [None] if
+//! the entry is missing. All the other fields must be present.
+//!
+//! ## Example
+//! ```
+//! use zuri_nbt::derive::{NbtDeserialize, NbtSerialize};
+//! use zuri_nbt::NBTTag;
+//!
+//! #[derive(Debug, PartialEq, NbtSerialize, NbtDeserialize)]
+//! struct Player {
+//!     #[nbt(rename = "Name")]
+//!     name: String,
+//!     #[nbt(rename = "OnGround", byte)]
+//!     on_ground: bool,
+//!     #[nbt(rename = "Score")]
+//!     score: Option<i32>,
+//! }
+//!
+//! let player = Player { name: "Zuri".to_string(), on_ground: true, score: None };
+//! let nbt = player.to_nbt();
+//! assert_eq!(nbt.view().at("OnGround").byte(), Ok(1));
+//! assert_eq!(Player::from_nbt(&nbt), Ok(player));
+//! ```
+use std::collections::HashMap;
+
+pub use zuri_nbt_derive::{NbtDeserialize, NbtSerialize};
+
+/// A type that can be created from NBT data. This is an alias of [crate::convert::FromNbt], under
+/// which the trait is derived.
+pub use crate::convert::FromNbt as NbtDeserialize;
+use crate::{tag, NBTTag};
+
+/// A type that can be converted into NBT data.
+pub trait NbtSerialize {
+    /// Converts the value into a tag.
+    fn to_nbt(&self) -> NBTTag;
+
+    /// Converts the value into the tag stored for a struct field, or [None] if the field should be
+    /// omitted. By default, the field is always stored using [Self::to_nbt].
+    fn to_nbt_field(&self) -> Option<NBTTag> {
+        Some(self.to_nbt())
+    }
+}
+
+macro_rules! impl_scalar {
+    ($(($typ:ty, $variant:ident, $to:expr)$(,)?)*) => {
+        $(impl NbtSerialize for $typ {
+            fn to_nbt(&self) -> NBTTag {
+                NBTTag::$variant(($to)(self).into())
+            }
+        })*
+    };
+}
+
+impl_scalar!(
+    (bool, Byte, |v: &bool| *v as u8),
+    (u8, Byte, |v: &u8| *v),
+    (i8, Byte, |v: &i8| *v as u8),
+    (i16, Short, |v: &i16| *v),
+    (i32, Int, |v: &i32| *v),
+    (i64, Long, |v: &i64| *v),
+    (f32, Float, |v: &f32| *v),
+    (f64, Double, |v: &f64| *v),
+    (String, String, String::clone),
+    (tag::ByteArray, ByteArray, tag::ByteArray::clone),
+    (tag::IntArray, IntArray, tag::IntArray::clone),
+    (tag::LongArray, LongArray, tag::LongArray::clone),
+);
+
+impl NbtSerialize for NBTTag {
+    fn to_nbt(&self) -> NBTTag {
+        self.clone()
+    }
+}
+
+impl<T: NbtSerialize> NbtSerialize for Vec<T> {
+    fn to_nbt(&self) -> NBTTag {
+        NBTTag::List(self.iter().map(T::to_nbt).collect::<Vec<_>>().into())
+    }
+}
+
+impl<T: NbtSerialize> NbtSerialize for HashMap<String, T> {
+    fn to_nbt(&self) -> NBTTag {
+        NBTTag::Compound(
+            self.iter()
+                .map(|(k, v)| (k.clone(), v.to_nbt()))
+                .collect::<HashMap<_, _>>()
+                .into(),
+        )
+    }
+}
+
+/// [None] is omitted when used as a struct field, and is converted into an empty compound
+/// otherwise.
+impl<T: NbtSerialize> NbtSerialize for Option<T> {
+    fn to_nbt(&self) -> NBTTag {
+        self.to_nbt_field()
+            .unwrap_or_else(|| NBTTag::Compound(Default::default()))
+    }
+
+    fn to_nbt_field(&self) -> Option<NBTTag> {
+        self.as_ref().map(T::to_nbt)
+    }
+}
+
+/// Helpers used by the code generated by the derive macros. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use crate::err::{ErrorPath, PathPart, TypeError};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    pub use crate::convert::{compound, field};
+
+    /// An integer type that can be stored as any integer tag type.
+    pub trait Integer: Copy {
+        fn to_i64(self) -> i64;
+        fn from_i64(v: i64) -> Self;
+    }
+
+    macro_rules! impl_integer {
+        ($($typ:ty),*) => {$(
+            impl Integer for $typ {
+                fn to_i64(self) -> i64 {
+                    self as i64
+                }
+
+                fn from_i64(v: i64) -> Self {
+                    v as $typ
+                }
+            }
+        )*};
+    }
+
+    impl_integer!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize);
+
+    impl Integer for bool {
+        fn to_i64(self) -> i64 {
+            self as i64
+        }
+
+        fn from_i64(v: i64) -> Self {
+            v != 0
+        }
+    }
+
+    /// Reads an integer struct field from the entry with the provided key, which must have the
+    /// provided integer tag type.
+    pub fn int_field<T: Integer>(
+        compound: &tag::Compound,
+        key: &str,
+        width: NBTTagType,
+    ) -> Result<T, ErrorPath<TypeError>> {
+        let v = match compound.get(key) {
+            Some(NBTTag::Byte(v)) if width == NBTTagType::Byte => v.as_i8() as i64,
+            Some(NBTTag::Short(v)) if width == NBTTagType::Short => v.0 as i64,
+            Some(NBTTag::Int(v)) if width == NBTTagType::Int => v.0 as i64,
+            Some(NBTTag::Long(v)) if width == NBTTagType::Long => v.0,
+            Some(v) => {
+                return Err(
+                    ErrorPath::new(TypeError::UnexpectedTag(width, v.tag_type()))
+                        .prepend(PathPart::MapKey(key.to_string())),
+                )
+            }
+            None => {
+                return Err(ErrorPath::new(TypeError::MissingTag)
+                    .prepend(PathPart::MapKey(key.to_string())))
+            }
+        };
+        Ok(T::from_i64(v))
+    }
+
+    /// Converts an integer struct field into a tag of the provided integer tag type.
+    pub fn int_to_nbt<T: Integer>(v: &T, width: NBTTagType) -> NBTTag {
+        let v = v.to_i64();
+        match width {
+            NBTTagType::Byte => NBTTag::Byte((v as u8).into()),
+            NBTTagType::Short => NBTTag::Short((v as i16).into()),
+            NBTTagType::Int => NBTTag::Int((v as i32).into()),
+            _ => NBTTag::Long(v.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::derive::{NbtDeserialize, NbtSerialize};
+    use crate::encoding::BigEndian;
+    use crate::err::{PathPart, TypeError};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[derive(Debug, PartialEq, NbtSerialize, NbtDeserialize)]
+    struct Item {
+        #[nbt(rename = "id")]
+        name: String,
+        #[nbt(rename = "Count", byte)]
+        count: i32,
+        #[nbt(short)]
+        damage: u16,
+        lore: Vec<String>,
+        tag: Option<NBTTag>,
+    }
+
+    #[derive(Debug, PartialEq, NbtSerialize, NbtDeserialize)]
+    struct Inventory {
+        items: Vec<Item>,
+        #[nbt(rename = "Selected", byte)]
+        selected: bool,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inventory = Inventory {
+            items: vec![
+                Item {
+                    name: "minecraft:stone".to_string(),
+                    count: 64,
+                    damage: 3,
+                    lore: vec!["Heavy".to_string()],
+                    tag: None,
+                },
+                Item {
+                    name: "minecraft:stick".to_string(),
+                    count: -1,
+                    damage: 0,
+                    lore: vec![],
+                    tag: Some(NBTTag::Int(5.into())),
+                },
+            ],
+            selected: true,
+        };
+
+        let nbt = inventory.to_nbt();
+        let view = nbt.view();
+        assert_eq!(view.at("Selected").byte(), Ok(1));
+        assert_eq!(
+            view.at("items").at(0).at("id").string(),
+            Ok("minecraft:stone")
+        );
+        assert_eq!(view.at("items").at(0).at("Count").byte(), Ok(64));
+        assert_eq!(view.at("items").at(0).at("damage").short(), Ok(3));
+        assert!(!view.at("items").at(0).at("tag").valid());
+        assert_eq!(view.at("items").at(1).at("Count").byte(), Ok(255));
+
+        let mut buf = Vec::new();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+        let read = NBTTag::read(&mut buf.as_slice(), &mut BigEndian::default()).unwrap();
+        assert_eq!(Inventory::from_nbt(&read), Ok(inventory));
+    }
+
+    #[test]
+    fn test_errors() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list(
+                    "items",
+                    vec![tag::Compound::builder()
+                        .with_string("id", "minecraft:stone")
+                        .with_int("Count", 1)
+                        .build()],
+                )
+                .with_byte("Selected", 0)
+                .build(),
+        );
+        let err = Inventory::from_nbt(&nbt).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Byte, NBTTagType::Int)
+        );
+        assert_eq!(
+            err.path.0,
+            [
+                PathPart::MapKey("items".to_string()),
+                PathPart::Element(0),
+                PathPart::MapKey("Count".to_string()),
+            ]
+        );
+
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_byte("Selected", 0).build());
+        let err = Inventory::from_nbt(&nbt).unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.0, [PathPart::MapKey("items".to_string())]);
+    }
+}
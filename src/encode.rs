@@ -1,10 +1,21 @@
 //! See [Writer].
 use crate::err::{ErrorPath, PathPart, WriteError};
+use crate::half;
 use bytes::BufMut;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::io::{self, Write};
 
 /// A short notation for the result type used in the [Writer].
 pub type Res = Result<(), ErrorPath<WriteError>>;
 
+/// A function that determines the order in which the keys of a compound tag are written.
+pub type KeyComparator = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+/// A function that writes the payload of a custom tag from the bytes stored in its
+/// [tag::Custom](crate::tag::Custom). See [Writer::custom_tag_writer].
+pub type CustomTagWriter = dyn Fn(&[u8], &mut dyn BufMut) -> Result<(), WriteError> + Send + Sync;
+
 /// A trait that can be implemented to alter how basic NBT types are written.
 ///
 /// All the implemented methods must not panic.
@@ -25,38 +36,143 @@ pub trait Writer {
     /// Writes a 64-bit floating point number.
     fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> Res;
 
+    /// Writes a 128-bit signed integer as two 64-bit signed integers. The most significant half is
+    /// written first, and both halves are written using [Self::write_i64]. Each half holds 64 of
+    /// the bits of the integer as-is, so the least significant half may be negative even if the
+    /// integer is positive.
+    ///
+    /// This is not an NBT type, but a convention for storing 128-bit values, such as in a pair of
+    /// long tags or a long array of two elements.
+    fn write_i128(&mut self, buf: &mut impl BufMut, x: i128) -> Res {
+        self.write_i64(buf, (x >> 64) as i64)?;
+        self.write_i64(buf, x as i64)
+    }
+
+    /// Writes a floating point number as the bits of a half-precision number, stored in a 16-bit
+    /// signed integer using [Self::write_i16].
+    ///
+    /// This is not an NBT type, but a convention for storing floating point numbers compactly in
+    /// short tags. The number is rounded to the nearest half-precision number. Numbers too large
+    /// to be represented are written as infinity, and numbers too small as zero.
+    fn write_f16(&mut self, buf: &mut impl BufMut, x: f32) -> Res {
+        self.write_i16(buf, half::from_f32(x) as i16)
+    }
+
+    /// Returns the comparator used to order the keys of compound tags when writing them.
+    ///
+    /// By default, no comparator is used, and the keys are written in the arbitrary iteration order
+    /// of the compound.
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        None
+    }
+
+    /// Returns whether empty lists are written with the `END` element type instead of their
+    /// declared element type.
+    ///
+    /// By default, the declared element type is written.
+    fn end_typed_empty_lists(&self) -> bool {
+        false
+    }
+
     /// Writes the NBT `end` tag, which indicates the end of a compound tag.
     fn write_end(&mut self, buf: &mut impl BufMut) -> Res {
         buf.put_u8(0);
         Ok(())
     }
 
-    /// Writes a variable-length string.
-    fn write_string(&mut self, buf: &mut impl BufMut, x: &str) -> Res {
-        if x.len() > i16::MAX as usize {
+    /// Writes pre-encoded bytes as-is.
+    ///
+    /// The bytes must have been encoded using the same encoding as the writer, or the written data
+    /// will be corrupt.
+    fn write_raw(&mut self, buf: &mut impl BufMut, x: &[u8]) -> Res {
+        buf.put_slice(x);
+        Ok(())
+    }
+
+    /// Returns whether the lengths of strings and arrays are written as 64-bit integers. See the
+    /// `extended_lengths` option of the encodings in [crate::encoding].
+    ///
+    /// By default, the standard lengths are written.
+    fn writes_extended_lengths(&self) -> bool {
+        false
+    }
+
+    /// Returns whether compound entries with a default value, such as an int of zero or an empty
+    /// string, are left out. See the `omit_defaults` option of the encodings in [crate::encoding].
+    ///
+    /// By default, all entries are written.
+    fn omits_defaults(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the `END` tag of the root compound tag is left out. See the
+    /// `omit_root_end` option of the encodings in [crate::encoding].
+    ///
+    /// By default, the `END` tag is written.
+    fn omits_root_end(&self) -> bool {
+        false
+    }
+
+    /// Returns the function used to write the payload of tags with the provided id, which is not
+    /// the id of a standard tag type. See the `custom_tag` option of the encodings in
+    /// [crate::encoding].
+    ///
+    /// By default, no custom tags are supported, and writing them results in
+    /// [WriteError::UnknownTagType].
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        let _ = id;
+        None
+    }
+
+    /// Writes the length prefix of a variable-length string.
+    fn write_string_len(&mut self, buf: &mut impl BufMut, len: usize) -> Res {
+        if self.writes_extended_lengths() {
+            return write_extended_len(self, buf, len);
+        }
+        if len > i16::MAX as usize {
             return Err(ErrorPath::new(WriteError::SeqLengthViolation(
                 i16::MAX as usize,
-                x.len(),
+                len,
             )));
         }
+        self.write_i16(buf, len as i16)
+    }
 
-        self.write_i16(buf, x.len() as i16)?;
-        for (i, b) in x.as_bytes().iter().enumerate() {
+    /// Writes a variable-length string.
+    fn write_string(&mut self, buf: &mut impl BufMut, x: &str) -> Res {
+        let bytes = self.encode_string(x)?;
+        self.write_string_len(buf, bytes.len())?;
+        for (i, b) in bytes.iter().enumerate() {
             self.write_u8(buf, *b)
                 .map_err(|err| err.prepend(PathPart::Element(i)))?;
         }
         Ok(())
     }
 
-    /// Writes variable-length array of 8-bit unsigned integers.
-    fn write_u8_vec(&mut self, buf: &mut impl BufMut, x: &Vec<u8>) -> Res {
-        if x.len() > i32::MAX as usize {
+    /// Converts a string into the bytes written by [Self::write_string].
+    ///
+    /// By default, the string is written as UTF-8.
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        Ok(Cow::Borrowed(x.as_bytes()))
+    }
+
+    /// Writes the length prefix of a variable-length array.
+    fn write_array_len(&mut self, buf: &mut impl BufMut, len: usize) -> Res {
+        if self.writes_extended_lengths() {
+            return write_extended_len(self, buf, len);
+        }
+        if len > i32::MAX as usize {
             return Err(ErrorPath::new(WriteError::SeqLengthViolation(
                 i32::MAX as usize,
-                x.len(),
+                len,
             )));
         }
-        self.write_i32(buf, x.len() as i32)?;
+        self.write_i32(buf, len as i32)
+    }
+
+    /// Writes variable-length array of 8-bit unsigned integers.
+    fn write_u8_vec(&mut self, buf: &mut impl BufMut, x: &[u8]) -> Res {
+        self.write_array_len(buf, x.len())?;
         for (i, v) in x.iter().enumerate() {
             self.write_u8(buf, *v)
                 .map_err(|err| err.prepend(PathPart::Element(i)))?;
@@ -64,15 +180,21 @@ pub trait Writer {
         Ok(())
     }
 
-    /// Writes variable-length array of 32-bit signed integers.
-    fn write_i32_vec(&mut self, buf: &mut impl BufMut, x: &Vec<i32>) -> Res {
-        if x.len() > i32::MAX as usize {
-            return Err(ErrorPath::new(WriteError::SeqLengthViolation(
-                i32::MAX as usize,
-                x.len(),
-            )));
+    /// Writes variable-length array of 8-bit signed integers, the way Minecraft: Java Edition
+    /// interprets byte arrays. The array is written like [Self::write_u8_vec], keeping the bits
+    /// of every element as-is.
+    fn write_i8_vec(&mut self, buf: &mut impl BufMut, x: &[i8]) -> Res {
+        self.write_array_len(buf, x.len())?;
+        for (i, v) in x.iter().enumerate() {
+            self.write_u8(buf, *v as u8)
+                .map_err(|err| err.prepend(PathPart::Element(i)))?;
         }
-        self.write_i32(buf, x.len() as i32)?;
+        Ok(())
+    }
+
+    /// Writes variable-length array of 32-bit signed integers.
+    fn write_i32_vec(&mut self, buf: &mut impl BufMut, x: &[i32]) -> Res {
+        self.write_array_len(buf, x.len())?;
         for (i, v) in x.iter().enumerate() {
             self.write_i32(buf, *v)
                 .map_err(|err| err.prepend(PathPart::Element(i)))?;
@@ -81,14 +203,8 @@ pub trait Writer {
     }
 
     /// Writes variable-length array of 64-bit signed integers.
-    fn write_i64_vec(&mut self, buf: &mut impl BufMut, x: &Vec<i64>) -> Res {
-        if x.len() > i32::MAX as usize {
-            return Err(ErrorPath::new(WriteError::SeqLengthViolation(
-                i32::MAX as usize,
-                x.len(),
-            )));
-        }
-        self.write_i32(buf, x.len() as i32)?;
+    fn write_i64_vec(&mut self, buf: &mut impl BufMut, x: &[i64]) -> Res {
+        self.write_array_len(buf, x.len())?;
         for (i, v) in x.iter().enumerate() {
             self.write_i64(buf, *v)
                 .map_err(|err| err.prepend(PathPart::Element(i)))?;
@@ -96,3 +212,139 @@ pub trait Writer {
         Ok(())
     }
 }
+
+/// Writes a 64-bit length prefix, as used when [Writer::writes_extended_lengths] is enabled.
+pub(crate) fn write_extended_len(
+    w: &mut (impl Writer + ?Sized),
+    buf: &mut impl BufMut,
+    len: usize,
+) -> Res {
+    let len = i64::try_from(len)
+        .map_err(|_| ErrorPath::new(WriteError::SeqLengthViolation(i64::MAX as usize, len)))?;
+    w.write_i64(buf, len)
+}
+
+/// The amount of bytes an [IoWriter] collects before writing them into its output.
+pub(crate) const IO_CHUNK_SIZE: usize = 8192;
+
+/// A [Writer] that writes into a [Write] instead of into the buffer passed to its methods, keeping
+/// track of the amount of bytes written. Used by [NBTTag::write_io](crate::NBTTag::write_io).
+///
+/// The data is encoded by the wrapped writer into a buffer, which is written into the output once
+/// it holds at least [IO_CHUNK_SIZE] bytes. Errors of the output thus occur in the method that
+/// completed the chunk, which gives them the path of the tag that was being written.
+pub(crate) struct IoWriter<'a, O, W> {
+    out: &'a mut O,
+    inner: &'a mut W,
+    pending: Vec<u8>,
+    written: usize,
+}
+
+impl<'a, O: Write, W: Writer> IoWriter<'a, O, W> {
+    pub(crate) fn new(out: &'a mut O, inner: &'a mut W) -> Self {
+        Self {
+            out,
+            inner,
+            pending: Vec::with_capacity(IO_CHUNK_SIZE),
+            written: 0,
+        }
+    }
+
+    /// Writes the remaining data into the output, returning the total amount of bytes written.
+    pub(crate) fn finish(mut self) -> Result<usize, ErrorPath<WriteError>> {
+        self.flush()?;
+        Ok(self.written)
+    }
+
+    /// Writes the collected data into the output if it holds at least [IO_CHUNK_SIZE] bytes.
+    fn flush_full(&mut self) -> Res {
+        if self.pending.len() >= IO_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all collected data into the output.
+    fn flush(&mut self) -> Res {
+        let mut pending = &self.pending[..];
+        while !pending.is_empty() {
+            match self.out.write(pending) {
+                Ok(0) => return Err(self.io_error(io::ErrorKind::WriteZero.into())),
+                Ok(n) => {
+                    self.written += n;
+                    pending = &pending[n..];
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(self.io_error(err)),
+            }
+        }
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn io_error(&self, source: io::Error) -> ErrorPath<WriteError> {
+        ErrorPath::new(WriteError::Io {
+            written: self.written,
+            source,
+        })
+    }
+}
+
+/// Implements a [Writer] method of an [IoWriter] by writing into its buffer using the wrapped
+/// writer.
+macro_rules! buffered {
+    ($($name:ident($($arg:ident: $typ:ty),*);)*) => {$(
+        fn $name(&mut self, _buf: &mut impl BufMut $(, $arg: $typ)*) -> Res {
+            self.inner.$name(&mut self.pending $(, $arg)*)?;
+            self.flush_full()
+        }
+    )*};
+}
+
+impl<O: Write, W: Writer> Writer for IoWriter<'_, O, W> {
+    buffered!(
+        write_u8(x: u8);
+        write_i16(x: i16);
+        write_i32(x: i32);
+        write_i64(x: i64);
+        write_f32(x: f32);
+        write_f64(x: f64);
+        write_i128(x: i128);
+        write_end();
+        write_raw(x: &[u8]);
+        write_string_len(len: usize);
+        write_string(x: &str);
+        write_array_len(len: usize);
+        write_u8_vec(x: &[u8]);
+        write_i32_vec(x: &[i32]);
+        write_i64_vec(x: &[i64]);
+    );
+
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        self.inner.key_comparator()
+    }
+
+    fn end_typed_empty_lists(&self) -> bool {
+        self.inner.end_typed_empty_lists()
+    }
+
+    fn writes_extended_lengths(&self) -> bool {
+        self.inner.writes_extended_lengths()
+    }
+
+    fn omits_defaults(&self) -> bool {
+        self.inner.omits_defaults()
+    }
+
+    fn omits_root_end(&self) -> bool {
+        self.inner.omits_root_end()
+    }
+
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        self.inner.custom_tag_writer(id)
+    }
+
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        self.inner.encode_string(x)
+    }
+}
@@ -4,36 +4,467 @@
 //!  - [BigEndian]
 //!  - [LittleEndian]
 //!  - [NetworkLittleEndian]
-use crate::decode::Reader;
-use crate::encode::Writer;
-use crate::err::{ErrorPath, PathPart, ReadError, WriteError};
-use crate::{decode, encode};
+//!
+//! If the encoding is only known at runtime, [AnyEncoding] can be used instead. The encoding of
+//! unknown data can be guessed using [detect_encoding].
+use crate::decode::{CustomTagReader, Reader};
+use crate::encode::{CustomTagWriter, KeyComparator, Writer};
+use crate::err::{ErrorPath, ReadError, WriteError};
+use crate::{decode, encode, mutf8};
 use bytes::{Buf, BufMut};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 use std::mem;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
 
 /// An NBT encoding that encodes all basic types using big endian encoding.
 ///
-/// This format is most commonly used in Minecraft: Java Edition.
-#[derive(Debug, Default, Clone)]
-pub struct BigEndian;
+/// This format is most commonly used in Minecraft: Java Edition. Like Java Edition, it converts
+/// strings using [StringEncoding::ModifiedUtf8] by default. Versions before 0.4.0 used
+/// [StringEncoding::Utf8] instead, which can still be selected using [Self::string_encoding].
+#[derive(Debug, Clone)]
+pub struct BigEndian {
+    settings: Settings,
+}
+
+impl Default for BigEndian {
+    fn default() -> Self {
+        Self {
+            settings: Settings {
+                string_encoding: StringEncoding::ModifiedUtf8,
+                ..Default::default()
+            },
+        }
+    }
+}
 
 /// An NBT encoding that encodes all basic types using little endian encoding.
 ///
 /// This format is most commonly used in Minecraft: Bedrock Edition, and more specifically in
-/// Bedrock Edition world saves.
+/// Bedrock Edition world saves. Like Bedrock Edition, it converts strings using
+/// [StringEncoding::Utf8] by default.
 ///
 /// It is not to be confused with the [NetworkLittleEndian] encoding.
+///
+/// The encoding itself is the same in all versions of Bedrock Edition, so it does not take a
+/// version. Differences between versions are found outside of the NBT data instead, such as in
+/// the 8-byte header of `level.dat` files, which contains the storage version followed by the
+/// length of the data. Should a version-specific quirk of the encoding be found, it can be added as
+/// an option of the encoding like the others, without requiring a new type.
 #[derive(Debug, Default, Clone)]
-pub struct LittleEndian;
+pub struct LittleEndian {
+    settings: Settings,
+}
 
 /// An NBT encoding that encodes certain integer types using variable-length encoding, while using
 /// fixed-size little endian encoding for all other basic types.
 ///
-/// This format is most commonly used for nbt sent in Minecraft: Bedrock Edition's protocol.
+/// This format is most commonly used for nbt sent in Minecraft: Bedrock Edition's protocol. Like
+/// Bedrock Edition, it converts strings using [StringEncoding::Utf8] by default.
+///
+/// The lengths of strings, lists and arrays, as well as the elements of int and long arrays, use
+/// variable-length encoding. The lengths of lists and arrays are written as ints, so they are
+/// zigzag encoded like other signed integers, while the lengths of strings are unsigned. This
+/// matches the encoding used by Bedrock Edition itself. All tags, including arrays of any length,
+/// round-trip losslessly.
+#[derive(Debug, Default, Clone)]
+pub struct NetworkLittleEndian {
+    settings: Settings,
+}
+
+/// The way the characters of a string are converted to and from bytes.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum StringEncoding {
+    /// Standard UTF-8.
+    #[default]
+    Utf8,
+    /// The modified UTF-8 used by Java, and thus by Minecraft: Java Edition.
+    ///
+    /// The null character is encoded using two bytes, and characters outside the basic
+    /// multilingual plane are encoded as a surrogate pair of three bytes each.
+    ModifiedUtf8,
+}
+
+/// Settings shared by all the standard encodings.
 #[derive(Debug, Default, Clone)]
-pub struct NetworkLittleEndian;
+struct Settings {
+    string_encoding: StringEncoding,
+    reject_non_finite: bool,
+    key_comparator: Option<SharedComparator>,
+    max_compound_entries: Option<usize>,
+    max_depth: Option<usize>,
+    max_string_len: Option<usize>,
+    lenient_string_lengths: bool,
+    lossy_strings: bool,
+    cancel_token: Option<Arc<AtomicBool>>,
+    end_typed_empty_lists: bool,
+    extended_lengths: bool,
+    omit_defaults: bool,
+    omit_root_end: bool,
+    canonical_varints: bool,
+    custom_tags: HashMap<u8, SharedCustomTag>,
+}
+
+/// A [KeyComparator] that can be cloned along with the [Settings].
+#[derive(Clone)]
+struct SharedComparator(Arc<KeyComparator>);
+
+impl Debug for SharedComparator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedComparator")
+    }
+}
+
+/// The handler of a custom tag type that can be cloned along with the [Settings].
+#[derive(Clone)]
+struct SharedCustomTag {
+    read: Arc<CustomTagReader>,
+    write: Arc<CustomTagWriter>,
+}
+
+impl Debug for SharedCustomTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedCustomTag")
+    }
+}
+
+impl Settings {
+    /// Converts the bytes of a string into a string using the configured [StringEncoding].
+    fn decode_string(&self, bytes: Vec<u8>) -> decode::Res<String> {
+        match self.string_encoding {
+            StringEncoding::Utf8 if self.lossy_strings => Ok(String::from_utf8(bytes)
+                .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())),
+            StringEncoding::Utf8 => {
+                String::from_utf8(bytes).map_err(|err| ErrorPath::new(ReadError::from(err)))
+            }
+            StringEncoding::ModifiedUtf8 => mutf8::decode(bytes, self.lossy_strings)
+                .map_err(|pos| ErrorPath::new(ReadError::InvalidModifiedUtf8(pos))),
+        }
+    }
+
+    /// Returns whether the cancellation token has been set.
+    fn cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(atomic::Ordering::Relaxed))
+    }
+
+    /// Returns an error if the floating point number is not finite and such numbers are rejected.
+    fn check_finite(&self, x: f64) -> encode::Res {
+        if self.reject_non_finite && !x.is_finite() {
+            return Err(ErrorPath::new(WriteError::NonFiniteFloat(x)));
+        }
+        Ok(())
+    }
+
+    /// Converts a string into bytes using the configured [StringEncoding].
+    fn encode_string<'a>(&self, x: &'a str) -> Cow<'a, [u8]> {
+        match self.string_encoding {
+            StringEncoding::Utf8 => Cow::Borrowed(x.as_bytes()),
+            StringEncoding::ModifiedUtf8 => mutf8::encode(x),
+        }
+    }
+}
+
+/// Implements the builder-style methods to change the [Settings] of the standard encodings, and
+/// the methods of [Reader] and [Writer] that depend on them.
+macro_rules! impl_settings {
+    ($($typ:ty),*) => {$(
+        impl $typ {
+            /// Sets the way strings are converted to and from bytes.
+            ///
+            /// Defaults to the encoding used by the edition the format is used by: that is
+            /// [StringEncoding::ModifiedUtf8] for [BigEndian], and [StringEncoding::Utf8] for
+            /// [LittleEndian] and [NetworkLittleEndian].
+            pub fn string_encoding(mut self, string_encoding: StringEncoding) -> Self {
+                self.settings.string_encoding = string_encoding;
+                self
+            }
+
+            /// Sets whether writing NaN or infinite floating point numbers results in an error.
+            ///
+            /// By default, all floating point numbers are written with their exact bits preserved.
+            pub fn reject_non_finite(mut self, reject_non_finite: bool) -> Self {
+                self.settings.reject_non_finite = reject_non_finite;
+                self
+            }
+
+            /// Sets the comparator that determines the order in which the keys of compound tags
+            /// are written, for example to match the output of another tool.
+            ///
+            /// By default, keys are written in the arbitrary iteration order of the compound.
+            pub fn key_comparator(
+                mut self,
+                comparator: impl Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+            ) -> Self {
+                self.settings.key_comparator = Some(SharedComparator(Arc::new(comparator)));
+                self
+            }
+
+            /// Sets the maximum amount of entries a compound tag may contain while reading.
+            ///
+            /// Reading a compound with more entries results in an error, which protects against
+            /// data crafted to exhaust memory. By default, there is no limit.
+            pub fn max_compound_entries(mut self, max: usize) -> Self {
+                self.settings.max_compound_entries = Some(max);
+                self
+            }
+
+            /// Sets the maximum depth to which compound and list tags may be nested while reading,
+            /// where the root tag is at depth one.
+            ///
+            /// Reading more deeply nested data results in an error. Tags are read without
+            /// recursion, so the limit can safely be raised to read deeply nested data that is
+            /// trusted. Defaults to [decode::DEFAULT_MAX_DEPTH].
+            pub fn max_depth(mut self, max: usize) -> Self {
+                self.settings.max_depth = Some(max);
+                self
+            }
+
+            /// Sets the maximum length in bytes of strings, including the names of tags, while
+            /// reading.
+            ///
+            /// Reading a longer string results in an error before any memory is allocated for
+            /// it, which protects against untrusted input. The limit applies in addition to the
+            /// maximum length the encoding can represent. By default, there is no limit.
+            pub fn max_string_len(mut self, max: usize) -> Self {
+                self.settings.max_string_len = Some(max);
+                self
+            }
+
+            /// Sets whether strings with a negative length are read as empty strings instead of
+            /// resulting in an error.
+            ///
+            /// This allows recovering slightly corrupt files. Varint string lengths can never be
+            /// negative, so this has no effect on the [NetworkLittleEndian] encoding. Defaults to
+            /// false.
+            pub fn lenient_string_lengths(mut self, lenient: bool) -> Self {
+                self.settings.lenient_string_lengths = lenient;
+                self
+            }
+
+            /// Sets whether invalid byte sequences in strings are replaced with U+FFFD instead of
+            /// resulting in an error while reading, like Java does when decoding bytes into a
+            /// string.
+            ///
+            /// For [StringEncoding::ModifiedUtf8], this includes unpaired surrogates and truncated
+            /// multibyte sequences. Replaced strings are written back with the replacement
+            /// characters, so the original bytes are lost. Defaults to false.
+            pub fn lossy_strings(mut self, lossy: bool) -> Self {
+                self.settings.lossy_strings = lossy;
+                self
+            }
+
+            /// Sets a token that cancels reading once it is set to `true`.
+            ///
+            /// The token is checked periodically while reading lists, compounds and arrays, after
+            /// which reading fails with [ReadError::Cancelled]. This allows aborting the parsing
+            /// of large data from another thread.
+            pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+                self.settings.cancel_token = Some(token);
+                self
+            }
+
+            /// Sorts the keys of compound tags lexicographically when writing them.
+            ///
+            /// This makes the output deterministic: reading data and writing it again always
+            /// produces the same bytes. Shorthand for [Self::key_comparator] with [str::cmp].
+            pub fn sorted_keys(self) -> Self {
+                self.key_comparator(str::cmp)
+            }
+
+            /// Sets whether the lengths of strings and arrays are read and written as 64-bit
+            /// integers, allowing for strings and arrays larger than the standard limits. Lists
+            /// keep their standard length.
+            ///
+            /// **This is not part of any NBT format.** Data written with this option enabled can
+            /// only be read by this crate with the same option enabled, and is incompatible with
+            /// Minecraft and all other tools. It is intended for custom pipelines only. Requires
+            /// the `extended-lengths` feature. Defaults to false.
+            #[cfg(feature = "extended-lengths")]
+            pub fn extended_lengths(mut self, extended_lengths: bool) -> Self {
+                self.settings.extended_lengths = extended_lengths;
+                self
+            }
+
+            /// Sets whether compound entries with a default value are left out when writing. The
+            /// default values are zero for numbers, including only positive zero for floating
+            /// point numbers, and empty strings, lists and arrays. Compounds are always written.
+            ///
+            /// **This is not part of any NBT format.** Minecraft and other tools read such data
+            /// without the omitted entries. The entries can be restored after reading using
+            /// [NBTTag::fill_defaults](crate::NBTTag::fill_defaults) with a template of the
+            /// complete data. It is intended for space-constrained custom storage only. Requires
+            /// the `omit-defaults` feature. Defaults to false.
+            #[cfg(feature = "omit-defaults")]
+            pub fn omit_defaults(mut self, omit_defaults: bool) -> Self {
+                self.settings.omit_defaults = omit_defaults;
+                self
+            }
+
+            /// Sets whether the `END` tag of the root compound tag is left out when writing, and
+            /// may be missing when reading, in which case the root compound ends at the end of the
+            /// buffer. The `END` tags of nested compounds are always written. This saves a byte
+            /// for every message in protocols that frame messages with their length, as the
+            /// length already marks the end of the root compound.
+            ///
+            /// **This is not part of any NBT format.** Minecraft and other tools fail to read data
+            /// written with this option enabled. While reading, the buffer must end exactly
+            /// where the message ends, and the root compound may still end with an `END` tag. Only
+            /// the reading and writing methods of [NBTTag](crate::NBTTag) and the
+            /// [StreamWriter](crate::stream::StreamWriter) take the option into account. Requires
+            /// the `omit-root-end` feature. Defaults to false.
+            #[cfg(feature = "omit-root-end")]
+            pub fn omit_root_end(mut self, omit_root_end: bool) -> Self {
+                self.settings.omit_root_end = omit_root_end;
+                self
+            }
+
+            /// Registers the handler for tags with the provided id, which is not the id of any
+            /// standard tag type, such as a tag type added by a mod.
+            ///
+            /// Such tags are read into a [tag::Custom](crate::tag::Custom) holding the bytes
+            /// returned by `read`, which is given the buffer positioned at the start of the
+            /// payload. Writing the tag passes those bytes to `write`, which writes the payload.
+            /// The size of the payload is only known to the handler, so custom tags are not
+            /// supported by parts of the crate that skip over tags without reading them.
+            ///
+            /// Panics if the id is that of a standard tag type or of the `END` tag, which are the
+            /// ids from 0 up to and including 12.
+            pub fn custom_tag(
+                mut self,
+                id: u8,
+                read: impl Fn(&mut dyn Buf) -> Result<Vec<u8>, ReadError> + Send + Sync + 'static,
+                write: impl Fn(&[u8], &mut dyn BufMut) -> Result<(), WriteError>
+                    + Send
+                    + Sync
+                    + 'static,
+            ) -> Self {
+                assert!(id > 12, "tag id {id} is the id of a standard tag type");
+                let handler = SharedCustomTag {
+                    read: Arc::new(read),
+                    write: Arc::new(write),
+                };
+                self.settings.custom_tags.insert(id, handler);
+                self
+            }
+
+            /// Writes data the way NBTExplorer saves it, so that the output can be compared with
+            /// files exported by the editor.
+            ///
+            /// Compound keys are sorted case-insensitively, with keys that only differ in case
+            /// ordered by their code points. Empty lists are written with the `END` element type,
+            /// regardless of their declared element type, like Minecraft: Java Edition does.
+            pub fn nbt_explorer(mut self) -> Self {
+                self.settings.end_typed_empty_lists = true;
+                self.key_comparator(|a, b| {
+                    let a_lower = a.chars().flat_map(char::to_lowercase);
+                    let b_lower = b.chars().flat_map(char::to_lowercase);
+                    a_lower.cmp(b_lower).then_with(|| a.cmp(b))
+                })
+            }
+        }
+    )*};
+}
+
+impl_settings!(BigEndian, LittleEndian, NetworkLittleEndian);
+
+impl NetworkLittleEndian {
+    /// Sets whether varints must be encoded canonically while reading, which fails with
+    /// [ReadError::NonCanonicalVarint] otherwise.
+    ///
+    /// Without this option, overlong varints that contain redundant zero bytes are accepted, as
+    /// are bits in the last byte that do not fit in the integer. This means that a value can be
+    /// encoded in multiple ways, which parsers may disagree on and which prevents comparing the
+    /// encoded data. The varints written by this crate are always canonical. Defaults to false.
+    pub fn canonical_varints(mut self, canonical_varints: bool) -> Self {
+        self.settings.canonical_varints = canonical_varints;
+        self
+    }
+
+    /// Reads an unsigned varint holding an integer of the provided amount of bits.
+    fn varint(&mut self, buf: &mut impl Buf, bits: u32) -> decode::Res<u64> {
+        let mut v: u64 = 0;
+        for i in (0..bits.next_multiple_of(7)).step_by(7) {
+            if buf.remaining() < mem::size_of::<u8>() {
+                return Err(ErrorPath::new(ReadError::UnexpectedEOF));
+            }
+            let b = self.u8(buf)?;
+
+            if self.settings.canonical_varints {
+                // A trailing zero byte adds nothing to the value, while bits beyond the size of
+                // the integer would be discarded.
+                let overlong = i > 0 && b == 0;
+                let overflows = i + 7 > bits && (b & 0x7f) >> (bits - i) != 0;
+                if overlong || overflows {
+                    return Err(ErrorPath::new(ReadError::NonCanonicalVarint));
+                }
+            }
+            v |= ((b & 0x7f) as u64) << i;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(ErrorPath::new(ReadError::Custom(
+            "varint overflows integer".to_string(),
+        )))
+    }
+}
+
+/// An NBT encoding that is chosen at runtime, delegating to one of the standard encodings.
+///
+/// This is useful when the encoding is detected from the data itself, for example from a file
+/// header, at the cost of a branch for every basic type read or written.
+#[derive(Debug, Clone)]
+pub enum AnyEncoding {
+    /// Delegates to [BigEndian].
+    BigEndian(BigEndian),
+    /// Delegates to [LittleEndian].
+    LittleEndian(LittleEndian),
+    /// Delegates to [NetworkLittleEndian].
+    NetworkLittleEndian(NetworkLittleEndian),
+}
 
 impl Reader for BigEndian {
+    fn decode_string(&mut self, bytes: Vec<u8>) -> decode::Res<String> {
+        self.settings.decode_string(bytes)
+    }
+
+    fn max_compound_entries(&self) -> Option<usize> {
+        self.settings.max_compound_entries
+    }
+
+    fn max_depth(&self) -> usize {
+        self.settings.max_depth.unwrap_or(decode::DEFAULT_MAX_DEPTH)
+    }
+
+    fn max_string_len(&self) -> Option<usize> {
+        self.settings.max_string_len
+    }
+
+    fn lenient_string_lengths(&self) -> bool {
+        self.settings.lenient_string_lengths
+    }
+
+    fn cancelled(&self) -> bool {
+        self.settings.cancelled()
+    }
+
+    fn reads_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn reads_omitted_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.read)
+    }
+
     fn i16(&mut self, buf: &mut impl Buf) -> decode::Res<i16> {
         if buf.remaining() < mem::size_of::<i16>() {
             return Err(ErrorPath::new(ReadError::UnexpectedEOF));
@@ -71,6 +502,34 @@ impl Reader for BigEndian {
 }
 
 impl Writer for BigEndian {
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        Ok(self.settings.encode_string(x))
+    }
+
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        self.settings.key_comparator.as_ref().map(|c| &*c.0)
+    }
+
+    fn end_typed_empty_lists(&self) -> bool {
+        self.settings.end_typed_empty_lists
+    }
+
+    fn writes_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn omits_defaults(&self) -> bool {
+        self.settings.omit_defaults
+    }
+
+    fn omits_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.write)
+    }
+
     fn write_i16(&mut self, buf: &mut impl BufMut, x: i16) -> encode::Res {
         buf.put_i16(x);
         Ok(())
@@ -87,17 +546,73 @@ impl Writer for BigEndian {
     }
 
     fn write_f32(&mut self, buf: &mut impl BufMut, x: f32) -> encode::Res {
+        self.settings.check_finite(x as f64)?;
         buf.put_f32(x);
         Ok(())
     }
 
     fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> encode::Res {
+        self.settings.check_finite(x)?;
         buf.put_f64(x);
         Ok(())
     }
+
+    fn write_u8_vec(&mut self, buf: &mut impl BufMut, x: &[u8]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        buf.put_slice(x);
+        Ok(())
+    }
+
+    fn write_i32_vec(&mut self, buf: &mut impl BufMut, x: &[i32]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        put_array(buf, x, i32::to_be_bytes);
+        Ok(())
+    }
+
+    fn write_i64_vec(&mut self, buf: &mut impl BufMut, x: &[i64]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        put_array(buf, x, i64::to_be_bytes);
+        Ok(())
+    }
 }
 
 impl Reader for LittleEndian {
+    fn decode_string(&mut self, bytes: Vec<u8>) -> decode::Res<String> {
+        self.settings.decode_string(bytes)
+    }
+
+    fn max_compound_entries(&self) -> Option<usize> {
+        self.settings.max_compound_entries
+    }
+
+    fn max_depth(&self) -> usize {
+        self.settings.max_depth.unwrap_or(decode::DEFAULT_MAX_DEPTH)
+    }
+
+    fn max_string_len(&self) -> Option<usize> {
+        self.settings.max_string_len
+    }
+
+    fn lenient_string_lengths(&self) -> bool {
+        self.settings.lenient_string_lengths
+    }
+
+    fn cancelled(&self) -> bool {
+        self.settings.cancelled()
+    }
+
+    fn reads_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn reads_omitted_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.read)
+    }
+
     fn i16(&mut self, buf: &mut impl Buf) -> decode::Res<i16> {
         if buf.remaining() < mem::size_of::<i16>() {
             return Err(ErrorPath::new(ReadError::UnexpectedEOF));
@@ -135,6 +650,34 @@ impl Reader for LittleEndian {
 }
 
 impl Writer for LittleEndian {
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        Ok(self.settings.encode_string(x))
+    }
+
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        self.settings.key_comparator.as_ref().map(|c| &*c.0)
+    }
+
+    fn end_typed_empty_lists(&self) -> bool {
+        self.settings.end_typed_empty_lists
+    }
+
+    fn writes_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn omits_defaults(&self) -> bool {
+        self.settings.omit_defaults
+    }
+
+    fn omits_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.write)
+    }
+
     fn write_i16(&mut self, buf: &mut impl BufMut, x: i16) -> encode::Res {
         buf.put_i16_le(x);
         Ok(())
@@ -151,60 +694,88 @@ impl Writer for LittleEndian {
     }
 
     fn write_f32(&mut self, buf: &mut impl BufMut, x: f32) -> encode::Res {
+        self.settings.check_finite(x as f64)?;
         buf.put_f32_le(x);
         Ok(())
     }
 
     fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> encode::Res {
+        self.settings.check_finite(x)?;
         buf.put_f64_le(x);
         Ok(())
     }
+
+    fn write_u8_vec(&mut self, buf: &mut impl BufMut, x: &[u8]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        buf.put_slice(x);
+        Ok(())
+    }
+
+    fn write_i32_vec(&mut self, buf: &mut impl BufMut, x: &[i32]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        put_array(buf, x, i32::to_le_bytes);
+        Ok(())
+    }
+
+    fn write_i64_vec(&mut self, buf: &mut impl BufMut, x: &[i64]) -> encode::Res {
+        self.write_array_len(buf, x.len())?;
+        put_array(buf, x, i64::to_le_bytes);
+        Ok(())
+    }
 }
 
 impl Reader for NetworkLittleEndian {
+    fn decode_string(&mut self, bytes: Vec<u8>) -> decode::Res<String> {
+        self.settings.decode_string(bytes)
+    }
+
+    fn max_compound_entries(&self) -> Option<usize> {
+        self.settings.max_compound_entries
+    }
+
+    fn max_depth(&self) -> usize {
+        self.settings.max_depth.unwrap_or(decode::DEFAULT_MAX_DEPTH)
+    }
+
+    fn max_string_len(&self) -> Option<usize> {
+        self.settings.max_string_len
+    }
+
+    fn lenient_string_lengths(&self) -> bool {
+        self.settings.lenient_string_lengths
+    }
+
+    fn cancelled(&self) -> bool {
+        self.settings.cancelled()
+    }
+
+    fn reads_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn reads_omitted_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.read)
+    }
+
     fn i16(&mut self, buf: &mut impl Buf) -> decode::Res<i16> {
-        if buf.remaining() < mem::size_of::<u8>() {
+        if buf.remaining() < mem::size_of::<i16>() {
             return Err(ErrorPath::new(ReadError::UnexpectedEOF));
         }
         Ok(buf.get_i16_le())
     }
 
     fn i32(&mut self, buf: &mut impl Buf) -> decode::Res<i32> {
-        let mut v: u32 = 0;
-        for i in (0..35).step_by(7) {
-            if buf.remaining() < mem::size_of::<u8>() {
-                return Err(ErrorPath::new(ReadError::UnexpectedEOF));
-            }
-            let b = self.u8(buf)?;
-
-            v |= ((b & 0x7f) as u32) << i;
-            if b & 0x80 == 0 {
-                let x = (v >> 1) as i32;
-                return Ok(if v & 1 != 0 { -x } else { x });
-            }
-        }
-        Err(ErrorPath::new(ReadError::Custom(
-            "varint overflows integer".to_string(),
-        )))
+        let v = self.varint(buf, 32)? as u32;
+        Ok((v >> 1) as i32 ^ -((v & 1) as i32))
     }
 
     fn i64(&mut self, buf: &mut impl Buf) -> decode::Res<i64> {
-        let mut v: u64 = 0;
-        for i in (0..70).step_by(7) {
-            if buf.remaining() < mem::size_of::<u8>() {
-                return Err(ErrorPath::new(ReadError::UnexpectedEOF));
-            }
-            let b = self.u8(buf)?;
-
-            v |= ((b & 0x7f) as u64) << i;
-            if b & 0x80 == 0 {
-                let x = (v >> 1) as i64;
-                return Ok(if v & 1 != 0 { -x } else { x });
-            }
-        }
-        Err(ErrorPath::new(ReadError::Custom(
-            "varint overflows integer".to_string(),
-        )))
+        let v = self.varint(buf, 64)?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
     }
 
     fn f32(&mut self, buf: &mut impl Buf) -> decode::Res<f32> {
@@ -221,38 +792,43 @@ impl Reader for NetworkLittleEndian {
         Ok(buf.get_f64_le())
     }
 
-    fn string(&mut self, buf: &mut impl Buf) -> decode::Res<String> {
-        let len = 'var_len: {
-            let mut v: u32 = 0;
-            for i in (0..35).step_by(7) {
-                if buf.remaining() < mem::size_of::<u8>() {
-                    return Err(ErrorPath::new(ReadError::UnexpectedEOF));
-                }
-                let b = self.u8(buf)?;
-
-                v |= ((b & 0x7f) as u32) << i;
-                if b & 0x80 == 0 {
-                    break 'var_len v;
-                }
-            }
-            return Err(ErrorPath::new(ReadError::Custom(
-                "varint overflows integer".to_string(),
-            )));
-        };
-
-        let mut str_buf = Vec::with_capacity(len as usize);
-        for i in 0..len {
-            str_buf.push(
-                self.u8(buf)
-                    .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
-            );
+    fn string_len(&mut self, buf: &mut impl Buf) -> decode::Res<usize> {
+        if self.settings.extended_lengths {
+            return decode::extended_len(self, buf);
         }
-
-        String::from_utf8(str_buf).map_err(|err| ErrorPath::new(ReadError::from(err)))
+        Ok(self.varint(buf, 32)? as u32 as usize)
     }
 }
 
 impl Writer for NetworkLittleEndian {
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        Ok(self.settings.encode_string(x))
+    }
+
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        self.settings.key_comparator.as_ref().map(|c| &*c.0)
+    }
+
+    fn end_typed_empty_lists(&self) -> bool {
+        self.settings.end_typed_empty_lists
+    }
+
+    fn writes_extended_lengths(&self) -> bool {
+        self.settings.extended_lengths
+    }
+
+    fn omits_defaults(&self) -> bool {
+        self.settings.omit_defaults
+    }
+
+    fn omits_root_end(&self) -> bool {
+        self.settings.omit_root_end
+    }
+
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        self.settings.custom_tags.get(&id).map(|t| &*t.write)
+    }
+
     fn write_u8(&mut self, buf: &mut impl BufMut, x: u8) -> encode::Res {
         buf.put_u8(x);
         Ok(())
@@ -264,6 +840,8 @@ impl Writer for NetworkLittleEndian {
     }
 
     fn write_i32(&mut self, buf: &mut impl BufMut, x: i32) -> encode::Res {
+        // Zigzag encoding. The shift discards the sign bit, which the inversion accounts for, so
+        // no overflow occurs at the boundaries: `i32::MIN` is encoded as `u32::MAX`.
         let mut u = (x as u32) << 1;
         if x < 0 {
             u = !u;
@@ -290,45 +868,413 @@ impl Writer for NetworkLittleEndian {
     }
 
     fn write_f32(&mut self, buf: &mut impl BufMut, x: f32) -> encode::Res {
+        self.settings.check_finite(x as f64)?;
         buf.put_f32_le(x);
         Ok(())
     }
 
     fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> encode::Res {
+        self.settings.check_finite(x)?;
         buf.put_f64_le(x);
         Ok(())
     }
 
-    fn write_string(&mut self, buf: &mut impl BufMut, x: &str) -> encode::Res {
-        if x.len() > i16::MAX as usize {
+    fn write_string_len(&mut self, buf: &mut impl BufMut, len: usize) -> encode::Res {
+        if self.settings.extended_lengths {
+            return encode::write_extended_len(self, buf, len);
+        }
+        if len > i16::MAX as usize {
             return Err(ErrorPath::new(WriteError::SeqLengthViolation(
                 i16::MAX as usize,
-                x.len(),
+                len,
             )));
         }
 
-        let mut l = x.len() as u32;
+        let mut l = len as u32;
         while l >= 0x80 {
             self.write_u8(buf, l as u8 | 0x80)?;
             l >>= 7;
         }
-        self.write_u8(buf, l as u8)?;
-        for b in x.as_bytes() {
-            self.write_u8(buf, *b)?;
-        }
-        Ok(())
+        self.write_u8(buf, l as u8)
     }
 }
 
-/// Test all encodings with various data.
-#[cfg(test)]
-mod tests {
-    use crate::decode::Reader;
-    use crate::encode::Writer;
-    use crate::encoding::{BigEndian, LittleEndian, NetworkLittleEndian};
-    use crate::{tag, NBTTag};
-    use bytes::{Bytes, BytesMut};
-
+/// Calls the same method on the encoding contained in an [AnyEncoding].
+macro_rules! delegate {
+    ($self:ident, $method:ident($($arg:expr),*)) => {
+        match $self {
+            AnyEncoding::BigEndian(e) => e.$method($($arg),*),
+            AnyEncoding::LittleEndian(e) => e.$method($($arg),*),
+            AnyEncoding::NetworkLittleEndian(e) => e.$method($($arg),*),
+        }
+    };
+}
+
+impl Reader for AnyEncoding {
+    fn max_compound_entries(&self) -> Option<usize> {
+        delegate!(self, max_compound_entries())
+    }
+
+    fn max_depth(&self) -> usize {
+        delegate!(self, max_depth())
+    }
+
+    fn max_string_len(&self) -> Option<usize> {
+        delegate!(self, max_string_len())
+    }
+
+    fn lenient_string_lengths(&self) -> bool {
+        delegate!(self, lenient_string_lengths())
+    }
+
+    fn cancelled(&self) -> bool {
+        delegate!(self, cancelled())
+    }
+
+    fn reads_extended_lengths(&self) -> bool {
+        delegate!(self, reads_extended_lengths())
+    }
+
+    fn reads_omitted_root_end(&self) -> bool {
+        delegate!(self, reads_omitted_root_end())
+    }
+
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        delegate!(self, custom_tag_reader(id))
+    }
+
+    fn array_len(&mut self, buf: &mut impl Buf) -> decode::Res<usize> {
+        delegate!(self, array_len(buf))
+    }
+
+    fn u8(&mut self, buf: &mut impl Buf) -> decode::Res<u8> {
+        delegate!(self, u8(buf))
+    }
+
+    fn i16(&mut self, buf: &mut impl Buf) -> decode::Res<i16> {
+        delegate!(self, i16(buf))
+    }
+
+    fn i32(&mut self, buf: &mut impl Buf) -> decode::Res<i32> {
+        delegate!(self, i32(buf))
+    }
+
+    fn i64(&mut self, buf: &mut impl Buf) -> decode::Res<i64> {
+        delegate!(self, i64(buf))
+    }
+
+    fn f32(&mut self, buf: &mut impl Buf) -> decode::Res<f32> {
+        delegate!(self, f32(buf))
+    }
+
+    fn f64(&mut self, buf: &mut impl Buf) -> decode::Res<f64> {
+        delegate!(self, f64(buf))
+    }
+
+    fn end(&mut self, buf: &mut impl Buf) -> decode::Res<()> {
+        delegate!(self, end(buf))
+    }
+
+    fn bytes(&mut self, buf: &mut impl Buf, len: usize) -> decode::Res<Vec<u8>> {
+        delegate!(self, bytes(buf, len))
+    }
+
+    fn bytes_into(&mut self, buf: &mut impl Buf, len: usize, out: &mut Vec<u8>) -> decode::Res<()> {
+        delegate!(self, bytes_into(buf, len, out))
+    }
+
+    fn string_len(&mut self, buf: &mut impl Buf) -> decode::Res<usize> {
+        delegate!(self, string_len(buf))
+    }
+
+    fn string(&mut self, buf: &mut impl Buf) -> decode::Res<String> {
+        delegate!(self, string(buf))
+    }
+
+    fn string_into(&mut self, buf: &mut impl Buf, out: &mut String) -> decode::Res<()> {
+        delegate!(self, string_into(buf, out))
+    }
+
+    fn decode_string(&mut self, bytes: Vec<u8>) -> decode::Res<String> {
+        delegate!(self, decode_string(bytes))
+    }
+
+    fn u8_vec(&mut self, buf: &mut impl Buf) -> decode::Res<Vec<u8>> {
+        delegate!(self, u8_vec(buf))
+    }
+
+    fn u8_vec_into(&mut self, buf: &mut impl Buf, out: &mut Vec<u8>) -> decode::Res<()> {
+        delegate!(self, u8_vec_into(buf, out))
+    }
+
+    fn i32_vec(&mut self, buf: &mut impl Buf) -> decode::Res<Vec<i32>> {
+        delegate!(self, i32_vec(buf))
+    }
+
+    fn i64_vec(&mut self, buf: &mut impl Buf) -> decode::Res<Vec<i64>> {
+        delegate!(self, i64_vec(buf))
+    }
+}
+
+impl Writer for AnyEncoding {
+    fn key_comparator(&self) -> Option<&KeyComparator> {
+        delegate!(self, key_comparator())
+    }
+
+    fn end_typed_empty_lists(&self) -> bool {
+        delegate!(self, end_typed_empty_lists())
+    }
+
+    fn writes_extended_lengths(&self) -> bool {
+        delegate!(self, writes_extended_lengths())
+    }
+
+    fn omits_defaults(&self) -> bool {
+        delegate!(self, omits_defaults())
+    }
+
+    fn omits_root_end(&self) -> bool {
+        delegate!(self, omits_root_end())
+    }
+
+    fn custom_tag_writer(&self, id: u8) -> Option<&CustomTagWriter> {
+        delegate!(self, custom_tag_writer(id))
+    }
+
+    fn write_string_len(&mut self, buf: &mut impl BufMut, len: usize) -> encode::Res {
+        delegate!(self, write_string_len(buf, len))
+    }
+
+    fn write_array_len(&mut self, buf: &mut impl BufMut, len: usize) -> encode::Res {
+        delegate!(self, write_array_len(buf, len))
+    }
+
+    fn write_u8(&mut self, buf: &mut impl BufMut, x: u8) -> encode::Res {
+        delegate!(self, write_u8(buf, x))
+    }
+
+    fn write_i16(&mut self, buf: &mut impl BufMut, x: i16) -> encode::Res {
+        delegate!(self, write_i16(buf, x))
+    }
+
+    fn write_i32(&mut self, buf: &mut impl BufMut, x: i32) -> encode::Res {
+        delegate!(self, write_i32(buf, x))
+    }
+
+    fn write_i64(&mut self, buf: &mut impl BufMut, x: i64) -> encode::Res {
+        delegate!(self, write_i64(buf, x))
+    }
+
+    fn write_f32(&mut self, buf: &mut impl BufMut, x: f32) -> encode::Res {
+        delegate!(self, write_f32(buf, x))
+    }
+
+    fn write_f64(&mut self, buf: &mut impl BufMut, x: f64) -> encode::Res {
+        delegate!(self, write_f64(buf, x))
+    }
+
+    fn write_end(&mut self, buf: &mut impl BufMut) -> encode::Res {
+        delegate!(self, write_end(buf))
+    }
+
+    fn write_raw(&mut self, buf: &mut impl BufMut, x: &[u8]) -> encode::Res {
+        delegate!(self, write_raw(buf, x))
+    }
+
+    fn write_string(&mut self, buf: &mut impl BufMut, x: &str) -> encode::Res {
+        delegate!(self, write_string(buf, x))
+    }
+
+    fn encode_string<'a>(&mut self, x: &'a str) -> Result<Cow<'a, [u8]>, ErrorPath<WriteError>> {
+        delegate!(self, encode_string(x))
+    }
+
+    fn write_u8_vec(&mut self, buf: &mut impl BufMut, x: &[u8]) -> encode::Res {
+        delegate!(self, write_u8_vec(buf, x))
+    }
+
+    fn write_i32_vec(&mut self, buf: &mut impl BufMut, x: &[i32]) -> encode::Res {
+        delegate!(self, write_i32_vec(buf, x))
+    }
+
+    fn write_i64_vec(&mut self, buf: &mut impl BufMut, x: &[i64]) -> encode::Res {
+        delegate!(self, write_i64_vec(buf, x))
+    }
+}
+
+/// Attempts to detect the encoding of the NBT data in a buffer.
+///
+/// The data is expected to contain exactly one NBT value, optionally prefixed by the 8-byte header
+/// found in Minecraft: Bedrock Edition's `level.dat` files. In the latter case, the header must be
+/// skipped before the data can be read using the returned encoding.
+///
+/// Detection is heuristic: only the root tag and the first few entries of a root compound tag are
+/// inspected, so the result can be wrong. When the data is plausible in more than one encoding
+/// (for example, an empty compound with an empty name), [BigEndian] is preferred over
+/// [LittleEndian], which is preferred over [NetworkLittleEndian]. Strings are validated using the
+/// default [StringEncoding] of each encoding, so data with strings written using another string
+/// encoding may not be detected.
+///
+/// Returns [None] if the data does not look like NBT in any of the encodings.
+pub fn detect_encoding(data: &[u8]) -> Option<AnyEncoding> {
+    if let (Some(header), Some(rest)) = (data.get(4..8), data.get(8..)) {
+        let len = u32::from_le_bytes(header.try_into().unwrap());
+        if len as usize == rest.len() && rest.first() == Some(&10) {
+            return Some(AnyEncoding::LittleEndian(LittleEndian::default()));
+        }
+    }
+
+    [
+        AnyEncoding::BigEndian(BigEndian::default()),
+        AnyEncoding::LittleEndian(LittleEndian::default()),
+        AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+    ]
+    .into_iter()
+    .find(|e| plausible(e, data).is_some())
+}
+
+/// Checks whether the data could be NBT in the given encoding. Returns [None] if it could not.
+///
+/// Entries of a root compound are checked one by one until either the end of the compound, or a
+/// tag that is too expensive to skip, is found.
+fn plausible(e: &AnyEncoding, data: &[u8]) -> Option<()> {
+    let root = *data.first()?;
+    if !(1..=12).contains(&root) {
+        return None;
+    }
+    let mut pos = skip_string(e, data, 1)?;
+    if root != 10 {
+        return Some(());
+    }
+    loop {
+        let id = *data.get(pos)?;
+        if id == 0 {
+            return (pos + 1 == data.len()).then_some(());
+        }
+        pos = skip_string(e, data, pos + 1)?;
+        pos = match (id, e) {
+            (1, _) => pos + 1,
+            (2, _) => pos + 2,
+            (3, AnyEncoding::NetworkLittleEndian(_)) => skip_varint(data, pos, 5)?,
+            (4, AnyEncoding::NetworkLittleEndian(_)) => skip_varint(data, pos, 10)?,
+            (3 | 5, _) => pos + 4,
+            (4 | 6, _) => pos + 8,
+            (8, _) => skip_string(e, data, pos)?,
+            (7 | 9..=12, _) => return Some(()),
+            _ => return None,
+        };
+    }
+}
+
+/// Skips over a string starting at `pos`, returning the position after it if the string is valid
+/// in the [StringEncoding] of the encoding.
+fn skip_string(e: &AnyEncoding, data: &[u8], pos: usize) -> Option<usize> {
+    let (len, start, settings) = match e {
+        AnyEncoding::BigEndian(v) => (
+            u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().unwrap()) as usize,
+            pos + 2,
+            &v.settings,
+        ),
+        AnyEncoding::LittleEndian(v) => (
+            u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().unwrap()) as usize,
+            pos + 2,
+            &v.settings,
+        ),
+        AnyEncoding::NetworkLittleEndian(v) => {
+            let end = skip_varint(data, pos, 5)?;
+            let mut len = 0;
+            for (i, b) in data[pos..end].iter().enumerate() {
+                len |= ((b & 0x7f) as usize) << (i * 7);
+            }
+            (len, end, &v.settings)
+        }
+    };
+    if len > i16::MAX as usize {
+        return None;
+    }
+    let bytes = data.get(start..start + len)?;
+    settings.decode_string(bytes.to_vec()).ok()?;
+    Some(start + len)
+}
+
+/// The size of the buffer used by [put_array].
+const ARRAY_CHUNK_LEN: usize = 512;
+
+/// Writes the elements of an array in chunks using [BufMut::put_slice], which is faster than
+/// writing each element separately for fixed-size encodings.
+fn put_array<T: Copy, const N: usize>(buf: &mut impl BufMut, x: &[T], to_bytes: fn(T) -> [u8; N]) {
+    let mut chunk = [0; ARRAY_CHUNK_LEN];
+    for elements in x.chunks(ARRAY_CHUNK_LEN / N) {
+        for (i, v) in elements.iter().enumerate() {
+            chunk[i * N..(i + 1) * N].copy_from_slice(&to_bytes(*v));
+        }
+        buf.put_slice(&chunk[..elements.len() * N]);
+    }
+}
+
+/// Skips over a varint of at most `max_len` bytes starting at `pos`, returning the position after
+/// it.
+fn skip_varint(data: &[u8], pos: usize, max_len: usize) -> Option<usize> {
+    for i in 0..max_len {
+        if data.get(pos + i)? & 0x80 == 0 {
+            return Some(pos + i + 1);
+        }
+    }
+    None
+}
+
+/// Test all encodings with various data.
+#[cfg(test)]
+mod tests {
+    use crate::decode::Reader;
+    use crate::encode::Writer;
+    use crate::encoding::{
+        detect_encoding, AnyEncoding, BigEndian, LittleEndian, NetworkLittleEndian, StringEncoding,
+    };
+    use crate::err::{PathPart, ReadError, WriteError};
+    use crate::{tag, NBTTag};
+    use bytes::{Bytes, BytesMut};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_bulk_array_writers() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let ints = [0, 1, -1, i32::MIN, i32::MAX, 0x01020304];
+        // Spans multiple chunks of the buffer used by the bulk writers.
+        let longs = (0..1000).map(|i| i * 0x0102030405).collect::<Vec<i64>>();
+
+        let big_endian = bulk_arrays(&mut BigEndian::default(), &bytes, &ints, &longs);
+        let little_endian = bulk_arrays(&mut LittleEndian::default(), &bytes, &ints, &longs);
+        assert_ne!(big_endian, little_endian);
+    }
+
+    /// Writes the arrays using the bulk array writers of the encoding, checking that the output is
+    /// the same as when writing the length and every element separately.
+    fn bulk_arrays(w: &mut impl Writer, bytes: &[u8], ints: &[i32], longs: &[i64]) -> Vec<u8> {
+        let mut bulk = Vec::new();
+        w.write_u8_vec(&mut bulk, bytes).unwrap();
+        w.write_i32_vec(&mut bulk, ints).unwrap();
+        w.write_i32_vec(&mut bulk, &[]).unwrap();
+        w.write_i64_vec(&mut bulk, longs).unwrap();
+
+        let mut per_element = Vec::new();
+        w.write_array_len(&mut per_element, bytes.len()).unwrap();
+        for v in bytes {
+            w.write_u8(&mut per_element, *v).unwrap();
+        }
+        w.write_array_len(&mut per_element, ints.len()).unwrap();
+        for v in ints {
+            w.write_i32(&mut per_element, *v).unwrap();
+        }
+        w.write_array_len(&mut per_element, 0).unwrap();
+        w.write_array_len(&mut per_element, longs.len()).unwrap();
+        for v in longs {
+            w.write_i64(&mut per_element, *v).unwrap();
+        }
+        assert_eq!(bulk, per_element);
+        bulk
+    }
+
     #[test]
     fn test_big_endian() {
         test::<BigEndian>();
@@ -344,7 +1290,792 @@ mod tests {
         test::<NetworkLittleEndian>();
     }
 
+    #[test]
+    fn test_any_encoding() {
+        let encodings = [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ];
+        for encoding in encodings {
+            let nbt = test_data();
+            let mut buf_writer = BytesMut::default();
+            nbt.write(&mut buf_writer, &mut encoding.clone()).unwrap();
+
+            let mut buf: Bytes = buf_writer.into();
+            assert_eq!(NBTTag::read(&mut buf, &mut encoding.clone()).unwrap(), nbt);
+        }
+
+        // The network encoding must produce the exact same output when wrapped.
+        let nbt = test_data();
+        let mut direct = BytesMut::default();
+        nbt.write(&mut direct, &mut NetworkLittleEndian::default())
+            .unwrap();
+        let mut wrapped = BytesMut::default();
+        nbt.write(
+            &mut wrapped,
+            &mut AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        )
+        .unwrap();
+        assert_eq!(direct, wrapped);
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_int("age", 300)
+                .with_long("seed", -1)
+                // Encoded differently in modified UTF-8, which BigEndian uses by default.
+                .with_string("text", "a\0b\u{1F600}")
+                .build(),
+        );
+        let detect = |mut encoding: AnyEncoding| {
+            let mut buf = BytesMut::default();
+            nbt.write(&mut buf, &mut encoding).unwrap();
+            detect_encoding(&buf)
+        };
+
+        assert!(matches!(
+            detect(AnyEncoding::BigEndian(BigEndian::default())),
+            Some(AnyEncoding::BigEndian(_))
+        ));
+        assert!(matches!(
+            detect(AnyEncoding::LittleEndian(LittleEndian::default())),
+            Some(AnyEncoding::LittleEndian(_))
+        ));
+        assert!(matches!(
+            detect(AnyEncoding::NetworkLittleEndian(
+                NetworkLittleEndian::default()
+            )),
+            Some(AnyEncoding::NetworkLittleEndian(_))
+        ));
+
+        // A Bedrock Edition level.dat header: storage version 10 followed by the data length.
+        let mut level_dat = vec![10, 0, 0, 0, 4, 0, 0, 0];
+        level_dat.extend([10, 0, 0, 0]);
+        assert!(matches!(
+            detect_encoding(&level_dat),
+            Some(AnyEncoding::LittleEndian(_))
+        ));
+
+        assert!(detect_encoding(&[]).is_none());
+        assert!(detect_encoding(&[0x42, 0x00, 0x00]).is_none());
+        assert!(detect_encoding(&[10, 0, 1, 3]).is_none());
+    }
+
+    #[test]
+    fn test_string_encoding() {
+        let str = "a\0b😀";
+        let utf8 = [0x00, 0x07, b'a', 0x00, b'b', 0xf0, 0x9f, 0x98, 0x80];
+        let modified = [
+            0x00, 0x0a, b'a', 0xc0, 0x80, b'b', 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80,
+        ];
+
+        let mut plain = BigEndian::default().string_encoding(StringEncoding::Utf8);
+        let mut buf = BytesMut::default();
+        plain.write_string(&mut buf, str).unwrap();
+        assert_eq!(buf.as_ref(), utf8);
+        assert_eq!(plain.string(&mut utf8.as_ref()).unwrap(), str);
+
+        let mut java = BigEndian::default().string_encoding(StringEncoding::ModifiedUtf8);
+        let mut buf = BytesMut::default();
+        java.write_string(&mut buf, str).unwrap();
+        assert_eq!(buf.as_ref(), modified);
+        assert_eq!(java.string(&mut modified.as_ref()).unwrap(), str);
+
+        // Neither encoding accepts the other's four- or six-byte sequences.
+        assert!(matches!(
+            java.string(&mut utf8.as_ref()).unwrap_err().inner,
+            ReadError::InvalidModifiedUtf8(3)
+        ));
+        assert!(matches!(
+            plain.string(&mut modified.as_ref()).unwrap_err().inner,
+            ReadError::InvalidString(_)
+        ));
+    }
+
+    #[test]
+    fn test_i128() {
+        let values = [
+            0,
+            1,
+            -1,
+            u64::MAX as i128,
+            1 << 64,
+            -(1 << 64),
+            i128::MIN,
+            i128::MAX,
+        ];
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            for x in values {
+                let mut buf = BytesMut::default();
+                encoding.write_i128(&mut buf, x).unwrap();
+                assert_eq!(encoding.i128(&mut buf.as_ref()).unwrap(), x, "{encoding:?}");
+            }
+        }
+
+        // The most significant half comes first, so big endian matches the native encoding.
+        let mut buf = BytesMut::default();
+        BigEndian::default()
+            .write_i128(&mut buf, -(1 << 64) + 5)
+            .unwrap();
+        assert_eq!(buf.as_ref(), (-(1i128 << 64) + 5).to_be_bytes());
+
+        let mut buf = BytesMut::default();
+        LittleEndian::default().write_i128(&mut buf, 1).unwrap();
+        assert_eq!(
+            buf.as_ref(),
+            [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_f64() {
+        let values = [
+            0.0,
+            -0.0,
+            1.5,
+            -std::f64::consts::PI,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::INFINITY,
+        ];
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            for x in values {
+                let mut buf = BytesMut::default();
+                encoding.write_f64(&mut buf, x).unwrap();
+                assert_eq!(buf.len(), 8, "{encoding:?}");
+                let read = encoding.f64(&mut buf.as_ref()).unwrap();
+                assert_eq!(read.to_bits(), x.to_bits(), "{encoding:?}");
+            }
+            let err = encoding.f64(&mut [0; 7].as_slice()).unwrap_err();
+            assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        }
+
+        // Only big endian differs in byte order, as network little endian does not use varints for
+        // floating point numbers.
+        let mut buf = BytesMut::default();
+        BigEndian::default().write_f64(&mut buf, -2.5).unwrap();
+        assert_eq!(buf.as_ref(), (-2.5f64).to_be_bytes());
+        for mut encoding in [
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            let mut buf = BytesMut::default();
+            encoding.write_f64(&mut buf, -2.5).unwrap();
+            assert_eq!(buf.as_ref(), (-2.5f64).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_f16() {
+        let subnormal = f32::from_bits(0x3380_0000);
+        let cases: [(f32, u16); 14] = [
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (0.33325195, 0x3555),
+            (65504.0, 0x7bff),
+            (f32::INFINITY, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00),
+            // The smallest normal number.
+            (6.1035156e-5, 0x0400),
+            // The smallest, a medium and the largest subnormal number.
+            (subnormal, 0x0001),
+            (subnormal * 100.0, 0x0064),
+            (subnormal * 1023.0, 0x03ff),
+            (-subnormal, 0x8001),
+            (f32::NAN, 0x7e00),
+        ];
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            for (x, bits) in cases {
+                let mut buf = BytesMut::default();
+                encoding.write_f16(&mut buf, x).unwrap();
+                assert_eq!(
+                    encoding.clone().i16(&mut buf.as_ref()).unwrap() as u16,
+                    bits
+                );
+                let read = encoding.f16(&mut buf.as_ref()).unwrap();
+                assert_eq!(read.to_bits(), x.to_bits(), "{x} {encoding:?}");
+            }
+        }
+
+        // Numbers are rounded to the nearest half-precision number, with ties to even.
+        let rounded = [
+            (1.0 + 2f32.powi(-11), 0x3c00),
+            (1.0 + 3.0 * 2f32.powi(-11), 0x3c02),
+            (65519.0, 0x7bff),
+            (65520.0, 0x7c00),
+            (1e10, 0x7c00),
+            (subnormal / 2.0, 0x0000),
+            (subnormal * 1.5, 0x0002),
+            (subnormal / 4.0, 0x0000),
+            (subnormal * 1023.5, 0x0400),
+        ];
+        for (x, bits) in rounded {
+            let mut buf = BytesMut::default();
+            BigEndian::default().write_f16(&mut buf, x).unwrap();
+            assert_eq!(buf.as_ref(), u16::to_be_bytes(bits), "{x}");
+        }
+    }
+
+    #[test]
+    fn test_lossy_strings() {
+        let cases: [(&[u8], usize, &str); 5] = [
+            // An unpaired high surrogate, followed by a character.
+            (&[b'a', 0xed, 0xa0, 0xbd, b'b'], 1, "a\u{FFFD}b"),
+            // A high surrogate followed by another high surrogate that is paired.
+            (
+                &[0xed, 0xa0, 0xbd, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80],
+                0,
+                "\u{FFFD}😀",
+            ),
+            // An unpaired low surrogate.
+            (&[0xed, 0xb8, 0x80, b'a'], 0, "\u{FFFD}a"),
+            // A three-byte sequence truncated by the end of the string.
+            (&[b'a', 0xe2, 0x82], 1, "a\u{FFFD}"),
+            // A two-byte sequence truncated by the next character.
+            (&[0xc3, b'a'], 0, "\u{FFFD}a"),
+        ];
+        for (bytes, pos, lossy) in cases {
+            let mut data = vec![0, bytes.len() as u8];
+            data.extend(bytes);
+
+            let mut strict = BigEndian::default();
+            assert!(matches!(
+                strict.string(&mut data.as_slice()).unwrap_err().inner,
+                ReadError::InvalidModifiedUtf8(p) if p == pos
+            ));
+            let mut lenient = BigEndian::default().lossy_strings(true);
+            assert_eq!(lenient.string(&mut data.as_slice()).unwrap(), lossy);
+        }
+
+        let mut lenient = LittleEndian::default().lossy_strings(true);
+        let data = [3, 0, b'a', 0xff, b'b'];
+        assert_eq!(lenient.string(&mut data.as_slice()).unwrap(), "a\u{FFFD}b");
+        assert!(LittleEndian::default()
+            .string(&mut data.as_slice())
+            .is_err());
+    }
+
+    #[test]
+    fn test_default_string_encoding() {
+        let str = "\0😀";
+        let mut buf = BytesMut::default();
+        BigEndian::default().write_string(&mut buf, str).unwrap();
+        assert_eq!(
+            buf.as_ref(),
+            [0x00, 0x08, 0xc0, 0x80, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]
+        );
+
+        let mut buf = BytesMut::default();
+        LittleEndian::default().write_string(&mut buf, str).unwrap();
+        assert_eq!(buf.as_ref(), [0x05, 0x00, 0x00, 0xf0, 0x9f, 0x98, 0x80]);
+
+        let mut buf = BytesMut::default();
+        NetworkLittleEndian::default()
+            .write_string(&mut buf, str)
+            .unwrap();
+        assert_eq!(buf.as_ref(), [0x05, 0x00, 0xf0, 0x9f, 0x98, 0x80]);
+
+        // The default can be overridden.
+        let mut buf = BytesMut::default();
+        LittleEndian::default()
+            .string_encoding(StringEncoding::ModifiedUtf8)
+            .write_string(&mut buf, str)
+            .unwrap();
+        assert_eq!(buf.len(), 10);
+    }
+
+    #[test]
+    fn test_reject_non_finite() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_float("a", f32::NAN)
+                .with_double("b", 1.0)
+                .build(),
+        );
+
+        let mut buf = BytesMut::default();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+        let read = NBTTag::read(&mut buf.freeze(), &mut BigEndian::default()).unwrap();
+        assert!(read.view().at("a").float().unwrap().is_nan());
+
+        let err = nbt
+            .write(
+                &mut BytesMut::default(),
+                &mut BigEndian::default().reject_non_finite(true),
+            )
+            .unwrap_err();
+        assert!(matches!(err.inner, WriteError::NonFiniteFloat(v) if v.is_nan()));
+        assert_eq!(err.path.0, [PathPart::MapKey("a".to_string())]);
+
+        let mut strict = LittleEndian::default().reject_non_finite(true);
+        assert!(strict
+            .write_f64(&mut BytesMut::default(), f64::INFINITY)
+            .is_err());
+        assert!(strict.write_f64(&mut BytesMut::default(), 1.5).is_ok());
+    }
+
+    #[test]
+    fn test_varint_boundaries() {
+        let mut ff = [0xff; 10];
+        ff[9] = 0x01;
+        let mut max_i64 = ff;
+        max_i64[0] = 0xfe;
+
+        let i32_cases: [(i32, &[u8]); 6] = [
+            (0, &[0x00]),
+            (-1, &[0x01]),
+            (1, &[0x02]),
+            (i32::MAX, &[0xfe, 0xff, 0xff, 0xff, 0x0f]),
+            (i32::MIN, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+            (i32::MIN + 1, &[0xfd, 0xff, 0xff, 0xff, 0x0f]),
+        ];
+        for (x, expected) in i32_cases {
+            let mut buf = BytesMut::default();
+            NetworkLittleEndian::default()
+                .write_i32(&mut buf, x)
+                .unwrap();
+            assert_eq!(&buf[..], expected, "{x}");
+            let read = NetworkLittleEndian::default().i32(&mut buf.freeze());
+            assert_eq!(read.unwrap(), x);
+        }
+
+        let i64_cases: [(i64, &[u8]); 6] = [
+            (0, &[0x00]),
+            (-1, &[0x01]),
+            (1, &[0x02]),
+            (i64::MAX, &max_i64),
+            (i64::MIN, &ff),
+            (i32::MIN as i64, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ];
+        for (x, expected) in i64_cases {
+            let mut buf = BytesMut::default();
+            NetworkLittleEndian::default()
+                .write_i64(&mut buf, x)
+                .unwrap();
+            assert_eq!(&buf[..], expected, "{x}");
+            let read = NetworkLittleEndian::default().i64(&mut buf.freeze());
+            assert_eq!(read.unwrap(), x);
+        }
+    }
+
+    #[test]
+    fn test_canonical_varints() {
+        let mut strict = NetworkLittleEndian::default().canonical_varints(true);
+        // 1 encoded using two and five bytes, and -1 with a bit that does not fit in an int.
+        let non_canonical: [&[u8]; 3] = [
+            &[0x82, 0x00],
+            &[0x82, 0x80, 0x80, 0x80, 0x00],
+            &[0x81, 0x80, 0x80, 0x80, 0x10],
+        ];
+        for data in non_canonical {
+            let err = strict.i32(&mut &data[..]).unwrap_err();
+            assert!(
+                matches!(err.inner, ReadError::NonCanonicalVarint),
+                "{data:?}"
+            );
+            let err = strict.string_len(&mut &data[..]).unwrap_err();
+            assert!(
+                matches!(err.inner, ReadError::NonCanonicalVarint),
+                "{data:?}"
+            );
+            // The data is accepted without the option.
+            assert!(NetworkLittleEndian::default().i32(&mut &data[..]).is_ok());
+        }
+        let mut ff = [0xff; 10];
+        ff[9] = 0x03;
+        let err = strict.i64(&mut &ff[..]).unwrap_err();
+        assert!(matches!(err.inner, ReadError::NonCanonicalVarint));
+        let err = strict.i64(&mut [0x80, 0x00].as_slice()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::NonCanonicalVarint));
+
+        // All varints that are written are canonical.
+        for x in [0, 1, -1, 64, -65, i32::MAX, i32::MIN] {
+            let mut buf = BytesMut::default();
+            strict.write_i32(&mut buf, x).unwrap();
+            assert_eq!(strict.i32(&mut buf.freeze()).unwrap(), x);
+        }
+        for x in [0, i64::MAX, i64::MIN] {
+            let mut buf = BytesMut::default();
+            strict.write_i64(&mut buf, x).unwrap();
+            assert_eq!(strict.i64(&mut buf.freeze()).unwrap(), x);
+        }
+        let nbt = test_data();
+        let mut buf = BytesMut::default();
+        nbt.write(&mut buf, &mut strict).unwrap();
+        assert_eq!(NBTTag::read(&mut buf.freeze(), &mut strict).unwrap(), nbt);
+    }
+
+    #[test]
+    fn test_array_round_trip() {
+        let encodings = [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ];
+        // Lengths of 63/64 and 127/128 lie on the boundaries of the variable-length encoding.
+        let lengths = [0, 1, 63, 64, 127, 128, 300];
+        let arrays = lengths.iter().flat_map(|&len| {
+            [
+                NBTTag::ByteArray((0..len).map(|i| (i * 7) as u8).collect::<Vec<_>>().into()),
+                NBTTag::IntArray(
+                    (0..len)
+                        .map(|i| [i32::MIN, -1, 0, 1, i32::MAX][i % 5])
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+                NBTTag::LongArray(
+                    (0..len)
+                        .map(|i| [i64::MIN, -1, 0, 1, i64::MAX][i % 5])
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+            ]
+        });
+
+        for array in arrays {
+            for encoding in &encodings {
+                let mut buf = BytesMut::default();
+                array.write(&mut buf, &mut encoding.clone()).unwrap();
+                assert_eq!(
+                    NBTTag::read(&mut buf.freeze(), &mut encoding.clone()).unwrap(),
+                    array
+                );
+            }
+        }
+
+        // The network encoding encodes array lengths as zigzag varints.
+        let mut buf = BytesMut::default();
+        NBTTag::ByteArray(vec![0; 64].into())
+            .write(&mut buf, &mut NetworkLittleEndian::default())
+            .unwrap();
+        assert_eq!(buf[..4], [7, 0, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_network_array_lengths() {
+        let cases: [(NBTTag, &[u8]); 5] = [
+            (NBTTag::ByteArray(vec![1, 2].into()), &[7, 0, 0x04, 1, 2]),
+            (
+                NBTTag::IntArray(vec![1, -1].into()),
+                &[11, 0, 0x04, 0x02, 0x01],
+            ),
+            (
+                NBTTag::LongArray(vec![300].into()),
+                &[12, 0, 0x02, 0xd8, 0x04],
+            ),
+            (
+                NBTTag::List(vec![tag::Byte(1), tag::Byte(2)].into()),
+                &[9, 0, 1, 0x04, 1, 2],
+            ),
+            (NBTTag::String("ab".into()), &[8, 0, 0x02, b'a', b'b']),
+        ];
+        for (nbt, expected) in cases {
+            let buf = nbt.to_bytes(&mut NetworkLittleEndian::default()).unwrap();
+            assert_eq!(buf, expected, "{nbt:?}");
+            let read = NBTTag::read(&mut &buf[..], &mut NetworkLittleEndian::default());
+            assert_eq!(read.unwrap(), nbt);
+        }
+
+        // A fixed-size length is not accepted: the first byte of a length of 2 is read as a
+        // zigzag varint of 1, after which the next byte of the length is read as the element.
+        let fixed = [11, 0, 0x02, 0, 0, 0, 0x02, 0x01];
+        let read = NBTTag::read(&mut &fixed[..], &mut NetworkLittleEndian::default()).unwrap();
+        assert_eq!(read, NBTTag::IntArray(vec![0].into()));
+    }
+
+    #[test]
+    fn test_key_comparator() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_byte("a", 1)
+                .with_byte("c", 3)
+                .with_byte("b", 2)
+                .build(),
+        );
+        let mut encoding =
+            AnyEncoding::BigEndian(BigEndian::default().key_comparator(|a, b| b.cmp(a)));
+        let mut buf = BytesMut::default();
+        nbt.write(&mut buf, &mut encoding).unwrap();
+        assert_eq!(
+            buf.as_ref(),
+            [10, 0, 0, 1, 0, 1, b'c', 3, 1, 0, 1, b'b', 2, 1, 0, 1, b'a', 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_max_compound_entries() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound(
+                    "a",
+                    (0..5)
+                        .map(|i| (i.to_string(), NBTTag::Int(i.into())))
+                        .collect::<HashMap<_, _>>(),
+                )
+                .build(),
+        );
+        let mut buf = BytesMut::default();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+
+        let mut r = AnyEncoding::BigEndian(BigEndian::default().max_compound_entries(5));
+        assert_eq!(NBTTag::read_slice(&buf, &mut r).unwrap(), nbt);
+
+        let mut r = AnyEncoding::BigEndian(BigEndian::default().max_compound_entries(4));
+        let err = NBTTag::read_slice(&buf, &mut r).unwrap_err();
+        assert!(matches!(err.inner, ReadError::SeqLengthViolation(4, 5)));
+        assert_eq!(err.path.0, [PathPart::MapKey("a".to_string())]);
+    }
+
+    #[test]
+    fn test_max_string_len() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "a".repeat(300))
+                .build(),
+        );
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default().max_string_len(300)),
+            AnyEncoding::LittleEndian(LittleEndian::default().max_string_len(300)),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default().max_string_len(300)),
+        ] {
+            let buf = nbt.to_bytes(&mut encoding).unwrap();
+            assert_eq!(NBTTag::read_slice(&buf, &mut encoding).unwrap(), nbt);
+        }
+
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default().max_string_len(256)),
+            AnyEncoding::LittleEndian(LittleEndian::default().max_string_len(256)),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default().max_string_len(256)),
+        ] {
+            let buf = nbt.to_bytes(&mut encoding).unwrap();
+            let err = NBTTag::read_slice(&buf, &mut encoding).unwrap_err();
+            assert!(matches!(err.inner, ReadError::SeqLengthViolation(256, 300)));
+            assert_eq!(err.path.0, [PathPart::MapKey("name".to_string())]);
+        }
+
+        // The limit also applies to the names of tags.
+        let mut r = BigEndian::default().max_string_len(3);
+        let err = NBTTag::read_slice(&nbt.to_bytes(&mut r.clone()).unwrap(), &mut r).unwrap_err();
+        assert!(matches!(err.inner, ReadError::SeqLengthViolation(3, 4)));
+    }
+
+    #[test]
+    fn test_lenient_string_lengths() {
+        // A compound with a string entry "a" whose value has a length of -1, then an int "b".
+        let buf = [
+            10, 0, 0, 8, 0, 1, b'a', 0xFF, 0xFF, 3, 0, 1, b'b', 0, 0, 0, 1, 0,
+        ];
+
+        let err = NBTTag::read_slice(&buf, &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(
+            err.inner,
+            ReadError::SeqLengthViolation(32767, usize::MAX)
+        ));
+        assert_eq!(err.path.0, [PathPart::MapKey("a".to_string())]);
+
+        let mut r = BigEndian::default().lenient_string_lengths(true);
+        let nbt = NBTTag::read_slice(&buf, &mut r).unwrap();
+        assert_eq!(
+            nbt,
+            NBTTag::Compound(
+                tag::Compound::builder()
+                    .with_string("a", "")
+                    .with_int("b", 1)
+                    .build()
+            )
+        );
+    }
+
+    #[test]
+    fn test_nbt_explorer() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("b", 1)
+                .with_list("A", tag::List::with_type(3, vec![]).unwrap())
+                .with_byte("a", 2)
+                .build(),
+        );
+        let mut buf = BytesMut::default();
+        nbt.write(&mut buf, &mut BigEndian::default().nbt_explorer())
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            10, 0, 0,
+            9, 0, 1, b'A', 0, 0, 0, 0, 0,
+            1, 0, 1, b'a', 2,
+            3, 0, 1, b'b', 0, 0, 0, 1,
+            0,
+        ];
+        assert_eq!(&buf[..], expected);
+    }
+
+    #[test]
+    fn test_read_into_reused_buffer() {
+        let mut w = AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default());
+        let mut buf = BytesMut::default();
+        w.write_string(&mut buf, "hello").unwrap();
+        w.write_string(&mut buf, "hi").unwrap();
+        w.write_u8_vec(&mut buf, &[1, 2, 3]).unwrap();
+        w.write_u8_vec(&mut buf, &[4]).unwrap();
+
+        let mut r = w;
+        let mut buf = buf.freeze();
+        let mut s = String::with_capacity(64);
+        let ptr = s.as_ptr();
+        r.string_into(&mut buf, &mut s).unwrap();
+        assert_eq!(s, "hello");
+        r.string_into(&mut buf, &mut s).unwrap();
+        assert_eq!(s, "hi");
+        assert_eq!(s.as_ptr(), ptr);
+        assert_eq!(s.capacity(), 64);
+
+        let mut vec = Vec::with_capacity(64);
+        let ptr = vec.as_ptr();
+        r.u8_vec_into(&mut buf, &mut vec).unwrap();
+        assert_eq!(vec, [1, 2, 3]);
+        r.u8_vec_into(&mut buf, &mut vec).unwrap();
+        assert_eq!(vec, [4]);
+        assert_eq!(vec.as_ptr(), ptr);
+        assert_eq!(vec.capacity(), 64);
+    }
+
+    #[cfg(feature = "extended-lengths")]
+    #[test]
+    fn test_extended_lengths() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_byte_array("bytes", vec![1, 2, 3])
+                .with_long_array("longs", vec![i64::MIN, i64::MAX])
+                .build(),
+        );
+        for encoding in [
+            AnyEncoding::BigEndian(BigEndian::default().extended_lengths(true)),
+            AnyEncoding::LittleEndian(LittleEndian::default().extended_lengths(true)),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default().extended_lengths(true)),
+        ] {
+            let mut buf = BytesMut::default();
+            nbt.write(&mut buf, &mut encoding.clone()).unwrap();
+            assert_eq!(
+                NBTTag::read(&mut buf.freeze(), &mut encoding.clone()).unwrap(),
+                nbt
+            );
+        }
+
+        // The lengths of both the empty name and the array are written as 64-bit integers, so the
+        // data is not read correctly without the option.
+        let array = NBTTag::ByteArray(vec![7].into());
+        let mut buf = BytesMut::default();
+        array
+            .write(&mut buf, &mut BigEndian::default().extended_lengths(true))
+            .unwrap();
+        assert_eq!(
+            &buf[..],
+            [7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 7]
+        );
+        assert_ne!(
+            NBTTag::read(&mut buf.freeze(), &mut BigEndian::default()).ok(),
+            Some(array)
+        );
+    }
+
+    #[cfg(feature = "omit-root-end")]
+    #[test]
+    fn test_omit_root_end() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("a", 1)
+                .with_compound("b", tag::Compound::builder().with_byte("c", 2).build())
+                .build(),
+        );
+        for (encoding, default) in [
+            (
+                AnyEncoding::BigEndian(BigEndian::default().omit_root_end(true)),
+                AnyEncoding::BigEndian(BigEndian::default()),
+            ),
+            (
+                AnyEncoding::LittleEndian(LittleEndian::default().omit_root_end(true)),
+                AnyEncoding::LittleEndian(LittleEndian::default()),
+            ),
+            (
+                AnyEncoding::NetworkLittleEndian(
+                    NetworkLittleEndian::default().omit_root_end(true),
+                ),
+                AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+            ),
+        ] {
+            let mut buf = BytesMut::default();
+            nbt.write(&mut buf, &mut encoding.clone()).unwrap();
+            let mut full = BytesMut::default();
+            nbt.write(&mut full, &mut default.clone()).unwrap();
+            // Only the END tag of the root compound is left out.
+            assert_eq!(&buf[..], &full[..full.len() - 1]);
+            assert_eq!(
+                NBTTag::read(&mut buf.clone().freeze(), &mut encoding.clone()).unwrap(),
+                nbt
+            );
+            // Data that does include the END tag can still be read.
+            assert_eq!(
+                NBTTag::read(&mut full.freeze(), &mut encoding.clone()).unwrap(),
+                nbt
+            );
+            let err = NBTTag::read(&mut buf.freeze(), &mut default.clone()).unwrap_err();
+            assert!(matches!(err.inner, ReadError::MissingEndTag));
+        }
+    }
+
+    #[test]
+    fn test_sorted_keys() {
+        let rewrite = |bytes: &[u8]| {
+            let nbt = NBTTag::read_slice(bytes, &mut LittleEndian::default()).unwrap();
+            let mut buf = BytesMut::default();
+            nbt.write(&mut buf, &mut LittleEndian::default().sorted_keys())
+                .unwrap();
+            buf
+        };
+
+        let nbt = test_data();
+        let mut original = BytesMut::default();
+        nbt.write(&mut original, &mut LittleEndian::default())
+            .unwrap();
+        let first = rewrite(&original);
+        let second = rewrite(&first);
+        assert_eq!(first, second);
+        assert_eq!(
+            NBTTag::read_slice(&second, &mut LittleEndian::default()).unwrap(),
+            nbt
+        );
+    }
+
     fn test<T: Reader + Writer + Sized + Default>() {
+        let nbt = test_data();
+        let mut buf_writer = BytesMut::default();
+        nbt.write(&mut buf_writer, &mut T::default()).unwrap();
+
+        let mut buf: Bytes = buf_writer.into();
+        assert_eq!(NBTTag::read(&mut buf, &mut T::default()).unwrap(), nbt);
+    }
+
+    fn test_data() -> NBTTag {
         let nbt = tag::Compound::builder()
             .with_long("test", 10)
             .with_byte("test1", 100)
@@ -355,11 +2086,6 @@ mod tests {
             )
             .with_list("test4", vec![tag::Byte(1), tag::Byte(3)])
             .with("test5", tag::Compound::default());
-        let nbt = NBTTag::Compound(nbt.build());
-        let mut buf_writer = BytesMut::default();
-        nbt.write(&mut buf_writer, &mut T::default()).unwrap();
-
-        let mut buf: Bytes = buf_writer.into();
-        assert_eq!(NBTTag::read(&mut buf, &mut T::default()).unwrap(), nbt);
+        NBTTag::Compound(nbt.build())
     }
 }
@@ -1,20 +1,29 @@
 //! See [NbtError].
 use std::collections::VecDeque;
 use std::error::Error;
-use std::fmt::{Debug, Display, Formatter};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
+use crate::{tag, NBTTagType};
+
 /// An error that can occur while reading NBT data from a buffer.
 #[derive(Error, Debug)]
 pub enum ReadError {
     /// Occurs when the buffer is smaller than the expected size.
     #[error("unexpectedly reached end of buffer")]
     UnexpectedEOF,
+    /// Occurs when the buffer is empty before reading a root tag, meaning that there is no data
+    /// rather than corrupt data. Truncated data results in [ReadError::UnexpectedEOF] instead.
+    #[error("buffer is empty")]
+    EmptyInput,
     /// Occurs when the reader finds a tag type while reading that is not part of the expected tag
     /// types.
     #[error("expected tag {0}, found {1}")]
     UnexpectedTag(String, String),
+    /// Occurs when the reader finds a tag id that does not correspond to any NBT tag type.
+    #[error("unknown tag type {0:#04x}")]
+    UnknownTagType(u8),
     /// The length prefix found in the buffer for a sequence is not in the acceptable bounds for
     /// that type.
     #[error("sequence length must be between 0 and {0}, but got {1}")]
@@ -22,6 +31,26 @@ pub enum ReadError {
     /// A byte sequence could not be read as a valid UTF-8 byte sequence.
     #[error("could not decode string: {0}")]
     InvalidString(#[from] FromUtf8Error),
+    /// A byte sequence could not be read as a valid modified UTF-8 byte sequence. Contains the
+    /// offset of the first invalid byte.
+    #[error("could not decode modified UTF-8 string: invalid sequence at byte {0}")]
+    InvalidModifiedUtf8(usize),
+    /// The buffer ended exactly where the `END` tag of a compound tag was expected, meaning that
+    /// the data is complete apart from the terminating `END` tags.
+    #[error("buffer ended before the end of the compound")]
+    MissingEndTag,
+    /// A varint was not encoded in the shortest possible form, or had bits set that do not fit in
+    /// the integer. Only returned by readers that enforce canonical varints, such as
+    /// [crate::encoding::NetworkLittleEndian] with the `canonical_varints` option.
+    #[error("varint is not canonically encoded")]
+    NonCanonicalVarint,
+    /// Compound and list tags are nested deeper than the maximum depth of the reader. See
+    /// [crate::decode::Reader::max_depth].
+    #[error("nesting exceeds the maximum depth of {0}")]
+    TooDeep(usize),
+    /// Reading was cancelled by the reader, for example because a cancellation token was set.
+    #[error("reading was cancelled")]
+    Cancelled,
     /// A custom variant for errors other than the provided variants.
     #[error("{0}")]
     Custom(String),
@@ -33,15 +62,160 @@ pub enum WriteError {
     /// Occurs when a list is made up of NBT tags with differing types.
     #[error("expected tag {0}, found {1}")]
     UnexpectedTag(String, String),
+    /// Occurs when writing a [crate::tag::Custom] tag with an id that the writer does not support.
+    #[error("unknown tag type {0:#04x}")]
+    UnknownTagType(u8),
     /// The length of a  sequence (such as list or string) is not in the acceptable bounds for that
     /// type.
     #[error("sequence length must be between 0 and {0}, but got {1}")]
     SeqLengthViolation(usize, usize),
+    /// A floating point number is NaN or infinite while the encoding does not allow it.
+    #[error("floating point number must be finite, but got {0}")]
+    NonFiniteFloat(f64),
+    /// The amount of elements added to a list by a [crate::stream::StreamWriter] does not match
+    /// the length the list was begun with.
+    #[error("list was declared with {declared} elements, but got {actual}")]
+    ListLengthMismatch {
+        /// The length the list was begun with.
+        declared: usize,
+        /// The amount of elements added to the list.
+        actual: usize,
+    },
+    /// Writing into a [std::io::Write] failed, such as with
+    /// [NBTTag::write_io](crate::NBTTag::write_io).
+    #[error("could not write to the output after {written} bytes: {source}")]
+    Io {
+        /// The amount of bytes that were written successfully before the error occurred.
+        written: usize,
+        /// The error returned by the output.
+        source: std::io::Error,
+    },
+    /// A method of a [crate::stream::StreamWriter] was called in a state that does not allow it,
+    /// such as ending a compound while a list is open.
+    #[error("invalid stream: {0}")]
+    InvalidStream(String),
     /// A custom variant for errors other than the provided variants.
     #[error("{0}")]
     Custom(String),
 }
 
+/// An error that can occur while constructing a [crate::tag::List].
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum ListError {
+    /// The declared element type id does not correspond to any NBT tag type.
+    #[error("unknown tag type {0:#04x}")]
+    UnknownTagType(u8),
+    /// An element in the list does not match the declared element type id.
+    #[error("expected tag {0:#04x}, found {1:#04x}")]
+    UnexpectedTag(u8, u8),
+}
+
+/// An error that can occur while building a [crate::tag::Compound] from dotted paths.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum PathError {
+    /// A value in the path is not a compound tag, so no entries can be added to it.
+    #[error("expected tag {}, found {0}", NBTTagType::Compound)]
+    NotACompound(NBTTagType),
+    /// A value already exists at the path.
+    #[error("a value already exists at this path")]
+    Occupied,
+}
+
+/// An error that can occur while checking a tag against a [crate::schema::Schema].
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum SchemaError {
+    /// A key that is required by the schema is missing.
+    #[error("missing required key")]
+    MissingKey,
+    /// A tag does not have the type that the schema expects.
+    #[error("expected tag {0}, found {1}")]
+    UnexpectedTag(NBTTagType, NBTTagType),
+}
+
+/// An error returned by [NBTTag::write_validated](crate::NBTTag::write_validated).
+#[derive(Error, Debug)]
+pub enum ValidatedWriteError {
+    /// The tag does not match the schema, so nothing was written. Contains all mismatches.
+    #[error("tag does not match the schema ({} mismatches)", .0.len())]
+    Schema(Vec<ErrorPath<SchemaError>>),
+    /// The tag matches the schema, but could not be written.
+    #[error(transparent)]
+    Write(#[from] ErrorPath<WriteError>),
+}
+
+/// An error that can occur while applying a [crate::patch::Patch] to a tag.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum PatchError {
+    /// No tag exists at the path, or the index of an added list element is out of bounds.
+    #[error("missing tag")]
+    MissingTag,
+    /// An entry that is added to a compound already exists.
+    #[error("a value already exists at this path")]
+    Occupied,
+    /// A tag in the path is not a compound or a list, so it contains no tags.
+    #[error("expected tag {0}, found {1}")]
+    UnexpectedTag(NBTTagType, NBTTagType),
+    /// The tag the patch is applied to cannot be added or removed, only replaced.
+    #[error("the root tag can only be replaced")]
+    RootPath,
+}
+
+/// An error that can occur while looking up a tag of an expected type, such as with
+/// [NBTTag::type_check](crate::NBTTag::type_check).
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum TypeError {
+    /// No tag exists at the path.
+    #[error("missing tag")]
+    MissingTag,
+    /// A tag does not have the expected type.
+    #[error("expected tag {0}, found {1}")]
+    UnexpectedTag(NBTTagType, NBTTagType),
+}
+
+/// The error returned when converting a tag into a rust type using [crate::convert::FromNbt].
+pub type NbtConvertError = ErrorPath<TypeError>;
+
+/// An error that can occur while parsing SNBT. Positions are byte offsets into the input.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum SnbtError {
+    /// The input ended before the value was complete.
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+    /// A character was found where it is not allowed.
+    #[error("unexpected character `{1}` at position {0}")]
+    UnexpectedChar(usize, char),
+    /// A quoted string contains an escape sequence other than `\\` or an escaped quote.
+    #[error("invalid escape sequence at position {0}")]
+    InvalidEscape(usize),
+    /// An element of a list or array does not have the type of the other elements.
+    #[error("expected tag {1}, found {2} at position {0}")]
+    UnexpectedTag(usize, NBTTagType, NBTTagType),
+    /// Compounds and lists are nested deeper than the maximum depth.
+    #[error("nesting exceeds the maximum depth of {0}")]
+    TooDeep(usize),
+}
+
+/// An error that can occur while converting a tag to SNBT using a
+/// [crate::snbt::Serializer].
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum SnbtWriteError {
+    /// A [crate::tag::Raw] tag was found. Its payload is already encoded, so its value is unknown.
+    #[error("raw tags cannot be represented in SNBT")]
+    RawTag,
+    /// A [crate::tag::Custom] tag was found. Custom tag types have no SNBT representation.
+    #[error("custom tag type {:#04x} cannot be represented in SNBT", .0.get())]
+    CustomTag(tag::CustomId),
+    /// The output could not be written to.
+    #[error("could not write to the output")]
+    Fmt(#[from] fmt::Error),
+}
+
+impl From<fmt::Error> for ErrorPath<SnbtWriteError> {
+    fn from(err: fmt::Error) -> Self {
+        Self::new(err.into())
+    }
+}
+
 /// A generic wrapper that gives a [Path] to an error type.
 pub struct ErrorPath<I> {
     /// The inner element that the wrapper wraps around.
@@ -159,6 +333,9 @@ impl Display for Path {
 pub enum PathPart {
     /// The path part is a map key.
     MapKey(String),
+    /// The path part is the key of a map entry itself rather than its value, such as when the key
+    /// could not be written.
+    Key(String),
     /// The path part is a field in a struct.
     Field(String),
     /// THe path part is a field of a tuple.
@@ -171,6 +348,10 @@ impl Display for PathPart {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             PathPart::MapKey(v) => f.write_str(v),
+            PathPart::Key(v) => {
+                f.write_str(v)?;
+                f.write_str(" (key)")
+            }
             PathPart::Field(v) => f.write_str(v),
             PathPart::Element(v) => {
                 f.write_str("[")?;
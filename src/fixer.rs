@@ -0,0 +1,188 @@
+//! Basic version-aware upgrading of NBT data, similar to Mojang's DataFixerUpper.
+//!
+//! A [Fixer] upgrades data written by versions older than a certain data version. Fixers are
+//! applied to data using [apply_fixers], which reads the data version from the `DataVersion` key
+//! in the root compound, the same key Minecraft: Java Edition uses.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::{fixer::{apply_fixers, Fixer}, tag, NBTTag};
+//! let fixers = [Fixer::new(100, "Player", |player| {
+//!     if let NBTTag::Compound(player) = player {
+//!         if let Some(name) = player.remove("name") {
+//!             player.insert("Name".to_string(), name);
+//!         }
+//!     }
+//! })];
+//!
+//! let mut nbt = NBTTag::Compound(
+//!     tag::Compound::builder()
+//!         .with_int("DataVersion", 99)
+//!         .with_compound("Player", tag::Compound::builder().with_string("name", "Zuri"))
+//!         .build(),
+//! );
+//! apply_fixers(&mut nbt, &fixers);
+//! assert_eq!(nbt.view().at("Player").at("Name").string(), Ok("Zuri"));
+//! ```
+use std::fmt::{Debug, Formatter};
+
+use crate::{tag, NBTTag};
+
+/// The key in the root compound that holds the data version.
+pub const DATA_VERSION_KEY: &str = "DataVersion";
+
+/// A transformation of NBT data that is applied to data older than a certain data version.
+pub struct Fixer {
+    version: i32,
+    path: Vec<String>,
+    transform: Box<dyn Fn(&mut NBTTag) + Send + Sync>,
+}
+
+impl Fixer {
+    /// Creates a new fixer that applies the transform to data with a data version lower than
+    /// `version`.
+    ///
+    /// The transform is called for every tag matching the dotted path, such as `Level.Entities`.
+    /// A `*` in the path matches every entry of a compound or every element of a list, and a
+    /// number matches the list element with that index. An empty path matches the root tag.
+    pub fn new(
+        version: i32,
+        path: &str,
+        transform: impl Fn(&mut NBTTag) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            version,
+            path: if path.is_empty() {
+                Vec::new()
+            } else {
+                path.split('.').map(str::to_string).collect()
+            },
+            transform: Box::new(transform),
+        }
+    }
+
+    /// The data version that the fixer upgrades data to.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Calls the transform for all tags matching the path, starting at the provided segment.
+    fn apply(&self, nbt: &mut NBTTag, path: &[String]) {
+        let Some((segment, rest)) = path.split_first() else {
+            (self.transform)(nbt);
+            return;
+        };
+        match nbt {
+            NBTTag::Compound(c) if segment == "*" => {
+                c.0.values_mut().for_each(|v| self.apply(v, rest));
+            }
+            NBTTag::Compound(c) => {
+                if let Some(v) = c.0.get_mut(segment) {
+                    self.apply(v, rest);
+                }
+            }
+            NBTTag::List(l) if segment == "*" => {
+                l.0.iter_mut().for_each(|v| self.apply(v, rest));
+            }
+            NBTTag::List(l) => {
+                if let Some(v) = segment.parse().ok().and_then(|i: usize| l.0.get_mut(i)) {
+                    self.apply(v, rest);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Debug for Fixer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fixer")
+            .field("version", &self.version)
+            .field("path", &self.path.join("."))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Upgrades NBT data by applying all fixers for versions newer than the data version of the data.
+///
+/// The data version is read from the [DATA_VERSION_KEY] key in the root compound. Data without a
+/// data version is considered to be older than all fixers. The fixers are applied in order of
+/// their version, where fixers with the same version are applied in the order they were provided.
+/// Afterwards, the data version is updated to the highest version of the applied fixers.
+pub fn apply_fixers(nbt: &mut NBTTag, fixers: &[Fixer]) {
+    let data_version = match nbt {
+        NBTTag::Compound(c) => match c.get(DATA_VERSION_KEY) {
+            Some(NBTTag::Int(v)) => v.0,
+            _ => i32::MIN,
+        },
+        _ => i32::MIN,
+    };
+
+    let mut pending: Vec<&Fixer> = fixers.iter().filter(|f| f.version > data_version).collect();
+    pending.sort_by_key(|f| f.version);
+    for fixer in &pending {
+        fixer.apply(nbt, &fixer.path);
+    }
+
+    if let (Some(last), NBTTag::Compound(c)) = (pending.last(), nbt) {
+        c.0.insert(DATA_VERSION_KEY.to_string(), tag::Int(last.version).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixer::{apply_fixers, Fixer};
+    use crate::{tag, NBTTag};
+
+    fn rename(old: &'static str, new: &'static str) -> impl Fn(&mut NBTTag) + Send + Sync {
+        move |nbt| {
+            if let NBTTag::Compound(c) = nbt {
+                if let Some(v) = c.remove(old) {
+                    c.insert(new.to_string(), v);
+                }
+            }
+        }
+    }
+
+    fn data(version: i32) -> NBTTag {
+        NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("DataVersion", version)
+                .with_list(
+                    "Inventory",
+                    vec![
+                        tag::Compound::builder().with_string("id", "stone").build(),
+                        tag::Compound::builder().with_string("id", "dirt").build(),
+                    ],
+                )
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_apply_fixers() {
+        let fixers = [
+            Fixer::new(200, "Inventory.*", rename("Name", "item")),
+            Fixer::new(100, "Inventory.*", rename("id", "Name")),
+        ];
+
+        let mut old = data(50);
+        apply_fixers(&mut old, &fixers);
+        let view = old.view();
+        assert_eq!(view.at("DataVersion").int(), Ok(200));
+        assert_eq!(view.at("Inventory").at(0).at("item").string(), Ok("stone"));
+        assert_eq!(view.at("Inventory").at(1).at("item").string(), Ok("dirt"));
+
+        // Only the second fixer applies to data that is already at version 100.
+        let mut newer = data(100);
+        apply_fixers(&mut newer, &fixers);
+        assert_eq!(
+            newer.view().at("Inventory").at(0).at("id").string(),
+            Ok("stone")
+        );
+
+        let mut current = data(200);
+        apply_fixers(&mut current, &fixers);
+        assert_eq!(current, data(200));
+    }
+}
@@ -0,0 +1,69 @@
+//! Conversion between `f32` and the IEEE 754 half-precision floating point format.
+//!
+//! Half-precision numbers have a sign bit, 5 exponent bits and 10 mantissa bits. They are not an
+//! NBT type, but are read and written as the bits of a 16-bit integer.
+
+/// Converts the bits of a half-precision number into an `f32`. The conversion is exact.
+pub(crate) fn to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mant = (bits & 0x3ff) as u32;
+    match exp {
+        // Zero and subnormal numbers, which are a multiple of the smallest subnormal number.
+        0 => {
+            let v = mant as f32 * f32::from_bits(0x3380_0000);
+            f32::from_bits(v.to_bits() | sign)
+        }
+        // Infinity and NaN, keeping the payload of NaNs.
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mant << 13)),
+        _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (mant << 13)),
+    }
+}
+
+/// Converts an `f32` into the bits of the nearest half-precision number, rounding ties to even.
+///
+/// Numbers too large to be represented become infinity, and numbers too small to be represented
+/// become zero. NaNs remain NaN.
+pub(crate) fn from_f32(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        if mant == 0 {
+            return sign | 0x7c00;
+        }
+        // The quiet bit is set so that the NaN does not become infinity if the payload is lost.
+        return sign | 0x7e00 | (mant >> 13) as u16;
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exp <= 0 {
+        // Less than half of the smallest subnormal number, which rounds to zero.
+        if exp < -10 {
+            return sign;
+        }
+        let mant = mant | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        return sign | round(mant, shift) as u16;
+    }
+    // Rounding up may carry into the exponent, which correctly results in the next power of two
+    // or in infinity.
+    sign | round(((exp as u32) << 23) | mant, 13) as u16
+}
+
+/// Shifts the bits to the right, rounding to the nearest value with ties to even.
+fn round(bits: u32, shift: u32) -> u32 {
+    let v = bits >> shift;
+    let rem = bits & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if rem > half || (rem == half && v & 1 == 1) {
+        v + 1
+    } else {
+        v
+    }
+}
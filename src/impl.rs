@@ -2,11 +2,13 @@
 //! useful traits and methods.
 use crate::decode::Reader;
 use crate::encode::Writer;
-use crate::err::{ErrorPath, ReadError};
+use crate::encoding::BigEndian;
+use crate::err::{ErrorPath, Path, PathPart, ReadError, TypeError};
 use crate::{decode, encode, tag, NBTTag, NBTTagType};
 use bytes::{Buf, BufMut};
-use std::collections::HashMap;
+use std::collections::{hash_map, HashMap};
 use std::ops::{Deref, DerefMut};
+use std::slice;
 
 macro_rules! impl_enum_conv {
     ($typ:ty, $enum_variant:path) => {
@@ -48,11 +50,38 @@ impl_enum_conv!(
     (tag::LongArray, NBTTag::LongArray),
 );
 
+/// Extracts the elements of an array tag by value, returning the tag itself if it is of another
+/// type. For example, `Vec::<i32>::try_from(tag)` succeeds for [NBTTag::IntArray]s.
+macro_rules! impl_array_conv {
+    ($typ:ty, $enum_variant:path) => {
+        impl TryFrom<NBTTag> for $typ {
+            type Error = NBTTag;
+
+            fn try_from(value: NBTTag) -> Result<Self, Self::Error> {
+                if let $enum_variant(v) = value {
+                    Ok(v.0)
+                } else {
+                    Err(value)
+                }
+            }
+        }
+    };
+    ($(($typ:ty, $enum_variant:path)$(,)?)*) => {
+        $(impl_array_conv!($typ, $enum_variant);)*
+    };
+}
+
+impl_array_conv!(
+    (Vec<u8>, NBTTag::ByteArray),
+    (Vec<i32>, NBTTag::IntArray),
+    (Vec<i64>, NBTTag::LongArray),
+);
+
 macro_rules! impl_newtype_conv {
     ($typ:ty, $newtyp:path) => {
         impl From<$newtyp> for $typ {
-            fn from(value: $newtyp) -> Self {
-                value.0
+            fn from(mut value: $newtyp) -> Self {
+                std::mem::take(&mut value.0)
             }
         }
 
@@ -96,6 +125,525 @@ impl_newtype_conv!(
     (Vec<i64>, tag::LongArray),
 );
 
+macro_rules! impl_primitive_eq {
+    ($typ:ty, $enum_variant:path) => {
+        impl PartialEq<$typ> for NBTTag {
+            fn eq(&self, other: &$typ) -> bool {
+                matches!(self, $enum_variant(v) if v.0 == *other)
+            }
+        }
+    };
+    ($(($typ:ty, $enum_variant:path)$(,)?)*) => {
+        $(impl_primitive_eq!($typ, $enum_variant);)*
+    };
+}
+
+impl_primitive_eq!(
+    (u8, NBTTag::Byte),
+    (i16, NBTTag::Short),
+    (i32, NBTTag::Int),
+    (i64, NBTTag::Long),
+    (f32, NBTTag::Float),
+    (f64, NBTTag::Double),
+);
+
+/// Compares a [NBTTag::String] with a string. Like with the comparisons with other primitive
+/// types, tags of any other type are never equal.
+///
+/// ```
+/// use zuri_nbt::NBTTag;
+///
+/// assert_eq!(NBTTag::Byte(1.into()), 1u8);
+/// assert_eq!(NBTTag::Short(2.into()), 2i16);
+/// assert_eq!(NBTTag::Int(5.into()), 5i32);
+/// assert_eq!(NBTTag::Long(6.into()), 6i64);
+/// assert_eq!(NBTTag::Float(0.5.into()), 0.5f32);
+/// assert_eq!(NBTTag::Double(1.5.into()), 1.5f64);
+/// assert_eq!(NBTTag::String("Zuri".into()), "Zuri");
+/// assert_ne!(NBTTag::Long(5.into()), 5i32);
+/// ```
+impl PartialEq<str> for NBTTag {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, NBTTag::String(v) if v.0 == other)
+    }
+}
+
+impl PartialEq<&str> for NBTTag {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl tag::Byte {
+    /// Creates a byte tag from a signed integer. The bits of the value are kept as-is.
+    pub fn from_i8(value: i8) -> Self {
+        Self(value as u8)
+    }
+
+    /// Returns the value of the tag as a signed integer, the way Minecraft: Java Edition
+    /// interprets it.
+    pub fn as_i8(&self) -> i8 {
+        self.0 as i8
+    }
+}
+
+impl tag::ByteArray {
+    /// Returns the values of the array as signed integers, the way Minecraft: Java Edition
+    /// interprets them. The bits of the values are kept as-is.
+    pub fn as_i8_slice(&self) -> &[i8] {
+        // SAFETY: u8 and i8 have the same size and alignment, and every bit pattern is valid for
+        // both. The returned slice borrows the array, so it cannot outlive or alias a mutable
+        // borrow of the data. This is the only place where the crate reinterprets byte arrays.
+        unsafe { std::slice::from_raw_parts(self.0.as_ptr().cast::<i8>(), self.0.len()) }
+    }
+}
+
+impl NBTTag {
+    /// Converts a list of [tag::Byte]s into a [tag::ByteArray].
+    ///
+    /// Returns [None] if the tag is not a list, or if any of its elements is not a byte tag. Empty
+    /// lists are converted regardless of their element type.
+    pub fn list_to_byte_array(&self) -> Option<NBTTag> {
+        self.list_elements(|v| match v {
+            NBTTag::Byte(v) => Some(v.0),
+            _ => None,
+        })
+        .map(|v| NBTTag::ByteArray(v.into()))
+    }
+
+    /// Converts a list of [tag::Int]s into a [tag::IntArray].
+    ///
+    /// Returns [None] if the tag is not a list, or if any of its elements is not an int tag. Empty
+    /// lists are converted regardless of their element type.
+    pub fn list_to_int_array(&self) -> Option<NBTTag> {
+        self.list_elements(|v| match v {
+            NBTTag::Int(v) => Some(v.0),
+            _ => None,
+        })
+        .map(|v| NBTTag::IntArray(v.into()))
+    }
+
+    /// Converts a list of [tag::Long]s into a [tag::LongArray].
+    ///
+    /// Returns [None] if the tag is not a list, or if any of its elements is not a long tag. Empty
+    /// lists are converted regardless of their element type.
+    pub fn list_to_long_array(&self) -> Option<NBTTag> {
+        self.list_elements(|v| match v {
+            NBTTag::Long(v) => Some(v.0),
+            _ => None,
+        })
+        .map(|v| NBTTag::LongArray(v.into()))
+    }
+
+    /// Returns references to the elements of a list of [tag::Compound]s, such as a list of
+    /// entities.
+    ///
+    /// Returns [None] if the tag is not a list, or if any of its elements is not a compound tag.
+    /// Empty lists are converted regardless of their element type.
+    pub fn as_list_of_compounds(&self) -> Option<Vec<&tag::Compound>> {
+        self.list_elements(|v| match v {
+            NBTTag::Compound(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns true if the tag holds the default value of its type, which is left out when writing
+    /// with the `omit_defaults` option of the encodings. Compounds and raw tags never do.
+    pub(crate) fn is_default_value(&self) -> bool {
+        match self {
+            NBTTag::Byte(v) => v.0 == 0,
+            NBTTag::Short(v) => v.0 == 0,
+            NBTTag::Int(v) => v.0 == 0,
+            NBTTag::Long(v) => v.0 == 0,
+            // Negative zero is not the default, as it would not round-trip.
+            NBTTag::Float(v) => v.0.to_bits() == 0,
+            NBTTag::Double(v) => v.0.to_bits() == 0,
+            NBTTag::String(v) => v.0.is_empty(),
+            NBTTag::List(v) => v.is_empty(),
+            NBTTag::ByteArray(v) => v.is_empty(),
+            NBTTag::IntArray(v) => v.is_empty(),
+            NBTTag::LongArray(v) => v.is_empty(),
+            NBTTag::Compound(_) | NBTTag::Raw(_) | NBTTag::Custom(_) => false,
+        }
+    }
+
+    /// Returns true if the tag is a [tag::Byte], [tag::Short], [tag::Int], [tag::Long],
+    /// [tag::Float] or [tag::Double].
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            NBTTag::Byte(_)
+                | NBTTag::Short(_)
+                | NBTTag::Int(_)
+                | NBTTag::Long(_)
+                | NBTTag::Float(_)
+                | NBTTag::Double(_)
+        )
+    }
+
+    /// Returns true if the tag is a [tag::Compound] or a [tag::List], the tags that contain other
+    /// tags.
+    pub fn is_container(&self) -> bool {
+        matches!(self, NBTTag::Compound(_) | NBTTag::List(_))
+    }
+
+    /// Returns true if the tag is a [tag::ByteArray], [tag::IntArray] or [tag::LongArray].
+    pub fn is_array(&self) -> bool {
+        matches!(
+            self,
+            NBTTag::ByteArray(_) | NBTTag::IntArray(_) | NBTTag::LongArray(_)
+        )
+    }
+
+    /// Returns true if the tag is a [tag::String].
+    pub fn is_string(&self) -> bool {
+        matches!(self, NBTTag::String(_))
+    }
+
+    /// Returns the tag itself if it is of the expected type, or an error containing both the
+    /// expected and the actual type otherwise. This allows checking types in accessor chains:
+    /// ```
+    /// use zuri_nbt::{tag, NBTTag, NBTTagType};
+    ///
+    /// let nbt = NBTTag::Compound(tag::Compound::builder().with_int("level", 3).build());
+    /// let level = nbt.get_path("level")?.type_check(NBTTagType::Int)?;
+    /// assert_eq!(*level, 3i32);
+    /// # Ok::<_, zuri_nbt::err::ErrorPath<zuri_nbt::err::TypeError>>(())
+    /// ```
+    pub fn type_check(&self, expected: NBTTagType) -> Result<&Self, ErrorPath<TypeError>> {
+        let found = self.tag_type();
+        if found != expected {
+            return Err(ErrorPath::new(TypeError::UnexpectedTag(expected, found)));
+        }
+        Ok(self)
+    }
+
+    /// Returns the tag at the dotted path, such as `Inventory.0.id`. Numbers select the element
+    /// with that index in lists, and an empty path selects the tag itself.
+    ///
+    /// Returns an error containing the path to the first tag that is missing, or to the first tag
+    /// in the path that is not a compound or a list indexed by a number.
+    pub fn get_path(&self, path: &str) -> Result<&Self, ErrorPath<TypeError>> {
+        self.get_path_inner(path).map(|(tag, _)| tag)
+    }
+
+    /// Returns the tag at the dotted path if it is of the expected type. Combines
+    /// [Self::get_path] and [Self::type_check], with the path included in both kinds of error.
+    pub fn get_path_checked(
+        &self,
+        path: &str,
+        expected: NBTTagType,
+    ) -> Result<&Self, ErrorPath<TypeError>> {
+        let (tag, visited) = self.get_path_inner(path)?;
+        tag.type_check(expected)
+            .map_err(|err| ErrorPath::new_with_path(err.inner, visited))
+    }
+
+    /// Looks up the tag at the dotted path, returning it along with the path that was followed.
+    fn get_path_inner(&self, path: &str) -> Result<(&Self, Path), ErrorPath<TypeError>> {
+        let mut current = self;
+        let mut visited = Path::default();
+        if path.is_empty() {
+            return Ok((current, visited));
+        }
+        for segment in path.split('.') {
+            let next = match (current, segment.parse::<usize>()) {
+                (NBTTag::Compound(c), _) => {
+                    visited.0.push_back(PathPart::MapKey(segment.to_string()));
+                    c.get(segment)
+                }
+                (NBTTag::List(l), Ok(i)) => {
+                    visited.0.push_back(PathPart::Element(i));
+                    l.get(i)
+                }
+                (v, _) => {
+                    return Err(ErrorPath::new_with_path(
+                        TypeError::UnexpectedTag(NBTTagType::Compound, v.tag_type()),
+                        visited,
+                    ))
+                }
+            };
+            current = match next {
+                Some(v) => v,
+                None => return Err(ErrorPath::new_with_path(TypeError::MissingTag, visited)),
+            };
+        }
+        Ok((current, visited))
+    }
+
+    /// Returns the tag at the [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901) JSON pointer,
+    /// such as `/Level/Sections/0/Y`, or [None] if there is no such tag.
+    ///
+    /// Unlike the dotted paths of [Self::get_path], pointers can select keys that contain a `.`.
+    /// Every segment of the pointer starts with a `/`. Within a segment, `~1` stands for a `/` and
+    /// `~0` stands for a `~`. Segments select the element with that index in lists, where indices
+    /// must not have leading zeros, and an empty pointer selects the tag itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&Self> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut current = self;
+        for segment in pointer.strip_prefix('/')?.split('/') {
+            current = match current {
+                NBTTag::Compound(c) => c.get(&segment.replace("~1", "/").replace("~0", "~"))?,
+                NBTTag::List(l) => {
+                    if segment.len() > 1 && segment.starts_with('0') {
+                        return None;
+                    }
+                    // Parsing as a number would also accept a leading `+`.
+                    if !segment.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    l.get(segment.parse::<usize>().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Normalizes the floating point numbers in the tag and all tags nested in it, so that numbers
+    /// that are considered equal have the same representation. This is useful before hashing or
+    /// comparing the encoded data.
+    ///
+    /// Negative zero is replaced with positive zero, and every NaN is replaced with the canonical
+    /// quiet NaN, which has the bit pattern `0x7fc00000` for floats and `0x7ff8000000000000` for
+    /// doubles. All other numbers are kept as-is. As this changes the data, it is never done
+    /// implicitly.
+    pub fn canonicalize_floats(&mut self) {
+        let mut stack = vec![self];
+        while let Some(tag) = stack.pop() {
+            match tag {
+                NBTTag::Float(v) if v.0 == 0.0 => v.0 = 0.0,
+                NBTTag::Float(v) if v.0.is_nan() => v.0 = f32::NAN,
+                NBTTag::Double(v) if v.0 == 0.0 => v.0 = 0.0,
+                NBTTag::Double(v) if v.0.is_nan() => v.0 = f64::NAN,
+                NBTTag::Compound(c) => stack.extend(c.as_map_mut().values_mut()),
+                NBTTag::List(l) => stack.extend(l.0.iter_mut()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns a copy of the tag with `f` applied to every tag that is not a compound or a list,
+    /// such as to scale all coordinates or to mask all strings. Arrays are passed to `f` as a
+    /// whole.
+    ///
+    /// The structure of compounds and lists is kept intact, so `f` only ever sees the leaves of
+    /// the tree, and the tag itself if it is not a container. `f` should return a tag of the same
+    /// type for elements of a list, as a list with elements of differing types cannot be written.
+    pub fn map_values(&self, f: impl Fn(&NBTTag) -> NBTTag) -> NBTTag {
+        let Some(root) = MapFrame::open(self, None) else {
+            return f(self);
+        };
+        // The copy is built while traversing the tag, so that only the leaves returned by `f` and
+        // the containers themselves are allocated.
+        let mut stack = vec![root];
+        loop {
+            // Unwrapping will never panic here as the stack is only empty once the copy of the
+            // root tag is returned.
+            let frame = stack.last_mut().unwrap();
+            if let Some((key, child)) = frame.children.next() {
+                match MapFrame::open(child, key) {
+                    Some(child) => stack.push(child),
+                    None => push_child(&mut frame.tag, key, f(child)),
+                }
+                continue;
+            }
+            let frame = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some(parent) => push_child(&mut parent.tag, frame.key, frame.tag),
+                None => return frame.tag,
+            }
+        }
+    }
+
+    /// Returns a copy of the tag that is limited in size, for quickly rendering a preview of a
+    /// large tag.
+    ///
+    /// The copy holds at most `max_nodes` tags, which are taken depth-first with the entries of
+    /// compounds in key order. Tags nested deeper than `max_depth` are left out, with the root tag
+    /// being at depth zero. Any compound or list that has content left out is given a string tag
+    /// of `"..."` in its place: as an extra element for lists, or under a `"..."` key for
+    /// compounds. If the compound already has a `"..."` key, the first of `"...."`, `"....."` and
+    /// so on that it does not have is used instead, so that no entry is overwritten. A compound or
+    /// list that has all of its content left out is replaced by this string entirely. Arrays count
+    /// as a single tag and are never shortened.
+    ///
+    /// As the markers do not match the type of the other elements, a preview of a list may not be
+    /// written.
+    pub fn preview(&self, max_nodes: usize, max_depth: usize) -> NBTTag {
+        if max_nodes == 0 {
+            return preview_marker();
+        }
+        let mut nodes = 1;
+        let mut stack = match PreviewFrame::open(self, None, max_depth) {
+            Ok(frame) => vec![frame],
+            Err(tag) => return tag,
+        };
+        loop {
+            let depth = max_depth - stack.len();
+            // Unwrapping will never panic here as the stack is only empty once the copy of the
+            // root tag is returned.
+            let frame = stack.last_mut().unwrap();
+            if let Some((key, child)) = frame.children.get(frame.next).copied() {
+                if nodes < max_nodes {
+                    frame.next += 1;
+                    nodes += 1;
+                    match PreviewFrame::open(child, key, depth) {
+                        Ok(child) => stack.push(child),
+                        Err(tag) => frame.push(key, tag),
+                    }
+                    continue;
+                }
+                if frame.next == 0 {
+                    frame.tag = preview_marker();
+                } else {
+                    let key = frame.marker_key();
+                    frame.push(Some(&key), preview_marker());
+                }
+            }
+            let frame = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some(parent) => parent.push(frame.key, frame.tag),
+                None => return frame.tag,
+            }
+        }
+    }
+
+    /// Converts all elements of a list tag, returning [None] if the tag is not a list or if any of
+    /// the elements could not be converted.
+    fn list_elements<'a, T>(&'a self, f: impl Fn(&'a NBTTag) -> Option<T>) -> Option<Vec<T>> {
+        match self {
+            NBTTag::List(list) => list.iter().map(f).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the string tag that takes the place of content left out by [NBTTag::preview].
+fn preview_marker() -> NBTTag {
+    NBTTag::String("...".into())
+}
+
+/// A compound or list that is being copied by [NBTTag::map_values].
+struct MapFrame<'a> {
+    /// The key of the tag in its parent, if the parent is a compound.
+    key: Option<&'a str>,
+    /// The copy, which is an empty compound or list that the mapped children are added to.
+    tag: NBTTag,
+    /// The children of the original tag that remain to be mapped.
+    children: Children<'a>,
+}
+
+/// The children of a compound or list along with their keys.
+enum Children<'a> {
+    Compound(hash_map::Iter<'a, String, NBTTag>),
+    List(slice::Iter<'a, NBTTag>),
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = (Option<&'a str>, &'a NBTTag);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Children::Compound(iter) => iter.next().map(|(k, v)| (Some(k.as_str()), v)),
+            Children::List(iter) => iter.next().map(|v| (None, v)),
+        }
+    }
+}
+
+impl<'a> MapFrame<'a> {
+    /// Starts copying a tag, or returns [None] if the tag is not a compound or list.
+    fn open(tag: &'a NBTTag, key: Option<&'a str>) -> Option<Self> {
+        let (copy, children) = match tag {
+            NBTTag::Compound(c) => (
+                NBTTag::Compound(HashMap::with_capacity(c.len()).into()),
+                Children::Compound(c.0.iter()),
+            ),
+            NBTTag::List(l) => (
+                NBTTag::List(tag::List(Vec::with_capacity(l.len()), l.1)),
+                Children::List(l.0.iter()),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            key,
+            tag: copy,
+            children,
+        })
+    }
+}
+
+/// Adds a copied child to the copy of a compound or list.
+fn push_child(tag: &mut NBTTag, key: Option<&str>, child: NBTTag) {
+    match tag {
+        NBTTag::Compound(c) => {
+            // Unwrapping will never panic here as all children of compounds have a key.
+            c.0.insert(key.unwrap().to_string(), child);
+        }
+        NBTTag::List(l) => l.0.push(child),
+        _ => unreachable!(),
+    }
+}
+
+/// A compound or list that is being copied by [NBTTag::preview].
+struct PreviewFrame<'a> {
+    /// The key of the tag in its parent, if the parent is a compound.
+    key: Option<&'a str>,
+    /// The copy, which is an empty compound or list that the copied children are added to.
+    tag: NBTTag,
+    /// The children of the original tag along with their keys, in the order they are copied.
+    children: Vec<(Option<&'a str>, &'a NBTTag)>,
+    /// The index of the next child to copy.
+    next: usize,
+}
+
+impl<'a> PreviewFrame<'a> {
+    /// Starts copying a tag at which `depth` more levels of nesting are allowed. Returns the
+    /// finished copy instead if the tag has no children to copy.
+    fn open(tag: &'a NBTTag, key: Option<&'a str>, depth: usize) -> Result<Self, NBTTag> {
+        let (copy, children): (_, Vec<_>) = match tag {
+            NBTTag::Compound(c) if !c.0.is_empty() => (
+                NBTTag::Compound(Default::default()),
+                c.to_sorted()
+                    .into_iter()
+                    .map(|(k, v)| (Some(k), v))
+                    .collect(),
+            ),
+            NBTTag::List(l) if !l.0.is_empty() => (
+                NBTTag::List(tag::List(Vec::new(), l.1)),
+                l.0.iter().map(|v| (None, v)).collect(),
+            ),
+            _ => return Err(tag.clone()),
+        };
+        if depth == 0 {
+            return Err(preview_marker());
+        }
+        Ok(Self {
+            key,
+            tag: copy,
+            children,
+            next: 0,
+        })
+    }
+
+    /// Adds a copied child to the copy.
+    fn push(&mut self, key: Option<&str>, child: NBTTag) {
+        push_child(&mut self.tag, key, child)
+    }
+
+    /// Returns the key of the marker for content left out of a compound, which is the first of
+    /// `"..."`, `"...."` and so on that is not a key of the original compound.
+    fn marker_key(&self) -> String {
+        let mut key = "...".to_string();
+        while self.children.iter().any(|(k, _)| *k == Some(key.as_str())) {
+            key.push('.');
+        }
+        key
+    }
+}
+
 /// Special case: converting `&str` to a [tag::String] requires a clone.
 impl From<&str> for tag::String {
     fn from(value: &str) -> Self {
@@ -103,15 +651,38 @@ impl From<&str> for tag::String {
     }
 }
 
+/// Reads an NBT tag from a byte slice using the [BigEndian] encoding. Any bytes remaining after
+/// the tag has been read are ignored.
+///
+/// Use [NBTTag::read] to read the data using a different encoding.
+///
+/// ```
+/// use zuri_nbt::NBTTag;
+///
+/// let bytes: &[u8] = &[0x08, 0x00, 0x00, 0x00, 0x04, 0x5a, 0x75, 0x72, 0x69];
+/// let value = NBTTag::try_from(bytes).expect("Something went wrong while reading nbt");
+/// assert_eq!(value, NBTTag::String("Zuri".to_string().into()));
+/// ```
+impl TryFrom<&[u8]> for NBTTag {
+    type Error = ErrorPath<ReadError>;
+
+    fn try_from(mut value: &[u8]) -> Result<Self, Self::Error> {
+        NBTTag::read(&mut value, &mut BigEndian::default())
+    }
+}
+
 impl<T: Into<NBTTag>> From<Vec<T>> for tag::List {
     fn from(value: Vec<T>) -> Self {
-        tag::List(value.into_iter().map(|v| v.into()).collect())
+        tag::List(
+            value.into_iter().map(|v| v.into()).collect(),
+            tag::list::DEFAULT_ELEMENT_TYPE,
+        )
     }
 }
 
 impl From<tag::List> for Vec<NBTTag> {
-    fn from(value: tag::List) -> Self {
-        value.0
+    fn from(mut value: tag::List) -> Self {
+        std::mem::take(&mut value.0)
     }
 }
 
@@ -187,3 +758,342 @@ impl_tagtype!(
     (tag::IntArray, NBTTagType::IntArray, NBTTag::IntArray),
     (tag::LongArray, NBTTagType::LongArray, NBTTag::LongArray),
 );
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::Reader;
+    use crate::encode::Writer;
+    use crate::encoding::{AnyEncoding, BigEndian, LittleEndian, NetworkLittleEndian};
+    use crate::err::TypeError;
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_type_check() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list(
+                    "Inventory",
+                    vec![tag::Compound::builder().with_string("id", "stone").build()],
+                )
+                .build(),
+        );
+        assert_eq!(nbt.type_check(NBTTagType::Compound), Ok(&nbt));
+        assert_eq!(
+            nbt.get_path_checked("Inventory.0.id", NBTTagType::String),
+            Ok(&NBTTag::String("stone".into()))
+        );
+
+        let err = nbt.type_check(NBTTagType::List).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::List, NBTTagType::Compound)
+        );
+        assert!(err.path.0.is_empty());
+
+        let err = nbt
+            .get_path_checked("Inventory.0.id", NBTTagType::Int)
+            .unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Int, NBTTagType::String)
+        );
+        assert_eq!(err.path.to_string(), "Inventory[0].id");
+
+        let err = nbt.get_path("Inventory.1.id").unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.to_string(), "Inventory[1]");
+
+        let err = nbt.get_path("Inventory.0.id.count").unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Compound, NBTTagType::String)
+        );
+        assert_eq!(err.path.to_string(), "Inventory[0].id");
+    }
+
+    #[test]
+    fn test_pointer() {
+        let section = tag::Compound::builder().with_byte("Y", 4).build();
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound(
+                    "Level",
+                    tag::Compound::builder().with_list("Sections", vec![section]),
+                )
+                .with_int("minecraft:a.b", 1)
+                .with_int("a/b~c", 2)
+                .with_int("", 3)
+                .build(),
+        );
+        assert_eq!(nbt.pointer(""), Some(&nbt));
+        assert_eq!(
+            nbt.pointer("/Level/Sections/0/Y"),
+            Some(&NBTTag::Byte(4.into()))
+        );
+        assert_eq!(nbt.pointer("/minecraft:a.b"), Some(&NBTTag::Int(1.into())));
+        assert_eq!(nbt.pointer("/a~1b~0c"), Some(&NBTTag::Int(2.into())));
+        assert_eq!(nbt.pointer("/"), Some(&NBTTag::Int(3.into())));
+
+        assert_eq!(nbt.pointer("Level"), None);
+        assert_eq!(nbt.pointer("/Level/Sections/1"), None);
+        assert_eq!(nbt.pointer("/Level/Sections/00"), None);
+        assert_eq!(nbt.pointer("/Level/Sections/+0"), None);
+        assert_eq!(nbt.pointer("/Level/Sections/0/Y/Z"), None);
+    }
+
+    #[test]
+    fn test_canonicalize_floats() {
+        let mut nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_float("a", -0.0)
+                .with_double("b", f64::from_bits(0xfff0_0000_0000_0001))
+                .with_list(
+                    "c",
+                    vec![tag::Float(f32::from_bits(0x7f80_0001)), tag::Float(-1.5)],
+                )
+                .build(),
+        );
+        nbt.canonicalize_floats();
+        let bits = |pointer| match nbt.pointer(pointer) {
+            Some(NBTTag::Float(v)) => v.0.to_bits() as u64,
+            Some(NBTTag::Double(v)) => v.0.to_bits(),
+            v => panic!("expected a floating point number, found {v:?}"),
+        };
+        assert_eq!(bits("/a"), 0);
+        assert_eq!(bits("/b"), 0x7ff8_0000_0000_0000);
+        assert_eq!(bits("/c/0"), 0x7fc0_0000);
+        assert_eq!(bits("/c/1"), (-1.5f32).to_bits() as u64);
+    }
+
+    #[test]
+    fn test_classification() {
+        let tags = [
+            NBTTag::Byte(1.into()),
+            NBTTag::Short(1.into()),
+            NBTTag::Int(1.into()),
+            NBTTag::Long(1.into()),
+            NBTTag::Float(1.0.into()),
+            NBTTag::Double(1.0.into()),
+            NBTTag::String("a".into()),
+            NBTTag::List(Vec::<tag::Int>::new().into()),
+            NBTTag::Compound(tag::Compound::default()),
+            NBTTag::ByteArray(vec![1].into()),
+            NBTTag::IntArray(vec![1].into()),
+            NBTTag::LongArray(vec![1].into()),
+        ];
+        for tag in &tags {
+            let categories = [
+                tag.is_numeric(),
+                tag.is_string(),
+                tag.is_container(),
+                tag.is_array(),
+            ];
+            // Every tag apart from raw tags belongs to exactly one category.
+            assert_eq!(categories.iter().filter(|c| **c).count(), 1, "{tag:?}");
+        }
+        assert!(tags[..6].iter().all(NBTTag::is_numeric));
+        assert!(tags[6].is_string());
+        assert!(tags[7..9].iter().all(NBTTag::is_container));
+        assert!(tags[9..].iter().all(NBTTag::is_array));
+    }
+
+    #[test]
+    fn test_array_try_from() {
+        let ints = NBTTag::IntArray(vec![1, -2].into());
+        assert_eq!(Vec::<i32>::try_from(ints.clone()), Ok(vec![1, -2]));
+        assert_eq!(Vec::<i64>::try_from(ints.clone()), Err(ints.clone()));
+        assert_eq!(Vec::<u8>::try_from(ints.clone()), Err(ints));
+
+        let bytes = NBTTag::ByteArray(vec![0, 255].into());
+        assert_eq!(Vec::<u8>::try_from(bytes), Ok(vec![0, 255]));
+        let longs = NBTTag::LongArray(vec![i64::MAX].into());
+        assert_eq!(longs.try_into(), Ok(vec![i64::MAX]));
+
+        // Lists are not converted, even if their elements are of the matching type.
+        let list = NBTTag::List(vec![tag::Int(1)].into());
+        assert_eq!(Vec::<i32>::try_from(list.clone()), Err(list));
+    }
+
+    #[test]
+    fn test_list_to_array() {
+        let ints = NBTTag::List(vec![tag::Int(1), tag::Int(-2)].into());
+        assert_eq!(
+            ints.list_to_int_array(),
+            Some(NBTTag::IntArray(vec![1, -2].into()))
+        );
+        assert_eq!(ints.list_to_long_array(), None);
+
+        let bytes = NBTTag::List(vec![tag::Byte(1)].into());
+        assert_eq!(
+            bytes.list_to_byte_array(),
+            Some(NBTTag::ByteArray(vec![1].into()))
+        );
+        let longs = NBTTag::List(vec![tag::Long(i64::MAX)].into());
+        assert_eq!(
+            longs.list_to_long_array(),
+            Some(NBTTag::LongArray(vec![i64::MAX].into()))
+        );
+
+        let mixed = NBTTag::List(tag::List(
+            vec![NBTTag::Int(1.into()), NBTTag::Long(2.into())],
+            3,
+        ));
+        assert_eq!(mixed.list_to_int_array(), None);
+        assert_eq!(NBTTag::Int(1.into()).list_to_int_array(), None);
+        assert_eq!(
+            NBTTag::List(Vec::<tag::Byte>::new().into()).list_to_int_array(),
+            Some(NBTTag::IntArray(vec![].into()))
+        );
+    }
+
+    #[test]
+    fn test_as_list_of_compounds() {
+        let a = tag::Compound::builder()
+            .with_string("id", "minecraft:pig")
+            .build();
+        let b = tag::Compound::builder()
+            .with_string("id", "minecraft:cow")
+            .build();
+        let entities = NBTTag::List(vec![a.clone(), b.clone()].into());
+        assert_eq!(entities.as_list_of_compounds(), Some(vec![&a, &b]));
+
+        let ints = NBTTag::List(vec![tag::Int(1), tag::Int(2)].into());
+        assert_eq!(ints.as_list_of_compounds(), None);
+        assert_eq!(NBTTag::Compound(a).as_list_of_compounds(), None);
+    }
+
+    #[test]
+    fn test_signed_byte_array() {
+        let values = [-1, i8::MIN, 0, i8::MAX, -42];
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            let mut buf = Vec::new();
+            encoding.write_i8_vec(&mut buf, &values).unwrap();
+            assert_eq!(encoding.i8_vec(&mut buf.as_slice()).unwrap(), values);
+
+            // The array is stored like any other byte array.
+            let array = tag::ByteArray(encoding.u8_vec(&mut buf.as_slice()).unwrap());
+            assert_eq!(array.0, [255, 128, 0, 127, 214]);
+            assert_eq!(array.as_i8_slice(), values);
+        }
+    }
+
+    #[test]
+    fn test_signed_byte() {
+        let byte = tag::Byte::from_i8(-1);
+        assert_eq!(byte.0, 255);
+        assert_eq!(byte.as_i8(), -1);
+        assert_eq!(tag::Byte(128).as_i8(), i8::MIN);
+
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            let mut buf = Vec::new();
+            NBTTag::Byte(byte).write(&mut buf, &mut encoding).unwrap();
+            let read = tag::Byte::read(&mut buf.as_slice(), &mut encoding).unwrap();
+            assert_eq!(read.as_i8(), -1);
+        }
+    }
+
+    #[test]
+    fn test_map_values() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("a", 1)
+                .with_string("name", "Zuri")
+                .with_compound(
+                    "pos",
+                    tag::Compound::builder()
+                        .with_list("xyz", vec![tag::Int(-2), tag::Int(3), tag::Int(4)])
+                        .build(),
+                )
+                .build(),
+        );
+        let doubled = nbt.map_values(|v| match v {
+            NBTTag::Int(v) => NBTTag::Int(tag::Int(v.0 * 2)),
+            v => v.clone(),
+        });
+        assert_eq!(
+            doubled,
+            NBTTag::Compound(
+                tag::Compound::builder()
+                    .with_int("a", 2)
+                    .with_string("name", "Zuri")
+                    .with_compound(
+                        "pos",
+                        tag::Compound::builder()
+                            .with_list("xyz", vec![tag::Int(-4), tag::Int(6), tag::Int(8)])
+                            .build(),
+                    )
+                    .build(),
+            )
+        );
+        // The original tag is left unchanged, and leaves can be mapped on their own.
+        assert_eq!(nbt.pointer("/a"), Some(&NBTTag::Int(tag::Int(1))));
+        assert_eq!(
+            NBTTag::Int(tag::Int(5)).map_values(|_| NBTTag::Byte(tag::Byte(1))),
+            1u8
+        );
+    }
+
+    #[test]
+    fn test_preview() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("a", 1)
+                .with_list("b", vec![tag::Int(1), tag::Int(2)])
+                .with_compound("c", tag::Compound::builder().with_int("d", 1).build())
+                .build(),
+        );
+        let marker = || NBTTag::String("...".into());
+        let compound = |entries: Vec<(&str, NBTTag)>| {
+            NBTTag::Compound(
+                entries
+                    .into_iter()
+                    .fold(tag::Compound::builder(), |b, (k, v)| b.with(k, v))
+                    .build(),
+            )
+        };
+
+        assert_eq!(nbt.preview(usize::MAX, usize::MAX), nbt);
+        assert_eq!(nbt.preview(0, usize::MAX), marker());
+        assert_eq!(nbt.preview(usize::MAX, 0), marker());
+        assert_eq!(
+            nbt.preview(usize::MAX, 1),
+            compound(vec![
+                ("a", tag::Int(1).into()),
+                ("b", marker()),
+                ("c", marker())
+            ])
+        );
+        assert_eq!(
+            nbt.preview(3, usize::MAX),
+            compound(vec![
+                ("a", tag::Int(1).into()),
+                ("b", marker()),
+                ("...", marker())
+            ])
+        );
+        let list = tag::List(vec![tag::Int(1).into(), marker()], 3);
+        assert_eq!(
+            nbt.preview(4, usize::MAX),
+            compound(vec![
+                ("a", tag::Int(1).into()),
+                ("b", list.into()),
+                ("...", marker())
+            ])
+        );
+
+        // The marker does not overwrite an existing entry with the same key.
+        let nbt = compound(vec![("...", tag::Int(1).into()), ("z", tag::Int(2).into())]);
+        assert_eq!(
+            nbt.preview(2, usize::MAX),
+            compound(vec![("...", tag::Int(1).into()), ("....", marker())])
+        );
+    }
+}
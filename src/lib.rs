@@ -1,32 +1,64 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 
-use std::collections::HashMap;
+use std::collections::{hash_map, HashMap};
 use std::fmt::Debug;
+use std::io::{Cursor, IoSlice, Write};
+use std::{iter, mem, slice, vec};
 
 use bytes::{Buf, BufMut};
 use strum_macros::{Display, IntoStaticStr};
 
-use encode::Writer;
+use encode::{IoWriter, Writer};
 
 use crate::decode::Reader;
 use crate::err::{ErrorPath, Path, PathPart, ReadError, WriteError};
 use crate::view::View;
 
+// Allows the code generated by the derive macros to refer to this crate in its own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as zuri_nbt;
+
+#[cfg(test)]
+mod arbitrary;
+pub mod checksum;
+#[cfg(feature = "components")]
+pub mod components;
+pub mod console;
+pub mod convert;
 pub mod decode;
+#[cfg(feature = "derive")]
+pub mod derive;
 pub mod encode;
 pub mod encoding;
 pub mod err;
+pub mod fixer;
+mod half;
 mod r#impl;
+mod mutf8;
+pub mod patch;
+#[cfg(feature = "player")]
+pub mod player;
+pub mod schema;
 #[cfg(feature = "serde")]
 pub mod serde;
+pub mod shared;
+pub mod snbt;
+pub mod stats;
+pub mod stream;
 pub mod tag;
+pub mod trace;
 pub mod view;
 
 /// An enum representing all possible NBT data.
+///
+/// The enum is non-exhaustive, so that tag types can be added without breaking matches on it.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum NBTTag {
     /// An 8-bit unsigned integer.
+    ///
+    /// See [tag::Byte] for working with the value as a signed integer.
     Byte(tag::Byte),
     /// A 16-bit signed integer.
     Short(tag::Short),
@@ -57,11 +89,22 @@ pub enum NBTTag {
     IntArray(tag::IntArray),
     /// A variable-length array containing 64-bit signed integers.
     LongArray(tag::LongArray),
+    /// A pre-encoded tag payload that is written as-is, without being parsed.
+    ///
+    /// This tag is never produced when reading. See [tag::Raw] for more info.
+    Raw(tag::Raw),
+    /// A tag of a type that is not part of the NBT format, such as those added by mods.
+    ///
+    /// See [tag::Custom] for more info.
+    Custom(tag::Custom),
 }
 
 /// An enum representing all possible NBT tag types.
+///
+/// Like [NBTTag], the enum is non-exhaustive.
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, Display, IntoStaticStr, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum NBTTagType {
     Byte,
     Short,
@@ -75,6 +118,48 @@ pub enum NBTTagType {
     ByteArray,
     IntArray,
     LongArray,
+    /// A custom tag type with the contained id. See [tag::Custom].
+    Custom(tag::CustomId),
+}
+
+impl NBTTagType {
+    /// Returns the tag type associated with a tag id used for encoding and decoding, if any.
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            1 => Self::Byte,
+            2 => Self::Short,
+            3 => Self::Int,
+            4 => Self::Long,
+            5 => Self::Float,
+            6 => Self::Double,
+            7 => Self::ByteArray,
+            8 => Self::String,
+            9 => Self::List,
+            10 => Self::Compound,
+            11 => Self::IntArray,
+            12 => Self::LongArray,
+            _ => return None,
+        })
+    }
+
+    /// Returns the tag id used for encoding and decoding the tag type.
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::Byte => 1,
+            Self::Short => 2,
+            Self::Int => 3,
+            Self::Long => 4,
+            Self::Float => 5,
+            Self::Double => 6,
+            Self::ByteArray => 7,
+            Self::String => 8,
+            Self::List => 9,
+            Self::Compound => 10,
+            Self::IntArray => 11,
+            Self::LongArray => 12,
+            Self::Custom(id) => id.get(),
+        }
+    }
 }
 
 impl NBTTag {
@@ -93,32 +178,473 @@ impl NBTTag {
             NBTTag::ByteArray(v) => v.tag_type(),
             NBTTag::IntArray(v) => v.tag_type(),
             NBTTag::LongArray(v) => v.tag_type(),
+            NBTTag::Raw(v) => v.tag_type,
+            NBTTag::Custom(v) => NBTTagType::Custom(v.id),
         }
     }
 
+    /// Returns the amount of entries in the tag if it is a container tag or a string.
+    ///
+    /// For compounds, this is the number of keys. Lists and arrays return their element count, and
+    /// strings return their length in bytes. Returns [None] for all other tags.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            NBTTag::String(v) => Some(v.len()),
+            NBTTag::Compound(v) => Some(v.len()),
+            NBTTag::List(v) => Some(v.len()),
+            NBTTag::ByteArray(v) => Some(v.len()),
+            NBTTag::IntArray(v) => Some(v.len()),
+            NBTTag::LongArray(v) => Some(v.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the tag has no entries if it is a container tag or a string.
+    ///
+    /// See [Self::len] for more info. Returns [None] for all other tags.
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Returns the values of all string tags in the tree, along with their paths.
+    ///
+    /// The strings are returned in depth-first order, where the entries of compounds are visited
+    /// in order of their keys. Compound keys themselves are not included.
+    pub fn collect_strings(&self) -> Vec<(Vec<PathPart>, &str)> {
+        let mut strings = Vec::new();
+        let mut stack = vec![(self, Vec::new())];
+        while let Some((tag, path)) = stack.pop() {
+            match tag {
+                NBTTag::String(v) => strings.push((path, v.as_str())),
+                NBTTag::Compound(v) => {
+                    let mut entries: Vec<_> = v.iter().collect();
+                    // Sorted in reverse, as the entries are popped from the stack in reverse.
+                    entries.sort_by(|a, b| b.0.cmp(a.0));
+                    for (key, value) in entries {
+                        let mut path = path.clone();
+                        path.push(PathPart::MapKey(key.clone()));
+                        stack.push((value, path));
+                    }
+                }
+                NBTTag::List(v) => {
+                    for (i, value) in v.iter().enumerate().rev() {
+                        let mut path = path.clone();
+                        path.push(PathPart::Element(i));
+                        stack.push((value, path));
+                    }
+                }
+                _ => {}
+            }
+        }
+        strings
+    }
+
+    /// Flattens the tree into pairs of dotted keys and the tags at those keys, such as
+    /// `("Player.Name", tag)`. For trees without lists or empty compounds, this is the inverse of
+    /// [tag::Compound::from_paths].
+    ///
+    /// List elements are written as an index between brackets after the key of the list, such as
+    /// `Inventory[0].id`, like the [Path]s in errors. All other tags, including arrays and empty
+    /// compounds and lists, are included as a whole. A tag that is not a compound or list is
+    /// returned with an empty key. Keys that contain a `.` themselves are not escaped.
+    ///
+    /// The pairs are returned in depth-first order, where the entries of compounds are visited in
+    /// order of their keys.
+    pub fn flatten(&self) -> Vec<(String, &NBTTag)> {
+        let mut pairs = Vec::new();
+        let mut stack = vec![(self, String::new())];
+        while let Some((tag, key)) = stack.pop() {
+            match tag {
+                NBTTag::Compound(v) if !v.is_empty() => {
+                    let mut entries: Vec<_> = v.iter().collect();
+                    // Sorted in reverse, as the entries are popped from the stack in reverse.
+                    entries.sort_by(|a, b| b.0.cmp(a.0));
+                    for (k, value) in entries {
+                        let key = if key.is_empty() {
+                            k.clone()
+                        } else {
+                            format!("{key}.{k}")
+                        };
+                        stack.push((value, key));
+                    }
+                }
+                NBTTag::List(v) if !v.is_empty() => {
+                    for (i, value) in v.iter().enumerate().rev() {
+                        stack.push((value, format!("{key}[{i}]")));
+                    }
+                }
+                _ => pairs.push((key, tag)),
+            }
+        }
+        pairs
+    }
+
     /// Creates a [View] for the NBT tag for easy reading.
-    pub fn view(&self) -> View {
+    pub fn view(&self) -> View<'_> {
         View::new(self)
     }
 
     /// Attempts to read the data from a buffer into an NBT value using the specified [Reader]
     /// encoding.
+    ///
+    /// The name of the root tag is discarded. Use [Self::read_named_tag] to keep it.
     pub fn read(buf: &mut impl Buf, r: &mut impl Reader) -> decode::Res<Self> {
-        let tag_id = r.u8(buf)?;
-        r.string(buf)?;
+        Self::read_named_tag(buf, r).map(|(_, tag)| tag)
+    }
+
+    /// Returns the tag id of the root tag in a buffer without consuming any data, such as `10` if
+    /// the root tag is a compound. This allows inspecting data before deciding to read it.
+    ///
+    /// The tag id is the first byte of the data in all encodings. Returns [ReadError::EmptyInput]
+    /// if the buffer is empty, like [Self::read]. The tag id is returned as-is, even if it does
+    /// not correspond to any tag type.
+    pub fn peek_root_type(buf: &impl Buf) -> decode::Res<u8> {
+        if !buf.has_remaining() {
+            return Err(ErrorPath::new(ReadError::EmptyInput));
+        }
+        if let Some(id) = buf.chunk().first() {
+            return Ok(*id);
+        }
+        // Buffers consisting of multiple parts may return an empty chunk for a part that has been
+        // read completely, so the first byte is looked up in the chunks that follow it.
+        let mut chunks = [IoSlice::new(&[]); 16];
+        let len = buf.chunks_vectored(&mut chunks);
+        chunks[..len]
+            .iter()
+            .find_map(|chunk| chunk.first().copied())
+            .ok_or_else(|| ErrorPath::new(ReadError::UnexpectedEOF))
+    }
+
+    /// Attempts to read a single named tag from a buffer using the specified [Reader] encoding.
+    ///
+    /// A named tag consists of a tag id, the name of the tag and its payload, which is exactly how
+    /// the root tag of NBT data is structured. This allows NBT data to be embedded in other
+    /// formats, where multiple named tags may follow each other. Once all of them have been read,
+    /// [ReadError::EmptyInput] is returned rather than [ReadError::UnexpectedEOF].
+    pub fn read_named_tag(buf: &mut impl Buf, r: &mut impl Reader) -> decode::Res<(String, Self)> {
+        let tag_id = Self::read_root_id(buf, r)?;
+        let name = r.string(buf)?;
+        Ok((name, Self::read_inner(buf, tag_id, r)?))
+    }
+
+    /// Attempts to read only the payload of a tag with the provided tag id from a buffer using the
+    /// specified [Reader] encoding.
+    ///
+    /// Unlike [Self::read], neither a tag id nor a name is read, which is how the elements of a
+    /// list are stored. This allows reading tags from custom containers that store the tag id
+    /// separately. Returns an error if the tag id does not correspond to any tag type.
+    pub fn read_typed(buf: &mut impl Buf, tag_id: u8, r: &mut impl Reader) -> decode::Res<Self> {
         Self::read_inner(buf, tag_id, r)
     }
 
+    /// Attempts to read the data from a buffer into an NBT value using the specified [Reader]
+    /// encoding, salvaging as much data as possible if an error occurs.
+    ///
+    /// Reading stops at the first error, like with [Self::read]. Along with the error, the tag
+    /// that was being read is returned containing all data that was read before the error
+    /// occurred, which is useful to inspect the structure of corrupt data. Containers that were
+    /// only partially read are included as they were at the time of the error. The partial tag is
+    /// [None] if the error occurred before any of the root tag could be read.
+    #[allow(clippy::result_large_err)]
+    pub fn read_partial(
+        buf: &mut impl Buf,
+        r: &mut impl Reader,
+    ) -> Result<Self, (ErrorPath<ReadError>, Option<Self>)> {
+        let tag_id = Self::read_root_id(buf, r).map_err(|err| (err, None))?;
+        r.string(buf).map_err(|err| (err, None))?;
+
+        let mut stack = Vec::new();
+        Self::read_with_stack(buf, tag_id, r, &mut stack, None).map_err(|err| {
+            // Fold the partially read containers into each other, starting at the innermost.
+            let mut partial = None;
+            while let Some(frame) = stack.pop() {
+                partial = Some(frame.into_partial(partial));
+            }
+            (err, partial)
+        })
+    }
+
+    /// Attempts to read the data from a buffer into an NBT value using the specified [Reader]
+    /// encoding, replacing list elements that could not be read with a placeholder.
+    ///
+    /// This is a best-effort recovery: reading can only continue with the next element if the
+    /// size of the bad element is known. This is the case for strings directly in a list whose
+    /// contents are not valid in the string encoding of the reader, which are replaced with an
+    /// empty string to keep the elements of the list of the same type. Any other error stops
+    /// reading and is returned, like with [Self::read]. Use [Self::read_partial] to salvage the
+    /// data read before such errors instead.
+    ///
+    /// Returns the tag along with the errors of all replaced elements, which contain the path to
+    /// the element.
+    pub fn read_recovering(
+        buf: &mut impl Buf,
+        r: &mut impl Reader,
+    ) -> Result<(Self, Vec<ErrorPath<ReadError>>), ErrorPath<ReadError>> {
+        let tag_id = Self::read_root_id(buf, r)?;
+        r.string(buf)?;
+
+        let mut errors = Vec::new();
+        let tag = Self::read_with_stack(buf, tag_id, r, &mut Vec::new(), Some(&mut errors))?;
+        Ok((tag, errors))
+    }
+
+    /// Attempts to read the data from a byte slice into an NBT value using the specified [Reader]
+    /// encoding. Any bytes remaining after the tag has been read are ignored.
+    ///
+    /// Like with any contiguous buffer, the contents of strings and byte arrays are copied from the
+    /// slice at once rather than byte by byte.
+    pub fn read_slice(data: &[u8], r: &mut impl Reader) -> decode::Res<Self> {
+        Self::read(&mut &*data, r)
+    }
+
+    /// Attempts to read a single NBT value starting at `offset` in a byte slice using the
+    /// specified [Reader] encoding.
+    ///
+    /// Returns the value along with the amount of bytes it took up, so that data following it,
+    /// such as the next chunk in a region file, can be located.
+    pub fn read_at(data: &[u8], offset: usize, r: &mut impl Reader) -> decode::Res<(Self, usize)> {
+        let mut buf = data
+            .get(offset..)
+            .ok_or_else(|| ErrorPath::new(ReadError::UnexpectedEOF))?;
+        let start = buf.len();
+        let nbt = Self::read(&mut buf, r)?;
+        Ok((nbt, start - buf.len()))
+    }
+
     /// Attempts to write the NBT data into a buffer using the specified [Writer] encoding.
+    ///
+    /// The root tag is written with an empty name. Use [Self::write_named_tag] to specify one.
     pub fn write(&self, buf: &mut impl BufMut, w: &mut impl Writer) -> encode::Res {
-        w.write_u8(buf, self.tag_id())?;
-        w.write_string(buf, "")?;
+        self.write_named_tag(buf, w, "")
+    }
+
+    /// Attempts to write only the payload of the tag into a buffer using the specified [Writer]
+    /// encoding, without a tag id or name.
+    ///
+    /// This is the counterpart of [Self::read_typed], and is how the elements of a list are
+    /// written.
+    pub fn write_payload(&self, buf: &mut impl BufMut, w: &mut impl Writer) -> encode::Res {
         self.write_inner(buf, w)
     }
 
+    /// Attempts to write the NBT data into a new vector using the specified [Writer] encoding.
+    ///
+    /// See [Self::to_bytes_with_capacity] to avoid reallocations while writing large trees.
+    pub fn to_bytes(&self, w: &mut impl Writer) -> Result<Vec<u8>, ErrorPath<WriteError>> {
+        self.to_bytes_with_capacity(w, 0)
+    }
+
+    /// Attempts to write the NBT data into a new vector with the provided initial capacity using
+    /// the specified [Writer] encoding.
+    ///
+    /// The vector only needs to grow while writing if the data is larger than the capacity. When
+    /// writing similar data repeatedly, the length of previously written data is a good hint.
+    pub fn to_bytes_with_capacity(
+        &self,
+        w: &mut impl Writer,
+        capacity: usize,
+    ) -> Result<Vec<u8>, ErrorPath<WriteError>> {
+        let mut bytes = Vec::with_capacity(capacity);
+        self.write(&mut bytes, w)?;
+        Ok(bytes)
+    }
+
+    /// Attempts to write the NBT data into a buffer using the specified [Writer] encoding, staging
+    /// it in a scratch vector provided by the caller first.
+    ///
+    /// The scratch vector is cleared before writing, and the data is only copied into the buffer
+    /// once it has been written completely, so nothing is written into the buffer if an error
+    /// occurs. Writing with [NBTTag::write] directly avoids this copy, but may leave partially
+    /// written data in the buffer. Unlike [NBTTag::to_bytes], no new vector is allocated for every
+    /// write when the same scratch vector is reused.
+    pub fn write_with_scratch(
+        &self,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+        scratch: &mut Vec<u8>,
+    ) -> encode::Res {
+        scratch.clear();
+        self.write(scratch, w)?;
+        buf.put_slice(scratch);
+        Ok(())
+    }
+
+    /// Attempts to write the NBT data into a buffer using the specified [Writer] encoding, followed
+    /// by zero bytes until the total amount of bytes written is a multiple of `boundary`.
+    ///
+    /// This is useful for formats that store data in fixed-size sectors, such as the 4096-byte
+    /// sectors of region files. Returns the amount of bytes written including the padding.
+    ///
+    /// Panics if `boundary` is zero.
+    pub fn write_padded(
+        &self,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+        boundary: usize,
+    ) -> Result<usize, ErrorPath<WriteError>> {
+        assert_ne!(boundary, 0, "boundary must be greater than zero");
+
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, w)?;
+        let padded = bytes.len().next_multiple_of(boundary);
+        buf.put_slice(&bytes);
+        buf.put_bytes(0, padded - bytes.len());
+        Ok(padded)
+    }
+
+    /// Attempts to write the NBT data into a cursor using the specified [Writer] encoding.
+    ///
+    /// The data is written at the current position of the cursor, overwriting any existing data
+    /// and growing the vector where needed, like [std::io::Write] does. The cursor is left
+    /// positioned directly after the written data so that more data can be appended.
+    pub fn write_to_cursor(
+        &self,
+        cursor: &mut Cursor<Vec<u8>>,
+        w: &mut impl Writer,
+    ) -> encode::Res {
+        let pos = cursor.position() as usize;
+        if pos == cursor.get_ref().len() {
+            // Appending to the vector does not require an intermediate buffer.
+            self.write(cursor.get_mut(), w)?;
+            cursor.set_position(cursor.get_ref().len() as u64);
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, w)?;
+        cursor
+            .write_all(&bytes)
+            .map_err(|err| ErrorPath::new(WriteError::Custom(err.to_string())))
+    }
+
+    /// Attempts to write the NBT data into an [std::io::Write] using the specified [Writer]
+    /// encoding, returning the amount of bytes written.
+    ///
+    /// The data is written into the output in chunks while it is being encoded. If the output
+    /// returns an error, the error is a [WriteError::Io] containing the amount of bytes that were
+    /// written successfully, which allows resuming or diagnosing the write. The path of the error
+    /// is that of the tag that completed the chunk which could not be written, rather than that of
+    /// the tag at the exact byte that failed.
+    pub fn write_io(
+        &self,
+        out: &mut impl Write,
+        w: &mut impl Writer,
+    ) -> Result<usize, ErrorPath<WriteError>> {
+        let mut io = IoWriter::new(out, w);
+        // The writer writes into the output itself, so the buffer remains empty.
+        self.write(&mut Vec::new(), &mut io)?;
+        io.finish()
+    }
+
+    /// Attempts to write the NBT data as a single named tag into a buffer using the specified
+    /// [Writer] encoding.
+    ///
+    /// See [Self::read_named_tag] for more info.
+    pub fn write_named_tag(
+        &self,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+        name: &str,
+    ) -> encode::Res {
+        w.write_u8(buf, self.tag_id())?;
+        w.write_string(buf, name)
+            .map_err(|err| err.prepend(PathPart::Key(name.to_string())))?;
+        match self {
+            Self::Compound(x) if w.omits_root_end() => {
+                WriteFrame::compound(x, w, false).write(buf, w)
+            }
+            _ => self.write_inner(buf, w),
+        }
+    }
+
+    /// Reads the tag id of a root tag, returning [ReadError::EmptyInput] if the buffer is empty.
+    fn read_root_id(buf: &mut impl Buf, r: &mut impl Reader) -> decode::Res<u8> {
+        if !buf.has_remaining() {
+            return Err(ErrorPath::new(ReadError::EmptyInput));
+        }
+        r.u8(buf)
+    }
+
     /// Internal function used to read NBT data. Slightly differs from [Self::read].
+    ///
+    /// Nested tags are read using an explicit stack rather than through recursion, so that deeply
+    /// nested data cannot overflow the stack.
     fn read_inner(buf: &mut impl Buf, tag_id: u8, r: &mut impl Reader) -> decode::Res<Self> {
-        Ok(match tag_id {
+        Self::read_with_stack(buf, tag_id, r, &mut Vec::new(), None)
+    }
+
+    /// Reads a tag using the provided stack, which must be empty. If an error occurs, the stack
+    /// is left containing the containers that were being read at the time of the error.
+    ///
+    /// If `recovered` is provided, list elements that can be skipped are replaced as described in
+    /// [Self::read_recovering], and their errors are added to it.
+    fn read_with_stack(
+        buf: &mut impl Buf,
+        tag_id: u8,
+        r: &mut impl Reader,
+        stack: &mut Vec<ReadFrame>,
+        mut recovered: Option<&mut Vec<ErrorPath<ReadError>>>,
+    ) -> decode::Res<Self> {
+        let mut tag_id = tag_id;
+        let omitted_root_end = r.reads_omitted_root_end();
+        loop {
+            // Read the tag itself. Container tags are pushed onto the stack to be filled later.
+            let mut value = match Self::read_value(buf, tag_id, r) {
+                Ok(ReadFrame::Done(value)) => Some(value),
+                Ok(_) if stack.len() >= r.max_depth() => {
+                    let err = ErrorPath::new(ReadError::TooDeep(r.max_depth()));
+                    return Err(ReadFrame::prepend_path(err, stack));
+                }
+                Ok(frame) => {
+                    stack.push(frame);
+                    None
+                }
+                Err(err) => match recovered.as_deref_mut() {
+                    // The bytes of a string have already been consumed when they turn out to be
+                    // invalid, so the next element starts right after it.
+                    Some(errors)
+                        if tag_id == 8
+                            && matches!(stack.last(), Some(ReadFrame::List { .. }))
+                            && matches!(
+                                err.inner,
+                                ReadError::InvalidString(_) | ReadError::InvalidModifiedUtf8(_)
+                            ) =>
+                    {
+                        errors.push(ReadFrame::prepend_path(err, stack));
+                        Some(NBTTag::String(String::new().into()))
+                    }
+                    _ => return Err(ReadFrame::prepend_path(err, stack)),
+                },
+            };
+
+            // Find the next tag to read, completing any containers that have been filled.
+            loop {
+                let root = stack.len() == 1;
+                let Some(frame) = stack.last_mut() else {
+                    // The stack is only empty once the outermost tag has been completed.
+                    return Ok(value.unwrap());
+                };
+                match frame.next(buf, value.take(), r, root && omitted_root_end) {
+                    Ok(Some(done)) => {
+                        stack.pop();
+                        value = Some(done);
+                    }
+                    Ok(None) => {
+                        tag_id = frame.content_type();
+                        break;
+                    }
+                    Err(err) => {
+                        return Err(ReadFrame::prepend_path(err, &stack[..stack.len() - 1]))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a single tag with the provided tag id. Container tags are only partially read, and
+    /// must be completed using [ReadFrame::next].
+    fn read_value(buf: &mut impl Buf, tag_id: u8, r: &mut impl Reader) -> decode::Res<ReadFrame> {
+        let value = match tag_id {
             1 => NBTTag::Byte(r.u8(buf)?.into()),
             2 => NBTTag::Short(r.i16(buf)?.into()),
             3 => NBTTag::Int(r.i32(buf)?.into()),
@@ -127,46 +653,73 @@ impl NBTTag {
             6 => NBTTag::Double(r.f64(buf)?.into()),
             8 => NBTTag::String(r.string(buf)?.into()),
             10 => {
-                let mut map = HashMap::new();
-                loop {
-                    let content_type = r.u8(buf)?;
-                    if content_type == 0 {
-                        break;
-                    }
-                    let name = r.string(buf)?;
-                    let value = Self::read_inner(buf, content_type, r)
-                        .map_err(|err| err.prepend(PathPart::MapKey(name.clone())))?;
-                    map.insert(name, value);
-                }
-                NBTTag::Compound(map.into())
+                return Ok(ReadFrame::Compound {
+                    map: HashMap::new(),
+                    key: String::new(),
+                    content_type: 0,
+                })
             }
             9 => {
-                let content_type = r.u8(buf)?;
-                let len = r.i32(buf)?;
-                if len < 0 {
-                    return Err(ErrorPath::new(ReadError::SeqLengthViolation(
-                        i32::MAX as usize,
-                        len as usize,
-                    )));
-                }
-                let mut vec = Vec::with_capacity(len as usize);
-                for i in 0..len {
-                    vec.push(
-                        Self::read_inner(buf, content_type, r)
-                            .map_err(|err| err.prepend(PathPart::Element(i as usize)))?,
-                    );
-                }
-                NBTTag::List(vec.into())
+                let (content_type, len) = Self::read_list_header(buf, r)?;
+                return Ok(ReadFrame::List {
+                    vec: Vec::with_capacity(len.min(buf.remaining())),
+                    len,
+                    content_type,
+                });
             }
             7 => NBTTag::ByteArray(r.u8_vec(buf)?.into()),
             11 => NBTTag::IntArray(r.i32_vec(buf)?.into()),
             12 => NBTTag::LongArray(r.i64_vec(buf)?.into()),
-            _ => panic!("Unknown tag type {}", tag_id),
-        })
+            _ => match (tag::CustomId::new(tag_id), r.custom_tag_reader(tag_id)) {
+                (Some(id), Some(read)) => NBTTag::Custom(tag::Custom {
+                    id,
+                    payload: read(buf).map_err(ErrorPath::new)?,
+                }),
+                _ => return Err(ErrorPath::new(ReadError::UnknownTagType(tag_id))),
+            },
+        };
+        Ok(ReadFrame::Done(value))
+    }
+
+    /// Reads the element type id and the length that precede the elements of a list tag.
+    pub(crate) fn read_list_header(
+        buf: &mut impl Buf,
+        r: &mut impl Reader,
+    ) -> decode::Res<(u8, usize)> {
+        let content_type = r.u8(buf)?;
+        let len = r.i32(buf)?;
+        if len < 0 {
+            return Err(ErrorPath::new(ReadError::SeqLengthViolation(
+                i32::MAX as usize,
+                len as usize,
+            )));
+        }
+        if content_type == 0 && len != 0 {
+            return Err(ErrorPath::new(ReadError::Custom(
+                "list with End element type has nonzero length".to_string(),
+            )));
+        }
+        Ok((content_type, len as usize))
     }
 
     /// Internal function used to write NBT data. Slightly differs from [Self::write].
+    ///
+    /// Nested tags are written using an explicit stack rather than through recursion, so that
+    /// deeply nested data cannot overflow the stack.
     fn write_inner(&self, buf: &mut impl BufMut, w: &mut impl Writer) -> encode::Res {
+        match self.write_value(buf, w)? {
+            Some(frame) => frame.write(buf, w),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the payload of a tag. For container tags, only the list header is written, and a
+    /// frame is returned for writing the children of the container.
+    fn write_value<'a>(
+        &'a self,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+    ) -> Result<Option<WriteFrame<'a>>, ErrorPath<WriteError>> {
         match self {
             Self::Byte(x) => w.write_u8(buf, x.0)?,
             Self::Short(x) => w.write_i16(buf, x.0)?,
@@ -175,41 +728,39 @@ impl NBTTag {
             Self::Float(x) => w.write_f32(buf, x.0)?,
             Self::Double(x) => w.write_f64(buf, x.0)?,
             Self::String(x) => w.write_string(buf, x.0.as_str())?,
-            Self::Compound(x) => {
-                for (name, val) in &x.0 {
-                    w.write_u8(buf, val.tag_id())?;
-                    w.write_string(buf, name)?;
-                    val.write_inner(buf, w)?;
-                }
-                w.write_end(buf)?;
-            }
+            Self::Compound(x) => return Ok(Some(WriteFrame::compound(x, w, true))),
             Self::List(x) => {
-                let first_id = if x.0.is_empty() {
-                    NBTTag::Byte(0.into()).tag_id()
+                let id = if x.is_empty() && w.end_typed_empty_lists() {
+                    0
                 } else {
-                    x.0[0].tag_id()
+                    x.element_type()
                 };
 
-                w.write_u8(buf, first_id)?;
+                w.write_u8(buf, id)?;
                 w.write_i32(buf, x.len() as i32)?;
-                for (i, v) in x.0.iter().enumerate() {
-                    if v.tag_id() != first_id {
-                        return Err(ErrorPath::new_with_path(
-                            WriteError::UnexpectedTag(
-                                x[0].tag_type().to_string(),
-                                v.tag_type().to_string(),
-                            ),
-                            Path::from_single(PathPart::Element(i)),
-                        ));
-                    }
-                    v.write_inner(buf, w)?;
-                }
+                return Ok(Some(WriteFrame::List {
+                    list: x,
+                    elements: x.0.iter().enumerate(),
+                    id,
+                    index: None,
+                }));
             }
             Self::ByteArray(x) => w.write_u8_vec(buf, &x.0)?,
             Self::IntArray(x) => w.write_i32_vec(buf, &x.0)?,
             Self::LongArray(x) => w.write_i64_vec(buf, &x.0)?,
+            Self::Raw(x) => w.write_raw(buf, &x.payload)?,
+            Self::Custom(x) => {
+                let Some(write) = w.custom_tag_writer(x.id.get()) else {
+                    return Err(ErrorPath::new(WriteError::UnknownTagType(x.id.get())));
+                };
+                // The payload is written through the writer like the rest of the data, so that
+                // writers that do not write into the buffer directly receive it as well.
+                let mut payload = Vec::new();
+                write(&x.payload, &mut payload).map_err(ErrorPath::new)?;
+                w.write_raw(buf, &payload)?
+            }
         };
-        Ok(())
+        Ok(None)
     }
 
     /// Gets the discriminator of a [NBTTag]'s type used for encoding and decoding.
@@ -227,7 +778,285 @@ impl NBTTag {
             NBTTag::ByteArray(_) => 7,
             NBTTag::IntArray(_) => 11,
             NBTTag::LongArray(_) => 12,
+            NBTTag::Raw(v) => v.tag_type.id(),
+            NBTTag::Custom(v) => v.id.get(),
+        }
+    }
+}
+
+/// A container tag of which the children are being written by [NBTTag::write_inner].
+pub(crate) enum WriteFrame<'a> {
+    /// A compound tag. The key is that of the entry currently being written, if any. The `END`
+    /// tag is only written after the entries if `end` is true.
+    Compound {
+        entries: WriteEntries<'a>,
+        key: Option<&'a str>,
+        end: bool,
+        omit_defaults: bool,
+    },
+    /// A list tag with elements of type `id`. The index is that of the element currently being
+    /// written, if any.
+    List {
+        list: &'a tag::List,
+        elements: iter::Enumerate<slice::Iter<'a, NBTTag>>,
+        id: u8,
+        index: Option<usize>,
+    },
+}
+
+/// The entries of a compound tag that remain to be written, in the order they are written in.
+pub(crate) enum WriteEntries<'a> {
+    /// The entries in the order of the map.
+    Unsorted(hash_map::Iter<'a, String, NBTTag>),
+    /// The entries sorted using [Writer::key_comparator].
+    Sorted(vec::IntoIter<(&'a String, &'a NBTTag)>),
+}
+
+impl<'a> Iterator for WriteEntries<'a> {
+    type Item = (&'a String, &'a NBTTag);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            WriteEntries::Unsorted(iter) => iter.next(),
+            WriteEntries::Sorted(iter) => iter.next(),
+        }
+    }
+}
+
+impl<'a> WriteFrame<'a> {
+    /// Prepares to write the entries of a compound tag, followed by an `END` tag if `end` is true.
+    pub(crate) fn compound(compound: &'a tag::Compound, w: &impl Writer, end: bool) -> Self {
+        let entries = match w.key_comparator() {
+            Some(cmp) => {
+                let mut entries: Vec<_> = compound.0.iter().collect();
+                entries.sort_by(|a, b| cmp(a.0, b.0));
+                WriteEntries::Sorted(entries.into_iter())
+            }
+            None => WriteEntries::Unsorted(compound.0.iter()),
+        };
+        WriteFrame::Compound {
+            entries,
+            key: None,
+            end,
+            omit_defaults: w.omits_defaults(),
+        }
+    }
+
+    /// Writes the children of the container, and those of any containers nested in it.
+    pub(crate) fn write(self, buf: &mut impl BufMut, w: &mut impl Writer) -> encode::Res {
+        let mut stack = vec![self];
+        Self::write_stack(&mut stack, buf, w).map_err(|err| Self::prepend_path(err, &stack))
+    }
+
+    /// Writes the children of the containers on the stack until it is empty. If an error occurs,
+    /// the stack is left containing the containers that were being written at the time.
+    fn write_stack(
+        stack: &mut Vec<Self>,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+    ) -> encode::Res {
+        while let Some(frame) = stack.last_mut() {
+            let next = match frame {
+                WriteFrame::Compound {
+                    entries,
+                    key,
+                    end,
+                    omit_defaults,
+                } => {
+                    *key = None;
+                    let omit_defaults = *omit_defaults;
+                    match entries.find(|(_, v)| !(omit_defaults && v.is_default_value())) {
+                        Some((name, value)) => {
+                            w.write_u8(buf, value.tag_id())?;
+                            w.write_string(buf, name)
+                                .map_err(|err| err.prepend(PathPart::Key(name.clone())))?;
+                            *key = Some(name);
+                            Some(value)
+                        }
+                        None => {
+                            if *end {
+                                w.write_end(buf)?;
+                            }
+                            None
+                        }
+                    }
+                }
+                WriteFrame::List {
+                    list,
+                    elements,
+                    id,
+                    index,
+                } => {
+                    *index = None;
+                    match elements.next() {
+                        Some((i, value)) => {
+                            if value.tag_id() != *id {
+                                return Err(ErrorPath::new_with_path(
+                                    WriteError::UnexpectedTag(
+                                        list[0].tag_type().to_string(),
+                                        value.tag_type().to_string(),
+                                    ),
+                                    Path::from_single(PathPart::Element(i)),
+                                ));
+                            }
+                            *index = Some(i);
+                            Some(value)
+                        }
+                        None => None,
+                    }
+                }
+            };
+            match next {
+                Some(value) => stack.extend(value.write_value(buf, w)?),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepends the location of the tags currently being written in each of the frames to the
+    /// path of an error.
+    fn prepend_path(mut err: ErrorPath<WriteError>, stack: &[Self]) -> ErrorPath<WriteError> {
+        for frame in stack.iter().rev() {
+            match frame {
+                WriteFrame::Compound { key: Some(key), .. } => {
+                    err = err.prepend(PathPart::MapKey(key.to_string()))
+                }
+                WriteFrame::List {
+                    index: Some(index), ..
+                } => err = err.prepend(PathPart::Element(*index)),
+                _ => {}
+            }
         }
+        err
+    }
+}
+
+/// A partially read container tag used by [NBTTag::read_inner].
+enum ReadFrame {
+    /// A compound tag. The key and content type are those of the entry currently being read.
+    Compound {
+        map: HashMap<String, NBTTag>,
+        key: String,
+        content_type: u8,
+    },
+    /// A list tag of which `len` elements of type `content_type` are to be read.
+    List {
+        vec: Vec<NBTTag>,
+        len: usize,
+        content_type: u8,
+    },
+    /// A tag that has been read completely.
+    Done(NBTTag),
+}
+
+impl ReadFrame {
+    /// Adds a tag that has been read to the container, and prepares to read the next tag.
+    ///
+    /// Returns the container tag if it has been read completely, or [None] if another tag of type
+    /// [Self::content_type] should be read and added to the container. If `end_optional` is true,
+    /// a compound is also complete once the buffer has been read completely.
+    fn next(
+        &mut self,
+        buf: &mut impl Buf,
+        value: Option<NBTTag>,
+        r: &mut impl Reader,
+        end_optional: bool,
+    ) -> decode::Res<Option<NBTTag>> {
+        match self {
+            ReadFrame::Compound {
+                map,
+                key,
+                content_type,
+            } => {
+                if let Some(value) = value {
+                    map.insert(mem::take(key), value);
+                }
+                if !buf.has_remaining() {
+                    if end_optional {
+                        return Ok(Some(NBTTag::Compound(mem::take(map).into())));
+                    }
+                    // Either the next entry or the END tag has to follow.
+                    return Err(ErrorPath::new(ReadError::MissingEndTag));
+                }
+                *content_type = r.u8(buf)?;
+                if *content_type == 0 {
+                    return Ok(Some(NBTTag::Compound(mem::take(map).into())));
+                }
+                r.check_cancelled()?;
+                if let Some(max) = r.max_compound_entries() {
+                    if map.len() >= max {
+                        return Err(ErrorPath::new(ReadError::SeqLengthViolation(
+                            max,
+                            map.len() + 1,
+                        )));
+                    }
+                }
+                *key = r.string(buf)?;
+                Ok(None)
+            }
+            ReadFrame::List {
+                vec,
+                len,
+                content_type,
+            } => {
+                if let Some(value) = value {
+                    vec.push(value);
+                }
+                if vec.len() == *len {
+                    return Ok(Some(NBTTag::List(tag::List(mem::take(vec), *content_type))));
+                }
+                r.check_cancelled()?;
+                Ok(None)
+            }
+            ReadFrame::Done(_) => unreachable!(),
+        }
+    }
+
+    /// Converts a partially read container into a tag, adding the partially read tag that was
+    /// being read into the container, if any.
+    fn into_partial(self, value: Option<NBTTag>) -> NBTTag {
+        match self {
+            ReadFrame::Compound { mut map, key, .. } => {
+                if let Some(value) = value {
+                    map.insert(key, value);
+                }
+                NBTTag::Compound(map.into())
+            }
+            ReadFrame::List {
+                mut vec,
+                content_type,
+                ..
+            } => {
+                vec.extend(value);
+                NBTTag::List(tag::List(vec, content_type))
+            }
+            ReadFrame::Done(_) => unreachable!(),
+        }
+    }
+
+    /// The tag id of the tag that is currently being read into the container.
+    fn content_type(&self) -> u8 {
+        match self {
+            ReadFrame::Compound { content_type, .. } => *content_type,
+            ReadFrame::List { content_type, .. } => *content_type,
+            ReadFrame::Done(_) => unreachable!(),
+        }
+    }
+
+    /// Prepends the location of the tags currently being read in each of the frames to the path
+    /// of an error.
+    fn prepend_path(mut err: ErrorPath<ReadError>, stack: &[ReadFrame]) -> ErrorPath<ReadError> {
+        for frame in stack.iter().rev() {
+            err = err.prepend(match frame {
+                ReadFrame::Compound { key, .. } => PathPart::MapKey(key.clone()),
+                ReadFrame::List { vec, .. } => PathPart::Element(vec.len()),
+                ReadFrame::Done(_) => unreachable!(),
+            });
+        }
+        err
     }
 }
 
@@ -236,3 +1065,860 @@ impl Default for NBTTag {
         Self::Compound(HashMap::new().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use bytes::Buf;
+
+    use crate::decode::{self, Reader};
+    use crate::encoding::{AnyEncoding, BigEndian, LittleEndian, NetworkLittleEndian};
+    use crate::err::{Path, PathPart, ReadError, WriteError};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_flatten() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound(
+                    "Player",
+                    tag::Compound::builder()
+                        .with_string("Name", "Zuri")
+                        .with_list("Pos", vec![tag::Double(1.0), tag::Double(2.5)])
+                        .with_compound("Abilities", tag::Compound::default()),
+                )
+                .with_int("DataVersion", 3465)
+                .build(),
+        );
+        let flat: Vec<_> = nbt
+            .flatten()
+            .into_iter()
+            .map(|(k, v)| (k, v.clone()))
+            .collect();
+        assert_eq!(
+            flat,
+            [
+                ("DataVersion".to_string(), NBTTag::Int(3465.into())),
+                (
+                    "Player.Abilities".to_string(),
+                    NBTTag::Compound(Default::default())
+                ),
+                ("Player.Name".to_string(), NBTTag::String("Zuri".into())),
+                ("Player.Pos[0]".to_string(), NBTTag::Double(1.0.into())),
+                ("Player.Pos[1]".to_string(), NBTTag::Double(2.5.into())),
+            ]
+        );
+
+        let int = NBTTag::Int(1.into());
+        assert_eq!(int.flatten(), [(String::new(), &int)]);
+    }
+
+    #[test]
+    fn test_read_missing_end_tag() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound("inner", tag::Compound::builder().with_int("a", 1))
+                .build(),
+        );
+        let mut buf = Vec::new();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+
+        let err = NBTTag::read_slice(&buf[..buf.len() - 1], &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::MissingEndTag));
+        assert!(err.path.0.is_empty());
+
+        // Both END tags are missing, which is reported for the innermost compound.
+        let err = NBTTag::read_slice(&buf[..buf.len() - 2], &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::MissingEndTag));
+        assert_eq!(err.path.0, [PathPart::MapKey("inner".to_string())]);
+
+        // Truncation within an entry is still reported as a regular EOF.
+        let err = NBTTag::read_slice(&buf[..buf.len() - 3], &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_read_huge_list_length() {
+        // A list declaring almost `i32::MAX` elements must not be allocated up front.
+        let bytes = [9, 0, 0, 1, 0x7f, 0xff, 0xff, 0xff];
+        let err = NBTTag::read_slice(&bytes, &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let err = NBTTag::read(&mut [].as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+        let (err, partial) =
+            NBTTag::read_partial(&mut [].as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+        assert_eq!(partial, None);
+        let err =
+            NBTTag::read_recovering(&mut [].as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+
+        // Reading named tags one after another ends with an empty buffer.
+        let mut buf = Vec::new();
+        for value in 1..=2 {
+            NBTTag::Int(value.into())
+                .write(&mut buf, &mut BigEndian::default())
+                .unwrap();
+        }
+        let mut slice = buf.as_slice();
+        for value in 1..=2 {
+            let (_, tag) = NBTTag::read_named_tag(&mut slice, &mut BigEndian::default()).unwrap();
+            assert_eq!(tag, value);
+        }
+        let err = NBTTag::read_named_tag(&mut slice, &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+
+        // Data that is cut off is still reported as a regular EOF.
+        let err = NBTTag::read(&mut &buf[..1], &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_peek_root_type() {
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_int("a", 1).build());
+        let mut buf = Vec::new();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+
+        let mut slice = buf.as_slice();
+        assert_eq!(NBTTag::peek_root_type(&slice).unwrap(), 10);
+        // Nothing was consumed, so the data can still be read completely.
+        assert_eq!(slice.len(), buf.len());
+        assert_eq!(
+            NBTTag::read(&mut slice, &mut BigEndian::default()).unwrap(),
+            nbt
+        );
+
+        let err = NBTTag::peek_root_type(&[].as_slice()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+
+        // The first byte is found even if the first part of the buffer is empty.
+        let chained = [].as_slice().chain(buf.as_slice());
+        assert_eq!(NBTTag::peek_root_type(&chained).unwrap(), 10);
+        let err = NBTTag::peek_root_type(&[].as_slice().chain([].as_slice())).unwrap_err();
+        assert!(matches!(err.inner, ReadError::EmptyInput));
+    }
+
+    #[test]
+    fn test_int_array() {
+        // The size of the height map of a Java Edition chunk before 1.13.
+        let height_map: Vec<i32> = (0..256).map(|i| i * 3 - 100).collect();
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int_array("HeightMap", height_map.clone())
+                .with_list(
+                    "Sections",
+                    vec![
+                        tag::IntArray(vec![]),
+                        tag::IntArray(vec![i32::MIN, i32::MAX]),
+                    ],
+                )
+                .build(),
+        );
+        for mut encoding in [
+            AnyEncoding::BigEndian(BigEndian::default()),
+            AnyEncoding::LittleEndian(LittleEndian::default()),
+            AnyEncoding::NetworkLittleEndian(NetworkLittleEndian::default()),
+        ] {
+            let buf = nbt.to_bytes(&mut encoding).unwrap();
+            assert_eq!(NBTTag::read_slice(&buf, &mut encoding).unwrap(), nbt);
+        }
+
+        let mut buf = Vec::new();
+        NBTTag::IntArray(height_map.clone().into())
+            .write_payload(&mut buf, &mut BigEndian::default())
+            .unwrap();
+        assert_eq!(buf.len(), 4 + 256 * 4);
+        assert_eq!(buf[..8], [0, 0, 1, 0, 0xFF, 0xFF, 0xFF, 0x9C]);
+        let read = NBTTag::read_typed(&mut buf.as_slice(), 11, &mut BigEndian::default());
+        assert_eq!(read.unwrap(), NBTTag::IntArray(height_map.into()));
+        assert_eq!(NBTTagType::from_id(11), Some(NBTTagType::IntArray));
+        assert_eq!(NBTTagType::IntArray.id(), 11);
+    }
+
+    #[test]
+    fn test_read_typed() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_int_array("ids", vec![1, -2, 3])
+                .build(),
+        );
+        let mut buf = Vec::new();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+        // Skip the tag id and the empty name of the root tag.
+        let payload = &buf[3..];
+        assert_eq!(
+            NBTTag::read_typed(&mut &*payload, 10, &mut BigEndian::default()).unwrap(),
+            nbt
+        );
+
+        let payload = [0, 0, 0, 2, 0, 0, 0, 7, 255, 255, 255, 255];
+        assert_eq!(
+            NBTTag::read_typed(&mut payload.as_slice(), 11, &mut BigEndian::default()).unwrap(),
+            NBTTag::IntArray(vec![7, -1].into())
+        );
+
+        let err = NBTTag::read_typed(&mut payload.as_slice(), 13, &mut BigEndian::default());
+        assert!(matches!(
+            err.unwrap_err().inner,
+            ReadError::UnknownTagType(13)
+        ));
+    }
+
+    #[test]
+    fn test_read_cancelled() {
+        /// Sets the cancellation token after a fixed amount of integers have been read.
+        struct CancelAfter {
+            inner: BigEndian,
+            token: Arc<AtomicBool>,
+            remaining: usize,
+        }
+
+        impl Reader for CancelAfter {
+            fn i16(&mut self, buf: &mut impl Buf) -> decode::Res<i16> {
+                self.inner.i16(buf)
+            }
+            fn i32(&mut self, buf: &mut impl Buf) -> decode::Res<i32> {
+                self.remaining -= 1;
+                if self.remaining == 0 {
+                    self.token.store(true, Ordering::Relaxed);
+                }
+                self.inner.i32(buf)
+            }
+            fn i64(&mut self, buf: &mut impl Buf) -> decode::Res<i64> {
+                self.inner.i64(buf)
+            }
+            fn f32(&mut self, buf: &mut impl Buf) -> decode::Res<f32> {
+                self.inner.f32(buf)
+            }
+            fn f64(&mut self, buf: &mut impl Buf) -> decode::Res<f64> {
+                self.inner.f64(buf)
+            }
+            fn cancelled(&self) -> bool {
+                self.inner.cancelled()
+            }
+        }
+
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list(
+                    "list",
+                    (0..10).map(|i| NBTTag::Int(i.into())).collect::<Vec<_>>(),
+                )
+                .build(),
+        );
+        let mut buf = Vec::new();
+        nbt.write(&mut buf, &mut BigEndian::default()).unwrap();
+
+        let token = Arc::new(AtomicBool::new(false));
+        let mut r = CancelAfter {
+            inner: BigEndian::default().cancel_token(token.clone()),
+            token: token.clone(),
+            // The list length is read as an integer as well.
+            remaining: 4,
+        };
+        let err = NBTTag::read(&mut buf.as_slice(), &mut r).unwrap_err();
+        assert!(matches!(err.inner, ReadError::Cancelled));
+        assert_eq!(err.path.0, [PathPart::MapKey("list".to_string())]);
+
+        token.store(false, Ordering::Relaxed);
+        let mut r = BigEndian::default().cancel_token(token);
+        assert_eq!(NBTTag::read(&mut buf.as_slice(), &mut r).unwrap(), nbt);
+    }
+
+    #[test]
+    fn test_read_deeply_nested() {
+        const DEPTH: usize = 5000;
+
+        // Each compound contains a single compound with an empty key, apart from the innermost.
+        let mut bytes = vec![10, 0, 0];
+        for _ in 0..DEPTH {
+            bytes.extend([10, 0, 0]);
+        }
+        bytes.extend([3, 0, 1, b'x', 0, 0, 0, 42]);
+        bytes.extend(vec![0; DEPTH + 1]);
+
+        // The default depth limit rejects the data, but it can be raised to read it.
+        let err = NBTTag::read(&mut bytes.as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(
+            err.inner,
+            ReadError::TooDeep(decode::DEFAULT_MAX_DEPTH)
+        ));
+        assert_eq!(err.path.0.len(), decode::DEFAULT_MAX_DEPTH);
+        let mut r = BigEndian::default().max_depth(DEPTH + 1);
+        let err = NBTTag::read(
+            &mut bytes.as_slice(),
+            &mut BigEndian::default().max_depth(DEPTH),
+        )
+        .unwrap_err();
+        assert!(matches!(err.inner, ReadError::TooDeep(DEPTH)));
+        let nbt = NBTTag::read(&mut bytes.as_slice(), &mut r).unwrap();
+
+        let mut depth = 0;
+        let mut current = &nbt;
+        while let NBTTag::Compound(c) = current {
+            current = c.values().next().unwrap();
+            depth += 1;
+        }
+        assert_eq!(depth, DEPTH + 1);
+        assert_eq!(current, &NBTTag::Int(42.into()));
+
+        // Writing and dropping the tag does not recurse either.
+        assert_eq!(nbt.to_bytes(&mut BigEndian::default()).unwrap(), bytes);
+        drop(nbt);
+
+        let mut list = NBTTag::Int(42.into());
+        for _ in 0..DEPTH {
+            list = NBTTag::List(vec![list].into());
+        }
+        let bytes = list.to_bytes(&mut BigEndian::default()).unwrap();
+        let read = NBTTag::read(&mut bytes.as_slice(), &mut r).unwrap();
+        assert_eq!(read.to_bytes(&mut BigEndian::default()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_read_recovering() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("lore", vec![tag::String::from("a"), "X".into(), "c".into()])
+                .with_list(
+                    "nested",
+                    vec![NBTTag::List(vec![tag::String::from("X")].into())],
+                )
+                .build(),
+        );
+        let mut bytes = nbt.to_bytes(&mut LittleEndian::default()).unwrap();
+        for b in bytes.iter_mut().filter(|b| **b == b'X') {
+            *b = 0xFF;
+        }
+
+        let (read, errors) =
+            NBTTag::read_recovering(&mut bytes.as_slice(), &mut LittleEndian::default()).unwrap();
+        let expected = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("lore", vec![tag::String::from("a"), "".into(), "c".into()])
+                .with_list(
+                    "nested",
+                    vec![NBTTag::List(vec![tag::String::from("")].into())],
+                )
+                .build(),
+        );
+        assert_eq!(read, expected);
+        let mut paths: Vec<_> = errors.iter().map(|err| err.path.to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, ["lore[1]", "nested[0][0]"]);
+        assert!(errors
+            .iter()
+            .all(|err| matches!(err.inner, ReadError::InvalidString(_))));
+
+        // Other errors still stop reading.
+        let err =
+            NBTTag::read_recovering(&mut &bytes[..bytes.len() - 2], &mut LittleEndian::default())
+                .unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_read_partial() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("a", 1)
+                .with_compound(
+                    "b",
+                    tag::Compound::builder()
+                        .with_string("c", "d")
+                        .with_long("e", 2),
+                )
+                .build(),
+        );
+        let mut bytes = Vec::new();
+        let mut w = BigEndian::default().key_comparator(|a, b| a.cmp(b));
+        nbt.write(&mut bytes, &mut w).unwrap();
+        assert_eq!(
+            NBTTag::read_partial(&mut bytes.as_slice(), &mut BigEndian::default()).unwrap(),
+            nbt
+        );
+
+        // Cut off the data halfway through the value of `b.e`, the last value that was written.
+        bytes.truncate(bytes.len() - 6);
+        let (err, partial) =
+            NBTTag::read_partial(&mut bytes.as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        let expected = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("a", 1)
+                .with_compound("b", tag::Compound::builder().with_string("c", "d"))
+                .build(),
+        );
+        assert_eq!(partial, Some(expected));
+
+        let (_, partial) =
+            NBTTag::read_partial(&mut [10u8].as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(partial.is_none());
+    }
+
+    #[test]
+    fn test_read_end_typed_list() {
+        let nbt = NBTTag::read_slice(&[9, 0, 0, 0, 0, 0, 0, 0], &mut BigEndian::default()).unwrap();
+        let NBTTag::List(list) = nbt else {
+            unreachable!()
+        };
+        assert!(list.is_empty());
+        assert_eq!(list.element_type(), 0);
+
+        let err =
+            NBTTag::read_slice(&[9, 0, 0, 0, 0, 0, 0, 1], &mut BigEndian::default()).unwrap_err();
+        assert!(
+            matches!(err.inner, ReadError::Custom(msg) if msg == "list with End element type has nonzero length")
+        );
+    }
+
+    #[test]
+    fn test_read_at() {
+        let chunk = |x: i32| NBTTag::Compound(tag::Compound::builder().with_int("xPos", x).build());
+        let mut data = vec![0xff; 3];
+        chunk(1)
+            .write(&mut data, &mut BigEndian::default())
+            .unwrap();
+        chunk(-5)
+            .write(&mut data, &mut BigEndian::default())
+            .unwrap();
+
+        let (first, len) = NBTTag::read_at(&data, 3, &mut BigEndian::default()).unwrap();
+        assert_eq!(first, chunk(1));
+        let (second, len2) = NBTTag::read_at(&data, 3 + len, &mut BigEndian::default()).unwrap();
+        assert_eq!(second, chunk(-5));
+        assert_eq!(3 + len + len2, data.len());
+
+        let err = NBTTag::read_at(&data, data.len() + 1, &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_read_slice() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_byte_array("bytes", (0..=255).collect::<Vec<u8>>())
+                .with_list(
+                    "list",
+                    vec![tag::String("a".into()), tag::String("b".into())],
+                )
+                .build(),
+        );
+        let mut bytes = Vec::new();
+        nbt.write(&mut bytes, &mut BigEndian::default()).unwrap();
+
+        let from_slice = NBTTag::read_slice(&bytes, &mut BigEndian::default()).unwrap();
+        assert_eq!(from_slice, nbt);
+
+        // Compare against a buffer that is not contiguous.
+        for split in [1, 7, bytes.len() / 2, bytes.len() - 1] {
+            let (a, b) = bytes.split_at(split);
+            let mut chained = a.chain(b);
+            let generic = NBTTag::read(&mut chained, &mut BigEndian::default()).unwrap();
+            assert_eq!(generic, from_slice);
+        }
+
+        let err = NBTTag::read_slice(&bytes[..20], &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_read_error_path() {
+        // {a: [{b: <truncated int>}]}
+        let bytes = [10, 0, 0, 9, 0, 1, b'a', 10, 0, 0, 0, 1, 3, 0, 1, b'b', 0, 0];
+        let err = NBTTag::read(&mut bytes.as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        assert_eq!(
+            err.path.0,
+            [
+                PathPart::MapKey("a".to_string()),
+                PathPart::Element(0),
+                PathPart::MapKey("b".to_string()),
+            ]
+        );
+
+        let err = NBTTag::read(&mut [13, 0, 0].as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnknownTagType(13)));
+    }
+
+    #[test]
+    fn test_write_error_path() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("a", vec![NBTTag::Int(1.into()), NBTTag::Int(2.into())])
+                .with_list("b", vec![NBTTag::Int(1.into()), NBTTag::Byte(2.into())])
+                .build(),
+        );
+        let err = nbt
+            .write(&mut Vec::new(), &mut BigEndian::default())
+            .unwrap_err();
+        assert!(matches!(err.inner, WriteError::UnexpectedTag(_, _)));
+        assert_eq!(
+            err.path.0,
+            [PathPart::MapKey("b".to_string()), PathPart::Element(1)]
+        );
+    }
+
+    #[test]
+    fn test_named_tag() {
+        let level = NBTTag::Compound(tag::Compound::builder().with_long("seed", 7).build());
+        let player = NBTTag::String("Zuri".to_string().into());
+
+        // A custom container holding two named tags back to back.
+        let mut bytes = Vec::new();
+        let mut w = BigEndian::default();
+        level.write_named_tag(&mut bytes, &mut w, "level").unwrap();
+        player
+            .write_named_tag(&mut bytes, &mut w, "player")
+            .unwrap();
+
+        let mut buf = bytes.as_slice();
+        let mut r = BigEndian::default();
+        assert_eq!(
+            NBTTag::read_named_tag(&mut buf, &mut r).unwrap(),
+            ("level".to_string(), level)
+        );
+        assert_eq!(
+            NBTTag::read_named_tag(&mut buf, &mut r).unwrap(),
+            ("player".to_string(), player)
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_write_padded() {
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_int("x", 1).build());
+        let mut unpadded = Vec::new();
+        nbt.write(&mut unpadded, &mut BigEndian::default()).unwrap();
+
+        let mut bytes = Vec::new();
+        let len = nbt
+            .write_padded(&mut bytes, &mut BigEndian::default(), 4096)
+            .unwrap();
+        assert_eq!(len, 4096);
+        assert_eq!(bytes.len(), 4096);
+        assert_eq!(bytes[..unpadded.len()], unpadded);
+        assert!(bytes[unpadded.len()..].iter().all(|b| *b == 0));
+
+        // Data that is already aligned is not padded any further.
+        let mut bytes = Vec::new();
+        let len = nbt
+            .write_padded(&mut bytes, &mut BigEndian::default(), unpadded.len())
+            .unwrap();
+        assert_eq!(len, unpadded.len());
+        assert_eq!(bytes, unpadded);
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_string("a", "b").build());
+        let bytes = nbt.to_bytes(&mut BigEndian::default()).unwrap();
+        assert_eq!(bytes, [10, 0, 0, 8, 0, 1, b'a', 0, 1, b'b', 0]);
+
+        let with_capacity = nbt
+            .to_bytes_with_capacity(&mut BigEndian::default(), 64)
+            .unwrap();
+        assert_eq!(with_capacity, bytes);
+        assert_eq!(with_capacity.capacity(), 64);
+    }
+
+    #[test]
+    fn test_write_long_key() {
+        let key = "a".repeat(i16::MAX as usize + 1);
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound("tag", tag::Compound::builder().with_int(&key, 1))
+                .build(),
+        );
+        let err = nbt
+            .write(&mut Vec::new(), &mut BigEndian::default())
+            .unwrap_err();
+        assert!(matches!(err.inner, WriteError::SeqLengthViolation(_, _)));
+        assert_eq!(
+            err.path.0,
+            [PathPart::MapKey("tag".to_string()), PathPart::Key(key)]
+        );
+
+        let err = NBTTag::Int(1.into())
+            .write_named_tag(
+                &mut Vec::new(),
+                &mut LittleEndian::default(),
+                &"b".repeat(40000),
+            )
+            .unwrap_err();
+        assert!(matches!(err.path.0[0], PathPart::Key(_)));
+
+        let path = Path::from_single(PathPart::Key("Name".to_string()));
+        assert_eq!(path.to_string(), "Name (key)");
+    }
+
+    #[test]
+    fn test_write_with_scratch() {
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_string("a", "b").build());
+        let expected = nbt.to_bytes(&mut NetworkLittleEndian::default()).unwrap();
+
+        let mut buf = Vec::new();
+        let mut scratch = Vec::new();
+        for _ in 0..2 {
+            nbt.write_with_scratch(&mut buf, &mut NetworkLittleEndian::default(), &mut scratch)
+                .unwrap();
+        }
+        assert_eq!(buf, [expected.as_slice(), expected.as_slice()].concat());
+        assert_eq!(scratch, expected);
+
+        // Nothing is written if the tag cannot be written.
+        let invalid = NBTTag::List(vec![NBTTag::Int(1.into()), NBTTag::Byte(2.into())].into());
+        let mut buf = Vec::new();
+        assert!(invalid
+            .write_with_scratch(&mut buf, &mut BigEndian::default(), &mut scratch)
+            .is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_write_to_cursor() {
+        let nbt = NBTTag::Compound(tag::Compound::builder().with_int("x", 1).build());
+        let mut expected = Vec::new();
+        nbt.write(&mut expected, &mut BigEndian::default()).unwrap();
+
+        let mut cursor = Cursor::new(vec![0xff; 2]);
+        cursor.set_position(2);
+        nbt.write_to_cursor(&mut cursor, &mut BigEndian::default())
+            .unwrap();
+        assert_eq!(cursor.position() as usize, 2 + expected.len());
+        assert_eq!(cursor.get_ref()[2..], expected);
+
+        // Writing in the middle of the data overwrites it.
+        let mut cursor = Cursor::new(vec![0xff; 20]);
+        cursor.set_position(1);
+        nbt.write_to_cursor(&mut cursor, &mut BigEndian::default())
+            .unwrap();
+        assert_eq!(cursor.position() as usize, 1 + expected.len());
+        assert_eq!(cursor.get_ref()[1..1 + expected.len()], expected);
+        assert_eq!(cursor.get_ref().len(), 20);
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let inner = NBTTag::Compound(tag::Compound::builder().with_int("x", 42).build());
+        let mut payload = Vec::new();
+        inner
+            .write(&mut payload, &mut BigEndian::default())
+            .unwrap();
+        // Strip the tag id and the empty name to get just the payload.
+        payload.drain(..3);
+
+        let raw = NBTTag::Compound(
+            tag::Compound::builder()
+                .with(
+                    "inner",
+                    NBTTag::Raw(tag::Raw {
+                        tag_type: NBTTagType::Compound,
+                        payload,
+                    }),
+                )
+                .with_list("values", vec![tag::Int(1)])
+                .build(),
+        );
+        let mut bytes = Vec::new();
+        raw.write(&mut bytes, &mut BigEndian::default()).unwrap();
+
+        let expected = NBTTag::Compound(
+            tag::Compound::builder()
+                .with("inner", inner)
+                .with_list("values", vec![tag::Int(1)])
+                .build(),
+        );
+        assert_eq!(
+            NBTTag::read_slice(&bytes, &mut BigEndian::default()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_collect_strings() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("title", "Hello")
+                .with_int("count", 1)
+                .with_compound(
+                    "display",
+                    tag::Compound::builder().with_list(
+                        "Lore",
+                        vec![tag::String("first".into()), tag::String("second".into())],
+                    ),
+                )
+                .build(),
+        );
+        let key = |k: &str| PathPart::MapKey(k.to_string());
+        assert_eq!(
+            nbt.collect_strings(),
+            [
+                (
+                    vec![key("display"), key("Lore"), PathPart::Element(0)],
+                    "first"
+                ),
+                (
+                    vec![key("display"), key("Lore"), PathPart::Element(1)],
+                    "second"
+                ),
+                (vec![key("title")], "Hello"),
+            ]
+        );
+        assert_eq!(
+            NBTTag::String("root".into()).collect_strings(),
+            [(vec![], "root")]
+        );
+    }
+
+    #[test]
+    fn test_len() {
+        let compound = tag::Compound::builder()
+            .with_int("a", 1)
+            .with_int("b", 2)
+            .build();
+        assert_eq!(NBTTag::Compound(compound).len(), Some(2));
+        assert_eq!(
+            NBTTag::List(vec![tag::Int(1), tag::Int(2), tag::Int(3)].into()).len(),
+            Some(3)
+        );
+        assert_eq!(NBTTag::ByteArray(vec![1, 2].into()).len(), Some(2));
+        assert_eq!(NBTTag::IntArray(vec![1].into()).len(), Some(1));
+        assert_eq!(NBTTag::LongArray(vec![].into()).len(), Some(0));
+        assert_eq!(NBTTag::String("héllo".into()).len(), Some(6));
+        assert_eq!(NBTTag::Int(5.into()).len(), None);
+    }
+
+    #[test]
+    fn test_is_empty() {
+        assert_eq!(NBTTag::Compound(Default::default()).is_empty(), Some(true));
+        assert_eq!(NBTTag::List(Default::default()).is_empty(), Some(true));
+        assert_eq!(NBTTag::ByteArray(vec![0].into()).is_empty(), Some(false));
+        assert_eq!(NBTTag::IntArray(vec![].into()).is_empty(), Some(true));
+        assert_eq!(NBTTag::LongArray(vec![1].into()).is_empty(), Some(false));
+        assert_eq!(NBTTag::String("".into()).is_empty(), Some(true));
+        assert_eq!(NBTTag::Double(0.0.into()).is_empty(), None);
+    }
+
+    #[test]
+    fn test_custom_tag() {
+        // A fake tag type holding a colour of three bytes.
+        let encoding = || {
+            BigEndian::default().custom_tag(
+                20,
+                |buf| {
+                    if buf.remaining() < 3 {
+                        return Err(ReadError::UnexpectedEOF);
+                    }
+                    Ok(buf.copy_to_bytes(3).to_vec())
+                },
+                |payload, buf| {
+                    buf.put_slice(payload);
+                    Ok(())
+                },
+            )
+        };
+        let colour = |payload: [u8; 3]| {
+            NBTTag::Custom(tag::Custom {
+                id: tag::CustomId::new(20).unwrap(),
+                payload: payload.to_vec(),
+            })
+        };
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with("colour", colour([255, 0, 0]))
+                .with(
+                    "palette",
+                    tag::List(vec![colour([0, 1, 2]), colour([3, 4, 5])], 1),
+                )
+                .build(),
+        );
+
+        let bytes = nbt.to_bytes(&mut encoding()).unwrap();
+        assert_eq!(
+            NBTTag::read(&mut bytes.as_slice(), &mut encoding()).unwrap(),
+            nbt
+        );
+        let mut any = AnyEncoding::BigEndian(encoding());
+        assert_eq!(NBTTag::read(&mut bytes.as_slice(), &mut any).unwrap(), nbt);
+        assert_eq!(nbt.tag_type(), NBTTagType::Compound);
+        assert_eq!(
+            colour([0, 0, 0]).tag_type(),
+            NBTTagType::Custom(tag::CustomId::new(20).unwrap())
+        );
+        assert_eq!(tag::CustomId::new(12), None);
+
+        let err = NBTTag::read(&mut bytes.as_slice(), &mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnknownTagType(20)));
+        let err = nbt.to_bytes(&mut BigEndian::default()).unwrap_err();
+        assert!(matches!(err.inner, WriteError::UnknownTagType(20)));
+        let err = NBTTag::read(&mut &bytes[..bytes.len() - 3], &mut encoding()).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_write_io() {
+        /// An output that fails once it holds `limit` bytes, accepting part of the last write.
+        struct Limited {
+            data: Vec<u8>,
+            limit: usize,
+        }
+
+        impl std::io::Write for Limited {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(self.limit - self.data.len());
+                if n == 0 {
+                    return Err(std::io::ErrorKind::StorageFull.into());
+                }
+                self.data.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("values", (0..4000).map(tag::Long).collect::<Vec<_>>())
+                .build(),
+        );
+        let bytes = nbt.to_bytes(&mut BigEndian::default()).unwrap();
+        let mut out = Limited {
+            data: Vec::new(),
+            limit: usize::MAX,
+        };
+        let written = nbt.write_io(&mut out, &mut BigEndian::default()).unwrap();
+        assert_eq!(written, bytes.len());
+        assert_eq!(out.data, bytes);
+
+        let mut out = Limited {
+            data: Vec::new(),
+            limit: 10000,
+        };
+        let err = nbt
+            .write_io(&mut out, &mut BigEndian::default())
+            .unwrap_err();
+        assert!(matches!(err.inner, WriteError::Io { written: 10000, .. }));
+        assert_eq!(out.data, bytes[..10000]);
+        // The header takes 17 bytes, after which every long takes 8 bytes. The first chunk is
+        // completed by the long at index 1021, and the second chunk by the one at index 2045.
+        assert_eq!(
+            err.path.0,
+            [
+                PathPart::MapKey("values".to_string()),
+                PathPart::Element(2045)
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Conversion between strings and Java's modified UTF-8.
+//!
+//! Modified UTF-8 differs from standard UTF-8 in two ways: the null character is encoded using two
+//! bytes (`0xC0 0x80`), and characters outside the basic multilingual plane are encoded as a
+//! surrogate pair, where each surrogate is encoded separately using three bytes.
+use std::borrow::Cow;
+
+/// Encodes a string using modified UTF-8.
+///
+/// Borrows the string if its UTF-8 representation is equal to its modified UTF-8 representation.
+pub(crate) fn encode(s: &str) -> Cow<'_, [u8]> {
+    if !s.bytes().any(|b| b == 0 || b >= 0xf0) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+
+    let mut buf = Vec::with_capacity(s.len() + s.len() / 2);
+    for unit in s.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => buf.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                buf.extend([0xc0 | (unit >> 6) as u8, 0x80 | (unit & 0x3f) as u8])
+            }
+            _ => buf.extend([
+                0xe0 | (unit >> 12) as u8,
+                0x80 | ((unit >> 6) & 0x3f) as u8,
+                0x80 | (unit & 0x3f) as u8,
+            ]),
+        }
+    }
+    Cow::Owned(buf)
+}
+
+/// Decodes a modified UTF-8 byte sequence into a string.
+///
+/// Returns the offset of the first invalid byte sequence if the bytes are not valid modified UTF-8.
+/// If `lossy` is set, invalid byte sequences are replaced with U+FFFD instead: unpaired surrogates
+/// are replaced as a whole, while other invalid sequences are replaced along with any
+/// continuation bytes directly following their first byte.
+pub(crate) fn decode(bytes: Vec<u8>, lossy: bool) -> Result<String, usize> {
+    if bytes.is_ascii() {
+        // ASCII is encoded the same way in UTF-8 and modified UTF-8.
+        return Ok(String::from_utf8(bytes).unwrap());
+    }
+
+    let mut str = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = match next_unit(&bytes, &mut i) {
+            Some(high @ 0xd800..=0xdbff) => {
+                let after_high = i;
+                match next_unit(&bytes, &mut i).filter(|low| (0xdc00..=0xdfff).contains(low)) {
+                    Some(low) => {
+                        Some(0x10000 + (((high - 0xd800) as u32) << 10) + (low - 0xdc00) as u32)
+                    }
+                    None => {
+                        // The unit following the high surrogate is decoded on its own.
+                        i = after_high;
+                        None
+                    }
+                }
+            }
+            Some(0xdc00..=0xdfff) => None,
+            Some(unit) => Some(unit as u32),
+            None => {
+                i = start + 1;
+                while bytes.get(i).is_some_and(|b| b & 0xc0 == 0x80) {
+                    i += 1;
+                }
+                None
+            }
+        };
+        match c {
+            // Surrogates have been handled above, so the code point is always valid.
+            Some(c) => str.push(char::from_u32(c).unwrap()),
+            None if lossy => str.push(char::REPLACEMENT_CHARACTER),
+            None => return Err(start),
+        }
+    }
+    Ok(str)
+}
+
+/// Reads a single UTF-16 code unit starting at index `i`, advancing the index past it.
+fn next_unit(bytes: &[u8], i: &mut usize) -> Option<u16> {
+    let continuation = |offset: usize| {
+        bytes
+            .get(*i + offset)
+            .filter(|b| *b & 0xc0 == 0x80)
+            .map(|b| (b & 0x3f) as u16)
+    };
+
+    let b = *bytes.get(*i)?;
+    let (unit, len) = match b {
+        0x00..=0x7f => (b as u16, 1),
+        0xc0..=0xdf => (((b & 0x1f) as u16) << 6 | continuation(1)?, 2),
+        0xe0..=0xef => (
+            ((b & 0x0f) as u16) << 12 | continuation(1)? << 6 | continuation(2)?,
+            3,
+        ),
+        _ => return None,
+    };
+    *i += len;
+    Some(unit)
+}
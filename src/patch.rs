@@ -0,0 +1,413 @@
+//! Structural patches between two tags, for storing versions of data as deltas.
+//!
+//! [NBTTag::diff] compares two tags and returns a [Patch] containing the operations that turn the
+//! first tag into the second. Applying the patch to the first tag using [Patch::apply] then
+//! reproduces the second tag. Operations refer to tags using a [Path] of [PathPart::MapKey]s for
+//! compound entries and [PathPart::Element]s for list elements.
+//!
+//! A patch can be stored by converting it into a tag, which can be written using any encoding, and
+//! converted back using [FromNbt]. The tag is a list with a compound for every operation, holding
+//! the path in a `path` list of compounds that have either a `key` string or an `index` int. The
+//! value of an addition is stored under `add` and that of a replacement under `replace`, while a
+//! removal has neither.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::{tag, NBTTag};
+//! let v1 = NBTTag::Compound(tag::Compound::builder().with_int("level", 1).build());
+//! let v2 = NBTTag::Compound(
+//!     tag::Compound::builder()
+//!         .with_int("level", 2)
+//!         .with_string("name", "Zuri")
+//!         .build(),
+//! );
+//! let patch = v1.diff(&v2);
+//! assert_eq!(patch.len(), 2);
+//!
+//! let mut patched = v1.clone();
+//! patch.apply(&mut patched).unwrap();
+//! assert_eq!(patched, v2);
+//! ```
+use std::collections::BTreeSet;
+
+use crate::convert::{self, FromNbt};
+use crate::err::{ErrorPath, NbtConvertError, PatchError, Path, PathPart};
+use crate::{tag, NBTTag, NBTTagType};
+
+/// A single change made by a [Patch].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Adds an entry to a compound, or inserts an element into a list at the index of the last
+    /// part of the path, moving the elements after it.
+    Add {
+        /// The path of the new tag.
+        path: Path,
+        /// The tag to add.
+        value: NBTTag,
+    },
+    /// Removes an entry from a compound or an element from a list, moving the elements after it.
+    Remove {
+        /// The path of the tag to remove.
+        path: Path,
+    },
+    /// Replaces an existing tag. An empty path replaces the tag the patch is applied to.
+    Replace {
+        /// The path of the tag to replace.
+        path: Path,
+        /// The tag to replace it with.
+        value: NBTTag,
+    },
+}
+
+impl Operation {
+    /// Returns the path of the tag that the operation changes.
+    pub fn path(&self) -> &Path {
+        match self {
+            Operation::Add { path, .. }
+            | Operation::Remove { path }
+            | Operation::Replace { path, .. } => path,
+        }
+    }
+}
+
+/// A list of operations that turns one tag into another, as returned by [NBTTag::diff].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch(pub Vec<Operation>);
+
+impl Patch {
+    /// Returns the amount of operations in the patch.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the patch does not contain any operations, meaning that it was produced
+    /// from two equal tags.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Applies the operations of the patch to a tag in order.
+    ///
+    /// Returns an error containing the path of the operation that could not be applied. The
+    /// operations before it remain applied, so the tag should be discarded if an error occurs.
+    /// No type checks are done on the elements that are added to lists: a patch produced by
+    /// [NBTTag::diff] only adds elements of the type of the list.
+    pub fn apply(&self, tag: &mut NBTTag) -> Result<(), ErrorPath<PatchError>> {
+        for op in &self.0 {
+            apply_operation(tag, op)
+                .map_err(|err| ErrorPath::new_with_path(err, op.path().clone()))?;
+        }
+        Ok(())
+    }
+}
+
+impl NBTTag {
+    /// Returns a [Patch] that turns this tag into the target tag when applied to it.
+    ///
+    /// Compounds are compared entry by entry, and lists element by element as long as both lists
+    /// have the same element type. Elements that are only in one of the lists are added to or
+    /// removed from its end. Any other tags that differ are replaced entirely. The order of the
+    /// operations only depends on the tags, with the entries of compounds compared in key order.
+    pub fn diff(&self, target: &NBTTag) -> Patch {
+        let mut ops = Vec::new();
+        let mut stack = vec![(Path::default(), self, target)];
+        while let Some((path, base, target)) = stack.pop() {
+            let child = |part| {
+                let mut path = path.clone();
+                path.0.push_back(part);
+                path
+            };
+            // Children are pushed in reverse, so that they are compared in order.
+            match (base, target) {
+                (NBTTag::Compound(base), NBTTag::Compound(target)) => {
+                    let keys: BTreeSet<_> = base.keys().chain(target.keys()).collect();
+                    for key in keys.into_iter().rev() {
+                        let path = child(PathPart::MapKey(key.clone()));
+                        match (base.get(key), target.get(key)) {
+                            (Some(base), Some(target)) => stack.push((path, base, target)),
+                            (Some(_), None) => ops.push(Operation::Remove { path }),
+                            (None, Some(value)) => ops.push(Operation::Add {
+                                path,
+                                value: value.clone(),
+                            }),
+                            (None, None) => unreachable!(),
+                        }
+                    }
+                }
+                // Removing all elements of a list would keep its declared element type, so a list
+                // that becomes empty is replaced instead.
+                (NBTTag::List(base_list), NBTTag::List(target_list))
+                    if base_list.element_type() == target_list.element_type()
+                        && (!target_list.is_empty() || base_list == target_list) =>
+                {
+                    let common = base_list.len().min(target_list.len());
+                    // Removing from the end first keeps the indices of the other elements valid.
+                    for i in (common..base_list.len()).rev() {
+                        ops.push(Operation::Remove {
+                            path: child(PathPart::Element(i)),
+                        });
+                    }
+                    for (i, value) in target_list.iter().enumerate().skip(common) {
+                        ops.push(Operation::Add {
+                            path: child(PathPart::Element(i)),
+                            value: value.clone(),
+                        });
+                    }
+                    for i in (0..common).rev() {
+                        stack.push((child(PathPart::Element(i)), &base_list[i], &target_list[i]));
+                    }
+                }
+                (base, target) if base != target => ops.push(Operation::Replace {
+                    path,
+                    value: target.clone(),
+                }),
+                _ => {}
+            }
+        }
+        Patch(ops)
+    }
+}
+
+fn apply_operation(tag: &mut NBTTag, op: &Operation) -> Result<(), PatchError> {
+    let path = &op.path().0;
+    if let Operation::Replace { value, .. } = op {
+        *get_mut(tag, path.iter())? = value.clone();
+        return Ok(());
+    }
+    let Some(last) = path.back() else {
+        return Err(PatchError::RootPath);
+    };
+    let parent = get_mut(tag, path.iter().take(path.len() - 1))?;
+    match (parent, last, op) {
+        (NBTTag::Compound(c), PathPart::MapKey(key), Operation::Add { value, .. }) => {
+            if c.contains_key(key) {
+                return Err(PatchError::Occupied);
+            }
+            c.insert(key.clone(), value.clone());
+        }
+        (NBTTag::Compound(c), PathPart::MapKey(key), _) => {
+            c.remove(key).ok_or(PatchError::MissingTag)?;
+        }
+        (NBTTag::List(l), PathPart::Element(i), Operation::Add { value, .. }) => {
+            if *i > l.len() {
+                return Err(PatchError::MissingTag);
+            }
+            l.insert(*i, value.clone());
+        }
+        (NBTTag::List(l), PathPart::Element(i), _) => {
+            if *i >= l.len() {
+                return Err(PatchError::MissingTag);
+            }
+            l.remove(*i);
+        }
+        (parent, part, _) => return Err(unexpected_container(parent, part)),
+    }
+    Ok(())
+}
+
+/// Returns the tag at the path relative to the provided tag.
+fn get_mut<'a, 'b>(
+    mut tag: &'a mut NBTTag,
+    path: impl Iterator<Item = &'b PathPart>,
+) -> Result<&'a mut NBTTag, PatchError> {
+    for part in path {
+        tag = match (tag, part) {
+            (NBTTag::Compound(c), PathPart::MapKey(key)) => c.get_mut(key),
+            (NBTTag::List(l), PathPart::Element(i)) => l.get_mut(*i),
+            (tag, part) => return Err(unexpected_container(tag, part)),
+        }
+        .ok_or(PatchError::MissingTag)?;
+    }
+    Ok(tag)
+}
+
+/// Returns the error for a path part that cannot select a tag in the provided tag.
+fn unexpected_container(tag: &NBTTag, part: &PathPart) -> PatchError {
+    match part {
+        PathPart::MapKey(_) => PatchError::UnexpectedTag(NBTTagType::Compound, tag.tag_type()),
+        PathPart::Element(_) => PatchError::UnexpectedTag(NBTTagType::List, tag.tag_type()),
+        // Operations only ever select compound entries and list elements.
+        _ => PatchError::MissingTag,
+    }
+}
+
+/// Converts a patch into a tag for storing it. See the [module documentation](self) for the
+/// structure of the tag. Path parts other than map keys and elements are left out, as operations
+/// with such paths cannot be applied either way.
+impl From<&Patch> for NBTTag {
+    fn from(value: &Patch) -> Self {
+        let ops: Vec<_> = value.0.iter().map(operation_to_nbt).collect();
+        NBTTag::List(ops.into())
+    }
+}
+
+fn operation_to_nbt(op: &Operation) -> tag::Compound {
+    let path: Vec<_> = op
+        .path()
+        .0
+        .iter()
+        .filter_map(|part| match part {
+            PathPart::MapKey(key) => {
+                Some(tag::Compound::builder().with_string("key", key.as_str()))
+            }
+            // Indices are stored as ints, which are reinterpreted as unsigned when reading.
+            PathPart::Element(i) => Some(tag::Compound::builder().with_int("index", *i as i32)),
+            _ => None,
+        })
+        .map(|part| part.build())
+        .collect();
+    let builder = tag::Compound::builder().with_list("path", path);
+    match op {
+        Operation::Add { value, .. } => builder.with("add", value.clone()),
+        Operation::Remove { .. } => builder,
+        Operation::Replace { value, .. } => builder.with("replace", value.clone()),
+    }
+    .build()
+}
+
+/// Reads a patch from the tag it was converted into. See the [module documentation](self) for
+/// the structure of the tag.
+impl FromNbt for Patch {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        Vec::<Operation>::from_nbt(tag).map(Patch)
+    }
+}
+
+impl FromNbt for Operation {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        let op = convert::compound(tag)?;
+        let parts: Vec<tag::Compound> = convert::field(op, "path")?;
+        let path = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                path_part(part).map_err(|err| {
+                    err.prepend(PathPart::Element(i))
+                        .prepend(PathPart::MapKey("path".to_string()))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Path)?;
+        Ok(match (op.get("add"), op.get("replace")) {
+            (Some(value), _) => Operation::Add {
+                path,
+                value: value.clone(),
+            },
+            (None, Some(value)) => Operation::Replace {
+                path,
+                value: value.clone(),
+            },
+            (None, None) => Operation::Remove { path },
+        })
+    }
+}
+
+fn path_part(part: &tag::Compound) -> Result<PathPart, NbtConvertError> {
+    if let Some(key) = convert::optional_field(part, "key")? {
+        return Ok(PathPart::MapKey(key));
+    }
+    let index: i32 = convert::field(part, "index")?;
+    Ok(PathPart::Element(index as u32 as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::FromNbt;
+    use crate::encoding::NetworkLittleEndian;
+    use crate::err::{PatchError, Path, PathPart};
+    use crate::patch::{Operation, Patch};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_diff_apply() {
+        let a: NBTTag = r#"{
+            DataVersion: 3465,
+            Player: {Pos: [0.5d, 64.0d, 3.0d], Health: 20.0f, Tags: ["a", "b", "c"]},
+            Removed: 1b,
+            Retyped: 1,
+            Lists: [[1, 2], [3]],
+            Emptied: [1, 2],
+        }"#
+        .parse()
+        .unwrap();
+        let b: NBTTag = r#"{
+            DataVersion: 3700,
+            Player: {Pos: [0.5d, 70.0d, 3.0d], Health: 20.0f, Tags: ["a"], Name: "Zuri"},
+            Retyped: "1",
+            Lists: [[1, 2, 4], [], [5]],
+            Emptied: [],
+        }"#
+        .parse()
+        .unwrap();
+
+        let patch = a.diff(&b);
+        let mut patched = a.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, b);
+        assert!(a.diff(&a).is_empty());
+        assert!(patch.0.contains(&Operation::Remove {
+            path: Path([PathPart::MapKey("Removed".to_string())].into()),
+        }));
+
+        // The patch can be stored and read back.
+        let bytes = NBTTag::from(&patch)
+            .to_bytes(&mut NetworkLittleEndian::default())
+            .unwrap();
+        let nbt = NBTTag::read_slice(&bytes, &mut NetworkLittleEndian::default()).unwrap();
+        assert_eq!(Patch::from_nbt(&nbt).unwrap(), patch);
+
+        // Replacing the root tag itself.
+        let mut patched = a.clone();
+        NBTTag::Int(tag::Int(1))
+            .diff(&b)
+            .apply(&mut patched)
+            .unwrap();
+        assert_eq!(patched, b);
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let mut nbt: NBTTag = "{a: {b: 1}, c: []}".parse().unwrap();
+        let key = |key: &str| PathPart::MapKey(key.to_string());
+        let apply = |nbt: &mut NBTTag, op| Patch(vec![op]).apply(nbt).unwrap_err();
+
+        let path = Path([key("a"), key("b")].into());
+        let err = apply(
+            &mut nbt,
+            Operation::Add {
+                path: path.clone(),
+                value: NBTTag::Int(tag::Int(2)),
+            },
+        );
+        assert_eq!(err.inner, PatchError::Occupied);
+        assert_eq!(err.path, path);
+
+        let err = apply(
+            &mut nbt,
+            Operation::Remove {
+                path: Path([key("a"), key("b"), key("d")].into()),
+            },
+        );
+        assert_eq!(
+            err.inner,
+            PatchError::UnexpectedTag(NBTTagType::Compound, NBTTagType::Int)
+        );
+
+        let err = apply(
+            &mut nbt,
+            Operation::Add {
+                path: Path([key("c"), PathPart::Element(1)].into()),
+                value: NBTTag::Int(tag::Int(2)),
+            },
+        );
+        assert_eq!(err.inner, PatchError::MissingTag);
+
+        let err = apply(
+            &mut nbt,
+            Operation::Remove {
+                path: Path::default(),
+            },
+        );
+        assert_eq!(err.inner, PatchError::RootPath);
+    }
+}
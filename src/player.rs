@@ -0,0 +1,209 @@
+//! Typed access to the player data stored by Minecraft: Java Edition.
+//!
+//! Player data is stored as a compound, either under the `Player` key of `level.dat` or on its own
+//! in a `player.dat` file, such as those in the `playerdata` directory of a world. [PlayerData]
+//! reads a handful of common fields from such a compound into typed fields. Fields that are not
+//! covered are left in the tag tree, and can still be accessed through it:
+//!  - `Pos`, the position of the player as a list of three doubles.
+//!  - `Rotation`, the yaw and pitch of the player as a list of two floats.
+//!  - `Health` and `foodLevel`.
+//!  - `XpLevel` and `playerGameType`.
+//!  - `Inventory`, a list of items that each have a `Slot`. Both the `Count` byte used before
+//!    1.20.5 and the `count` int used afterwards are supported.
+//!
+//! Requires the `player` feature.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::convert::FromNbt;
+//! # use zuri_nbt::player::PlayerData;
+//! let player: zuri_nbt::NBTTag = r#"{
+//!     Pos: [0.5d, 64.0d, -12.25d],
+//!     Health: 20.0f,
+//!     Inventory: [{Slot: 0b, id: "minecraft:stone", Count: 64b}],
+//! }"#
+//! .parse()
+//! .unwrap();
+//! let player = PlayerData::from_nbt(&player).unwrap();
+//! assert_eq!(player.pos, Some([0.5, 64.0, -12.25]));
+//! assert_eq!(player.health, Some(20.0));
+//! assert_eq!(player.inventory[0].count, 64);
+//! ```
+use crate::convert::{self, FromNbt};
+use crate::err::{ErrorPath, NbtConvertError, PathPart, TypeError};
+use crate::{tag, NBTTag};
+
+/// An item in the inventory of a player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryItem {
+    /// The slot of the item. Slots `0` to `8` are the hotbar, `9` to `35` the rest of the
+    /// inventory, `100` to `103` the armor slots and `-106` the off hand.
+    pub slot: i8,
+    /// The id of the item, such as `minecraft:stone`.
+    pub id: String,
+    /// The amount of items in the stack. Items without a count are counted as one.
+    pub count: i32,
+    /// The compound of the item itself, for reading any other data of the item.
+    pub item: tag::Compound,
+}
+
+/// The common fields of player data. See the [module documentation](self) for the supported
+/// fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerData {
+    /// The `Pos` field, as x, y and z.
+    pub pos: Option<[f64; 3]>,
+    /// The `Rotation` field, as yaw and pitch.
+    pub rotation: Option<[f32; 2]>,
+    /// The `Health` field.
+    pub health: Option<f32>,
+    /// The `foodLevel` field.
+    pub food_level: Option<i32>,
+    /// The `XpLevel` field.
+    pub xp_level: Option<i32>,
+    /// The `playerGameType` field, which is `0` for survival, `1` for creative, `2` for adventure
+    /// and `3` for spectator.
+    pub game_type: Option<i32>,
+    /// The items of the `Inventory` field, in the order they are stored. Empty if the field is
+    /// missing.
+    pub inventory: Vec<InventoryItem>,
+}
+
+/// Reads a compound of player data. Fields that are missing are left as [None].
+///
+/// Returns an error if the tag or any of the supported fields has an unexpected type, or if `Pos`
+/// or `Rotation` has too few elements, containing the path to the offending tag.
+impl FromNbt for PlayerData {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        let player = convert::compound(tag)?;
+        Ok(Self {
+            pos: fixed_list(player, "Pos")?,
+            rotation: fixed_list(player, "Rotation")?,
+            health: convert::optional_field(player, "Health")?,
+            food_level: convert::optional_field(player, "foodLevel")?,
+            xp_level: convert::optional_field(player, "XpLevel")?,
+            game_type: convert::optional_field(player, "playerGameType")?,
+            inventory: convert::optional_field(player, "Inventory")?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Reads an item in the inventory. Returns an error if the `Slot` or `id` is missing.
+impl FromNbt for InventoryItem {
+    fn from_nbt(tag: &NBTTag) -> Result<Self, NbtConvertError> {
+        let item = convert::compound(tag)?;
+        // The count was stored as a signed byte before 1.20.5, and as an int afterwards.
+        let count = match convert::optional_field::<i8>(item, "Count")? {
+            Some(count) => Some(i32::from(count)),
+            None => convert::optional_field(item, "count")?,
+        };
+        Ok(Self {
+            slot: convert::field(item, "Slot")?,
+            id: convert::field(item, "id")?,
+            count: count.unwrap_or(1),
+            item: item.clone(),
+        })
+    }
+}
+
+/// Reads a list with a fixed amount of elements if present, such as `Pos`. Returns
+/// [TypeError::MissingTag] for the first missing element if the list is too short. Any further
+/// elements are ignored.
+fn fixed_list<T: FromNbt + Copy + Default, const N: usize>(
+    compound: &tag::Compound,
+    key: &str,
+) -> Result<Option<[T; N]>, NbtConvertError> {
+    let Some(values) = convert::optional_field::<Vec<T>>(compound, key)? else {
+        return Ok(None);
+    };
+    if values.len() < N {
+        return Err(ErrorPath::new(TypeError::MissingTag)
+            .prepend(PathPart::Element(values.len()))
+            .prepend(PathPart::MapKey(key.to_string())));
+    }
+    let mut array = [T::default(); N];
+    array.copy_from_slice(&values[..N]);
+    Ok(Some(array))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::FromNbt;
+    use crate::err::TypeError;
+    use crate::player::{InventoryItem, PlayerData};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_player_data() {
+        let nbt: NBTTag = r#"{
+            Pos: [0.5d, 64.0d, -12.25d],
+            Rotation: [90.0f, -15.0f],
+            Health: 18.5f,
+            foodLevel: 20,
+            XpLevel: 7,
+            playerGameType: 1,
+            Inventory: [
+                {Slot: 0b, id: "minecraft:stone", Count: 64b},
+                {Slot: -106b, id: "minecraft:shield", count: 1, components: {}},
+                {Slot: 103b, id: "minecraft:diamond_helmet"},
+                {Slot: 1b, id: "minecraft:dirt", Count: -1b},
+            ],
+            Dimension: "minecraft:overworld",
+        }"#
+        .parse()
+        .unwrap();
+        let player = PlayerData::from_nbt(&nbt).unwrap();
+        assert_eq!(player.pos, Some([0.5, 64.0, -12.25]));
+        assert_eq!(player.rotation, Some([90.0, -15.0]));
+        assert_eq!(player.health, Some(18.5));
+        assert_eq!(player.food_level, Some(20));
+        assert_eq!(player.xp_level, Some(7));
+        assert_eq!(player.game_type, Some(1));
+
+        let items: Vec<_> = player
+            .inventory
+            .iter()
+            .map(|item| (item.slot, item.id.as_str(), item.count))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (0, "minecraft:stone", 64),
+                (-106, "minecraft:shield", 1),
+                (103, "minecraft:diamond_helmet", 1),
+                (1, "minecraft:dirt", -1),
+            ]
+        );
+        assert!(player.inventory[1].item.contains_key("components"));
+
+        let nbt = NBTTag::Compound(tag::Compound::default());
+        assert_eq!(PlayerData::from_nbt(&nbt).unwrap(), PlayerData::default());
+    }
+
+    #[test]
+    fn test_player_data_errors() {
+        let nbt: NBTTag = "{Pos:[1.0d,2.0d]}".parse().unwrap();
+        let err = PlayerData::from_nbt(&nbt).unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.to_string(), "Pos[2]");
+
+        let nbt: NBTTag = "{Health:20}".parse().unwrap();
+        let err = PlayerData::from_nbt(&nbt).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::UnexpectedTag(NBTTagType::Float, NBTTagType::Int)
+        );
+        assert_eq!(err.path.to_string(), "Health");
+
+        let nbt: NBTTag = "{Inventory:[{Slot:0b,id:\"minecraft:stone\"},{Slot:1b}]}"
+            .parse()
+            .unwrap();
+        let err = PlayerData::from_nbt(&nbt).unwrap_err();
+        assert_eq!(err.inner, TypeError::MissingTag);
+        assert_eq!(err.path.to_string(), "Inventory[1].id");
+
+        let nbt: NBTTag = "{id:\"minecraft:stone\",Slot:2}".parse().unwrap();
+        let err = InventoryItem::from_nbt(&nbt).unwrap_err();
+        assert_eq!(err.path.to_string(), "Slot");
+    }
+}
@@ -0,0 +1,441 @@
+//! Checking the shape of NBT data against a declarative [Schema].
+//!
+//! A schema describes the keys that are expected in a compound tag, along with their types. This
+//! is useful for validating data from mods or plugins before using it.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::{schema::Schema, tag, NBTTag, NBTTagType};
+//! let schema = Schema::new()
+//!     .required("Name", NBTTagType::String)
+//!     .optional("Count", NBTTagType::Byte)
+//!     .optional_compound("tag", Schema::new().optional("Damage", NBTTagType::Int));
+//!
+//! let item = NBTTag::Compound(tag::Compound::builder().with_string("Name", "stone").build());
+//! assert!(item.check_schema(&schema).is_ok());
+//! ```
+//!
+//! The shape of existing data can also be inferred with [NBTTag::template], which returns a
+//! [Template] containing the structure and tag types of a tree without its values. Comparing the
+//! templates of two files shows whether they use the same format.
+use std::collections::BTreeMap;
+
+use bytes::BufMut;
+
+use crate::encode::Writer;
+use crate::err::{ErrorPath, PathPart, SchemaError, ValidatedWriteError};
+#[cfg(feature = "omit-defaults")]
+use crate::tag;
+use crate::{NBTTag, NBTTagType};
+
+/// A description of the keys expected in a compound tag.
+///
+/// Keys that are present in the data but not in the schema are allowed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    fields: Vec<(String, Field)>,
+}
+
+/// A single key in a [Schema].
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    tag_type: NBTTagType,
+    required: bool,
+    nested: Option<Schema>,
+}
+
+impl Schema {
+    /// Creates a new schema without any keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a key that must be present with a tag of the provided type.
+    pub fn required(self, key: impl Into<String>, tag_type: NBTTagType) -> Self {
+        self.field(key, tag_type, true, None)
+    }
+
+    /// Adds a key that may be absent, but must have a tag of the provided type if present.
+    pub fn optional(self, key: impl Into<String>, tag_type: NBTTagType) -> Self {
+        self.field(key, tag_type, false, None)
+    }
+
+    /// Adds a key that must be present with a compound tag matching the nested schema.
+    pub fn required_compound(self, key: impl Into<String>, schema: Schema) -> Self {
+        self.field(key, NBTTagType::Compound, true, Some(schema))
+    }
+
+    /// Adds a key that may be absent, but must have a compound tag matching the nested schema if
+    /// present.
+    pub fn optional_compound(self, key: impl Into<String>, schema: Schema) -> Self {
+        self.field(key, NBTTagType::Compound, false, Some(schema))
+    }
+
+    fn field(
+        mut self,
+        key: impl Into<String>,
+        tag_type: NBTTagType,
+        required: bool,
+        nested: Option<Schema>,
+    ) -> Self {
+        self.fields.push((
+            key.into(),
+            Field {
+                tag_type,
+                required,
+                nested,
+            },
+        ));
+        self
+    }
+
+    /// Checks a tag against the schema, adding any errors found to `errors`.
+    fn check(&self, nbt: &NBTTag, errors: &mut Vec<ErrorPath<SchemaError>>) {
+        let NBTTag::Compound(compound) = nbt else {
+            errors.push(ErrorPath::new(SchemaError::UnexpectedTag(
+                NBTTagType::Compound,
+                nbt.tag_type(),
+            )));
+            return;
+        };
+
+        for (key, field) in &self.fields {
+            let Some(value) = compound.get(key) else {
+                if field.required {
+                    errors.push(
+                        ErrorPath::new(SchemaError::MissingKey)
+                            .prepend(PathPart::MapKey(key.clone())),
+                    );
+                }
+                continue;
+            };
+            if value.tag_type() != field.tag_type {
+                errors.push(
+                    ErrorPath::new(SchemaError::UnexpectedTag(field.tag_type, value.tag_type()))
+                        .prepend(PathPart::MapKey(key.clone())),
+                );
+                continue;
+            }
+            if let Some(nested) = &field.nested {
+                let start = errors.len();
+                nested.check(value, errors);
+                for err in &mut errors[start..] {
+                    err.path.0.push_front(PathPart::MapKey(key.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// The structure and tag types of a tree without its values, as returned by [NBTTag::template].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Template {
+    /// A tag that is not a compound or list, such as a string or an array.
+    Tag(NBTTagType),
+    /// A compound tag with the templates of its entries.
+    Compound(BTreeMap<String, Template>),
+    /// A list tag with the template of its elements, or [None] if the list is empty. The templates
+    /// of all elements are merged into one: a list of compounds results in a compound template
+    /// containing the keys of all compounds in the list.
+    List(Option<Box<Template>>),
+}
+
+impl Template {
+    /// Returns the type of the tag the template was created from.
+    pub fn tag_type(&self) -> NBTTagType {
+        match self {
+            Template::Tag(t) => *t,
+            Template::Compound(_) => NBTTagType::Compound,
+            Template::List(_) => NBTTagType::List,
+        }
+    }
+
+    /// Merges another template into this one. Entries of compounds that are only present in
+    /// `other` are added, while entries present in both are merged. If the types of the templates
+    /// differ, this template is kept.
+    fn merge(&mut self, other: Template) {
+        match (self, other) {
+            (Template::Compound(a), Template::Compound(b)) => {
+                for (key, template) in b {
+                    match a.get_mut(&key) {
+                        Some(existing) => existing.merge(template),
+                        None => {
+                            a.insert(key, template);
+                        }
+                    }
+                }
+            }
+            (Template::List(a), Template::List(Some(b))) => match a {
+                Some(a) => a.merge(*b),
+                None => *a = Some(b),
+            },
+            _ => {}
+        }
+    }
+
+    /// Returns the default value of the tag the template was created from, which may have been
+    /// left out when writing with the `omit_defaults` option. Returns [None] for compounds and
+    /// custom tags, which are never left out.
+    #[cfg(feature = "omit-defaults")]
+    fn default_tag(&self) -> Option<NBTTag> {
+        Some(match self {
+            Template::Tag(NBTTagType::Byte) => NBTTag::Byte(0.into()),
+            Template::Tag(NBTTagType::Short) => NBTTag::Short(0.into()),
+            Template::Tag(NBTTagType::Int) => NBTTag::Int(0.into()),
+            Template::Tag(NBTTagType::Long) => NBTTag::Long(0.into()),
+            Template::Tag(NBTTagType::Float) => NBTTag::Float(0.0.into()),
+            Template::Tag(NBTTagType::Double) => NBTTag::Double(0.0.into()),
+            Template::Tag(NBTTagType::String) => NBTTag::String("".into()),
+            Template::Tag(NBTTagType::ByteArray) => NBTTag::ByteArray(vec![].into()),
+            Template::Tag(NBTTagType::IntArray) => NBTTag::IntArray(vec![].into()),
+            Template::Tag(NBTTagType::LongArray) => NBTTag::LongArray(vec![].into()),
+            Template::List(element) => NBTTag::List(
+                element
+                    .as_ref()
+                    .and_then(|e| tag::List::with_type(e.tag_type().id(), Vec::new()).ok())
+                    .unwrap_or_default(),
+            ),
+            Template::Tag(NBTTagType::Compound | NBTTagType::List | NBTTagType::Custom(_))
+            | Template::Compound(_) => return None,
+        })
+    }
+}
+
+impl NBTTag {
+    /// Returns the [Template] of the tag, which keeps the structure and tag types of the tree but
+    /// strips all values. Two trees with the same structure but different values have equal
+    /// templates.
+    pub fn template(&self) -> Template {
+        match self {
+            NBTTag::Compound(c) => Template::Compound(
+                c.iter()
+                    .map(|(key, value)| (key.clone(), value.template()))
+                    .collect(),
+            ),
+            NBTTag::List(l) => Template::List(
+                l.iter()
+                    .map(NBTTag::template)
+                    .reduce(|mut a, b| {
+                        a.merge(b);
+                        a
+                    })
+                    .map(Box::new),
+            ),
+            _ => Template::Tag(self.tag_type()),
+        }
+    }
+
+    /// Restores the compound entries that were left out when writing with the `omit_defaults`
+    /// option of the encodings, using the [Template] of the complete data.
+    ///
+    /// Every key of a compound in the template that is missing in the tag is inserted with the
+    /// default value of its type. Empty lists get the element type of the list in the template,
+    /// if it has one. Entries that are present are filled recursively, including the elements of
+    /// lists. Requires the `omit-defaults` feature.
+    #[cfg(feature = "omit-defaults")]
+    pub fn fill_defaults(&mut self, template: &Template) {
+        match (self, template) {
+            (NBTTag::Compound(c), Template::Compound(entries)) => {
+                for (key, template) in entries {
+                    match c.get_mut(key) {
+                        Some(v) => v.fill_defaults(template),
+                        None => {
+                            if let Some(v) = template.default_tag() {
+                                c.insert(key.clone(), v);
+                            }
+                        }
+                    }
+                }
+            }
+            (NBTTag::List(l), Template::List(Some(element))) => {
+                for v in l.iter_mut() {
+                    v.fill_defaults(element);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks whether the tag matches the provided [Schema].
+    ///
+    /// All mismatches are collected rather than stopping at the first one. Each error contains the
+    /// path to the offending key.
+    pub fn check_schema(&self, schema: &Schema) -> Result<(), Vec<ErrorPath<SchemaError>>> {
+        let mut errors = Vec::new();
+        schema.check(self, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks whether the tag matches the provided [Schema], and only writes it into the buffer
+    /// using the specified [Writer] encoding if it does, like [Self::write].
+    ///
+    /// Returns all mismatches if the tag does not match the schema, in which case nothing is
+    /// written. This guarantees that data that does not match the schema is never written.
+    pub fn write_validated(
+        &self,
+        buf: &mut impl BufMut,
+        w: &mut impl Writer,
+        schema: &Schema,
+    ) -> Result<(), ValidatedWriteError> {
+        self.check_schema(schema)
+            .map_err(ValidatedWriteError::Schema)?;
+        Ok(self.write(buf, w)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::BigEndian;
+    use crate::err::{PathPart, SchemaError, ValidatedWriteError};
+    use crate::schema::{Schema, Template};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    fn schema() -> Schema {
+        Schema::new()
+            .required("Name", NBTTagType::String)
+            .optional("Count", NBTTagType::Byte)
+            .required_compound("tag", Schema::new().required("Damage", NBTTagType::Int))
+    }
+
+    #[test]
+    fn test_missing_key() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("Name", "stone")
+                .with_compound("tag", tag::Compound::builder())
+                .build(),
+        );
+        let errors = nbt.check_schema(&schema()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].inner, SchemaError::MissingKey);
+        assert_eq!(
+            errors[0].path.0,
+            [
+                PathPart::MapKey("tag".to_string()),
+                PathPart::MapKey("Damage".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int("Name", 1)
+                .with_int("Count", 64)
+                .with_compound("tag", tag::Compound::builder().with_int("Damage", 3))
+                .build(),
+        );
+        let errors = nbt.check_schema(&schema()).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].inner,
+            SchemaError::UnexpectedTag(NBTTagType::String, NBTTagType::Int)
+        );
+        assert_eq!(errors[0].path.0, [PathPart::MapKey("Name".to_string())]);
+        assert_eq!(
+            errors[1].inner,
+            SchemaError::UnexpectedTag(NBTTagType::Byte, NBTTagType::Int)
+        );
+        assert_eq!(errors[1].path.0, [PathPart::MapKey("Count".to_string())]);
+
+        assert!(NBTTag::Int(1.into()).check_schema(&schema()).is_err());
+    }
+
+    #[test]
+    fn test_write_validated() {
+        let valid = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("Name", "stone")
+                .with_compound("tag", tag::Compound::builder().with_int("Damage", 3))
+                .build(),
+        );
+        let mut buf = Vec::new();
+        valid
+            .write_validated(&mut buf, &mut BigEndian::default(), &schema())
+            .unwrap();
+        assert_eq!(buf, valid.to_bytes(&mut BigEndian::default()).unwrap());
+
+        let invalid = NBTTag::Compound(tag::Compound::builder().with_int("Name", 1).build());
+        let mut buf = Vec::new();
+        let err = invalid
+            .write_validated(&mut buf, &mut BigEndian::default(), &schema())
+            .unwrap_err();
+        let ValidatedWriteError::Schema(errors) = err else {
+            panic!("expected schema errors, found {err:?}");
+        };
+        assert_eq!(errors.len(), 2);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_template() {
+        let a: NBTTag = "{Name:\"stone\",Count:1b,Items:[{id:1s},{id:2s,tag:{}}],Pos:[I;1,2]}"
+            .parse()
+            .unwrap();
+        let b: NBTTag = "{Name:\"dirt\",Count:64b,Items:[{id:3s,tag:{}}],Pos:[I;]}"
+            .parse()
+            .unwrap();
+        assert_eq!(a.template(), b.template());
+
+        let Template::Compound(template) = a.template() else {
+            panic!("expected a compound template");
+        };
+        assert_eq!(template["Pos"], Template::Tag(NBTTagType::IntArray));
+        let Template::List(Some(items)) = &template["Items"] else {
+            panic!("expected a list template");
+        };
+        assert_eq!(items.tag_type(), NBTTagType::Compound);
+
+        let c: NBTTag = "{Name:\"dirt\",Count:64s,Items:[],Pos:[I;]}"
+            .parse()
+            .unwrap();
+        assert_ne!(a.template(), c.template());
+    }
+
+    #[test]
+    #[cfg(feature = "omit-defaults")]
+    fn test_fill_defaults() {
+        use crate::encoding::BigEndian;
+
+        let nbt: NBTTag = concat!(
+            "{Name:\"\",Count:0b,Health:2.5f,Tags:[],Pos:[I;],",
+            "Items:[{id:\"stone\",Damage:0s},{id:\"\",Damage:3s}],Data:{Flags:0L}}"
+        )
+        .parse()
+        .unwrap();
+        let mut w = BigEndian::default().omit_defaults(true);
+        let bytes = nbt.to_bytes(&mut w).unwrap();
+        assert!(bytes.len() < nbt.to_bytes(&mut BigEndian::default()).unwrap().len());
+
+        let mut read = NBTTag::read_slice(&bytes, &mut BigEndian::default()).unwrap();
+        assert_eq!(
+            read,
+            "{Health:2.5f,Items:[{id:\"stone\"},{Damage:3s}],Data:{}}"
+                .parse::<NBTTag>()
+                .unwrap()
+        );
+
+        // The template can be taken from any data with the same structure.
+        let reference: NBTTag = concat!(
+            "{Name:\"Zuri\",Count:1b,Health:1f,Tags:[\"a\"],Pos:[I;1],",
+            "Items:[{id:\"dirt\",Damage:1s}],Data:{Flags:1L}}"
+        )
+        .parse()
+        .unwrap();
+        read.fill_defaults(&reference.template());
+        let mut expected = nbt.clone();
+        // The element type of the empty list is taken from the template.
+        if let NBTTag::Compound(c) = &mut expected {
+            c.insert(
+                "Tags".to_string(),
+                NBTTag::List(tag::List::with_type(8, vec![]).unwrap()),
+            );
+        }
+        assert_eq!(read, expected);
+    }
+}
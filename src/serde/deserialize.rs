@@ -35,6 +35,9 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
             NBTTag::ByteArray(_) => self.deserialize_seq(visitor),
             NBTTag::IntArray(_) => self.deserialize_seq(visitor),
             NBTTag::LongArray(_) => self.deserialize_seq(visitor),
+            NBTTag::Raw(_) | NBTTag::Custom(_) => {
+                Err(ErrorPath::new(DeserializeError::UnexpectedTag))
+            }
         }
     }
 
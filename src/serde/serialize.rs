@@ -108,9 +108,9 @@ impl ser::Serializer for Serializer {
         Ok(wrap_enum("None", NBTTag::Compound(Default::default())))
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Ok(wrap_enum("Some", value.serialize(Serializer)?))
     }
@@ -132,18 +132,18 @@ impl ser::Serializer for Serializer {
         Ok(wrap_enum(variant, NBTTag::Compound(Default::default())))
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(Serializer)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -151,7 +151,7 @@ impl ser::Serializer for Serializer {
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Ok(wrap_enum(variant, value.serialize(Serializer)?))
     }
@@ -289,13 +289,9 @@ impl ser::SerializeStruct for CompoundSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.v.insert(
             key.to_string(),
@@ -315,28 +311,24 @@ impl ser::SerializeMap for CompoundSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         unreachable!()
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         unreachable!()
     }
 
-    fn serialize_entry<K: ?Sized, V: ?Sized>(
-        &mut self,
-        key: &K,
-        value: &V,
-    ) -> Result<(), Self::Error>
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Self::Error>
     where
-        K: Serialize,
-        V: Serialize,
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
     {
         let key_str = if let NBTTag::String(str) = key.serialize(Serializer)? {
             str
@@ -366,9 +358,9 @@ impl ser::SerializeTuple for CompoundSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.v.insert(
             format!("{}", self.index),
@@ -389,9 +381,9 @@ impl ser::SerializeTupleStruct for CompoundSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         <Self as ser::SerializeTuple>::serialize_element(self, value)
     }
@@ -421,9 +413,9 @@ impl ser::SerializeTupleVariant for CompoundVariantSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         <CompoundSerializer as ser::SerializeTuple>::serialize_element(&mut self.inner, value)
     }
@@ -440,13 +432,9 @@ impl ser::SerializeStructVariant for CompoundVariantSerializer {
     type Ok = <Serializer as ser::Serializer>::Ok;
     type Error = <Serializer as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         <CompoundSerializer as ser::SerializeStruct>::serialize_field(&mut self.inner, key, value)
     }
@@ -0,0 +1,816 @@
+//! See [SharedNBTTag].
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::mem;
+use std::sync::Arc;
+
+use bytes::Buf;
+
+use crate::decode::Reader;
+use crate::err::{ErrorPath, PathPart, ReadError};
+use crate::{decode, tag, NBTTag, NBTTagType};
+
+/// An alternative representation of [NBTTag] in which strings, arrays, raw tags and container
+/// tags are reference counted.
+///
+/// Cloning a shared tag is cheap, as the clone shares all of its data with the original. Editing a
+/// clone through one of the `*_mut` methods only copies the edited tag itself (copy-on-write):
+/// unchanged children stay shared between the clones. This makes it well suited for keeping many
+/// versions of a large tree around, for example for undo and redo in an editor.
+///
+/// Shared tags can be converted from and to regular tags using [From]. Compound keys are interned
+/// during the conversion, so that keys that are repeated throughout the tree are only stored once.
+/// An [Interner] can be used to share keys between multiple trees as well, and to share identical
+/// subtrees using [Interner::deduplicating].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedNBTTag {
+    /// An 8-bit unsigned integer.
+    Byte(tag::Byte),
+    /// A 16-bit signed integer.
+    Short(tag::Short),
+    /// A 32-bit signed integer.
+    Int(tag::Int),
+    /// A 64-bit signed integer.
+    Long(tag::Long),
+    /// A 32-bit floating point number.
+    Float(tag::Float),
+    /// A 64-bit floating point number.
+    Double(tag::Double),
+    /// A string of characters.
+    String(Arc<String>),
+    /// A map containing zero or more key-value pairs.
+    Compound(Arc<HashMap<Arc<str>, SharedNBTTag>>),
+    /// A variable-length list of tags of the same type, along with the declared element type id
+    /// used when the list is empty. See [tag::List::element_type].
+    List(Arc<Vec<SharedNBTTag>>, u8),
+    /// A variable-length array containing 8-bit unsigned integers.
+    ByteArray(Arc<Vec<u8>>),
+    /// A variable-length array containing 32-bit signed integers.
+    IntArray(Arc<Vec<i32>>),
+    /// A variable-length array containing 64-bit signed integers.
+    LongArray(Arc<Vec<i64>>),
+    /// A pre-encoded tag payload that is written as-is. See [tag::Raw].
+    Raw(Arc<tag::Raw>),
+    /// A tag of a type that is not part of the NBT format. See [tag::Custom].
+    Custom(Arc<tag::Custom>),
+}
+
+impl SharedNBTTag {
+    /// Returns the [NBTTagType] associated with the tag variant contained in the enum.
+    pub fn tag_type(&self) -> NBTTagType {
+        match self {
+            SharedNBTTag::Byte(_) => NBTTagType::Byte,
+            SharedNBTTag::Short(_) => NBTTagType::Short,
+            SharedNBTTag::Int(_) => NBTTagType::Int,
+            SharedNBTTag::Long(_) => NBTTagType::Long,
+            SharedNBTTag::Float(_) => NBTTagType::Float,
+            SharedNBTTag::Double(_) => NBTTagType::Double,
+            SharedNBTTag::String(_) => NBTTagType::String,
+            SharedNBTTag::Compound(_) => NBTTagType::Compound,
+            SharedNBTTag::List(_, _) => NBTTagType::List,
+            SharedNBTTag::ByteArray(_) => NBTTagType::ByteArray,
+            SharedNBTTag::IntArray(_) => NBTTagType::IntArray,
+            SharedNBTTag::LongArray(_) => NBTTagType::LongArray,
+            SharedNBTTag::Raw(v) => v.tag_type,
+            SharedNBTTag::Custom(v) => NBTTagType::Custom(v.id),
+        }
+    }
+
+    /// Returns a mutable reference to the entries of a compound tag, copying the entries first if
+    /// they are shared with another tag. The entries themselves remain shared.
+    ///
+    /// Returns [None] if the tag is not a compound tag.
+    pub fn compound_mut(&mut self) -> Option<&mut HashMap<Arc<str>, SharedNBTTag>> {
+        match self {
+            SharedNBTTag::Compound(v) => Some(Arc::make_mut(v)),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the elements of a list tag, copying the elements first if
+    /// they are shared with another tag. The elements themselves remain shared.
+    ///
+    /// Returns [None] if the tag is not a list tag.
+    pub fn list_mut(&mut self) -> Option<&mut Vec<SharedNBTTag>> {
+        match self {
+            SharedNBTTag::List(v, _) => Some(Arc::make_mut(v)),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value of a string tag, copying the string first if it is
+    /// shared with another tag.
+    ///
+    /// Returns [None] if the tag is not a string tag.
+    pub fn string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            SharedNBTTag::String(v) => Some(Arc::make_mut(v)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if both tags share the same underlying data.
+    ///
+    /// Always returns false for numeric tags, as these are not reference counted.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SharedNBTTag::String(a), SharedNBTTag::String(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::Compound(a), SharedNBTTag::Compound(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::List(a, _), SharedNBTTag::List(b, _)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::ByteArray(a), SharedNBTTag::ByteArray(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::IntArray(a), SharedNBTTag::IntArray(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::LongArray(a), SharedNBTTag::LongArray(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::Raw(a), SharedNBTTag::Raw(b)) => Arc::ptr_eq(a, b),
+            (SharedNBTTag::Custom(a), SharedNBTTag::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Converts a regular tag into a shared tag, interning all compound keys using the provided
+    /// [Interner].
+    pub fn from_interned(value: NBTTag, interner: &mut Interner) -> Self {
+        match value {
+            NBTTag::Byte(v) => SharedNBTTag::Byte(v),
+            NBTTag::Short(v) => SharedNBTTag::Short(v),
+            NBTTag::Int(v) => SharedNBTTag::Int(v),
+            NBTTag::Long(v) => SharedNBTTag::Long(v),
+            NBTTag::Float(v) => SharedNBTTag::Float(v),
+            NBTTag::Double(v) => SharedNBTTag::Double(v),
+            NBTTag::String(v) => SharedNBTTag::String(Arc::new(v.0)),
+            NBTTag::Compound(mut v) => {
+                let compound = SharedNBTTag::Compound(Arc::new(
+                    mem::take(&mut v.0)
+                        .into_iter()
+                        .map(|(k, v)| (interner.intern(&k), Self::from_interned(v, interner)))
+                        .collect(),
+                ));
+                interner.subtree(compound)
+            }
+            NBTTag::List(mut v) => {
+                let element_type = v.element_type();
+                let list = SharedNBTTag::List(
+                    Arc::new(
+                        mem::take(&mut v.0)
+                            .into_iter()
+                            .map(|v| Self::from_interned(v, interner))
+                            .collect(),
+                    ),
+                    element_type,
+                );
+                interner.subtree(list)
+            }
+            NBTTag::ByteArray(v) => SharedNBTTag::ByteArray(Arc::new(v.0)),
+            NBTTag::IntArray(v) => SharedNBTTag::IntArray(Arc::new(v.0)),
+            NBTTag::LongArray(v) => SharedNBTTag::LongArray(Arc::new(v.0)),
+            NBTTag::Raw(v) => SharedNBTTag::Raw(Arc::new(v)),
+            NBTTag::Custom(v) => SharedNBTTag::Custom(Arc::new(v)),
+        }
+    }
+
+    /// Attempts to read the data from a buffer into a shared tag using the specified [Reader]
+    /// encoding, interning all compound keys using the provided [Interner].
+    ///
+    /// Reusing the same interner for many reads, such as for all chunks in a region file, ensures
+    /// that common keys like `x`, `y`, `z` and `Name` are only kept in memory once. The shared tag
+    /// is built while reading, so keys that are already interned are not allocated again.
+    pub fn read_interned(
+        buf: &mut impl Buf,
+        r: &mut impl Reader,
+        interner: &mut Interner,
+    ) -> decode::Res<Self> {
+        let mut tag_id = NBTTag::read_root_id(buf, r)?;
+        // The name of the root tag is not kept, so it is read into the buffer used for keys.
+        let mut key = String::new();
+        r.string_into(buf, &mut key)?;
+
+        let omitted_root_end = r.reads_omitted_root_end();
+        let mut stack = Vec::new();
+        loop {
+            // Read the tag itself. Container tags are pushed onto the stack to be filled later.
+            let mut value = match tag_id {
+                9 | 10 if stack.len() >= r.max_depth() => {
+                    let err = ErrorPath::new(ReadError::TooDeep(r.max_depth()));
+                    return Err(ReadFrame::prepend_path(err, &stack));
+                }
+                10 => {
+                    stack.push(ReadFrame::Compound {
+                        map: HashMap::new(),
+                        key: None,
+                    });
+                    None
+                }
+                9 => match NBTTag::read_list_header(buf, r) {
+                    Ok((content_type, len)) => {
+                        stack.push(ReadFrame::List {
+                            vec: Vec::with_capacity(len.min(buf.remaining())),
+                            len,
+                            content_type,
+                        });
+                        None
+                    }
+                    Err(err) => return Err(ReadFrame::prepend_path(err, &stack)),
+                },
+                _ => match NBTTag::read_value(buf, tag_id, r) {
+                    Ok(crate::ReadFrame::Done(value)) => Some(Self::from_interned(value, interner)),
+                    Ok(_) => unreachable!(),
+                    Err(err) => return Err(ReadFrame::prepend_path(err, &stack)),
+                },
+            };
+
+            // Find the next tag to read, completing any containers that have been filled.
+            loop {
+                let root = stack.len() == 1;
+                let Some(frame) = stack.last_mut() else {
+                    // The stack is only empty once the outermost tag has been completed.
+                    return Ok(value.unwrap());
+                };
+                let next = frame.next(
+                    buf,
+                    value.take(),
+                    r,
+                    interner,
+                    &mut key,
+                    root && omitted_root_end,
+                );
+                match next {
+                    Ok(Some(done)) => {
+                        stack.pop();
+                        value = Some(interner.subtree(done));
+                    }
+                    Ok(None) => {
+                        tag_id = frame.content_type();
+                        break;
+                    }
+                    Err(err) => {
+                        return Err(ReadFrame::prepend_path(err, &stack[..stack.len() - 1]))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A partially read container tag used by [SharedNBTTag::read_interned].
+enum ReadFrame {
+    /// A compound tag. The key is that of the entry currently being read, if any.
+    Compound {
+        map: HashMap<Arc<str>, SharedNBTTag>,
+        key: Option<(Arc<str>, u8)>,
+    },
+    /// A list tag of which `len` elements of type `content_type` are to be read.
+    List {
+        vec: Vec<SharedNBTTag>,
+        len: usize,
+        content_type: u8,
+    },
+}
+
+impl ReadFrame {
+    /// Adds a tag that has been read to the container, and prepares to read the next tag.
+    ///
+    /// Returns the container tag if it has been read completely, or [None] if another tag of type
+    /// [Self::content_type] should be read and added to the container. Keys are read into
+    /// `key_buf` before being interned. If `end_optional` is true, a compound is also complete
+    /// once the buffer has been read completely.
+    fn next(
+        &mut self,
+        buf: &mut impl Buf,
+        value: Option<SharedNBTTag>,
+        r: &mut impl Reader,
+        interner: &mut Interner,
+        key_buf: &mut String,
+        end_optional: bool,
+    ) -> decode::Res<Option<SharedNBTTag>> {
+        match self {
+            ReadFrame::Compound { map, key } => {
+                if let (Some((key, _)), Some(value)) = (key.take(), value) {
+                    map.insert(key, value);
+                }
+                if !buf.has_remaining() {
+                    if end_optional {
+                        return Ok(Some(SharedNBTTag::Compound(Arc::new(mem::take(map)))));
+                    }
+                    // Either the next entry or the END tag has to follow.
+                    return Err(ErrorPath::new(ReadError::MissingEndTag));
+                }
+                let content_type = r.u8(buf)?;
+                if content_type == 0 {
+                    return Ok(Some(SharedNBTTag::Compound(Arc::new(mem::take(map)))));
+                }
+                r.check_cancelled()?;
+                if let Some(max) = r.max_compound_entries() {
+                    if map.len() >= max {
+                        return Err(ErrorPath::new(ReadError::SeqLengthViolation(
+                            max,
+                            map.len() + 1,
+                        )));
+                    }
+                }
+                r.string_into(buf, key_buf)?;
+                *key = Some((interner.intern(key_buf), content_type));
+                Ok(None)
+            }
+            ReadFrame::List {
+                vec,
+                len,
+                content_type,
+            } => {
+                if let Some(value) = value {
+                    vec.push(value);
+                }
+                if vec.len() == *len {
+                    return Ok(Some(SharedNBTTag::List(
+                        Arc::new(mem::take(vec)),
+                        *content_type,
+                    )));
+                }
+                r.check_cancelled()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// The tag id of the tag that is currently being read into the container.
+    fn content_type(&self) -> u8 {
+        match self {
+            ReadFrame::Compound { key, .. } => key.as_ref().map_or(0, |(_, id)| *id),
+            ReadFrame::List { content_type, .. } => *content_type,
+        }
+    }
+
+    /// Prepends the location of the tags currently being read in each of the frames to the path
+    /// of an error.
+    fn prepend_path(mut err: ErrorPath<ReadError>, stack: &[ReadFrame]) -> ErrorPath<ReadError> {
+        for frame in stack.iter().rev() {
+            err = err.prepend(match frame {
+                ReadFrame::Compound { key, .. } => {
+                    PathPart::MapKey(key.as_ref().map_or("", |(k, _)| k).to_string())
+                }
+                ReadFrame::List { vec, .. } => PathPart::Element(vec.len()),
+            });
+        }
+        err
+    }
+}
+
+impl From<NBTTag> for SharedNBTTag {
+    fn from(value: NBTTag) -> Self {
+        Self::from_interned(value, &mut Interner::default())
+    }
+}
+
+impl From<SharedNBTTag> for NBTTag {
+    /// Converts a shared tag back into a regular tag. Data that is still shared with other tags is
+    /// copied.
+    fn from(value: SharedNBTTag) -> Self {
+        match value {
+            SharedNBTTag::Byte(v) => NBTTag::Byte(v),
+            SharedNBTTag::Short(v) => NBTTag::Short(v),
+            SharedNBTTag::Int(v) => NBTTag::Int(v),
+            SharedNBTTag::Long(v) => NBTTag::Long(v),
+            SharedNBTTag::Float(v) => NBTTag::Float(v),
+            SharedNBTTag::Double(v) => NBTTag::Double(v),
+            SharedNBTTag::String(v) => NBTTag::String(Arc::unwrap_or_clone(v).into()),
+            SharedNBTTag::Compound(v) => NBTTag::Compound(
+                Arc::unwrap_or_clone(v)
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.into()))
+                    .collect::<HashMap<_, _>>()
+                    .into(),
+            ),
+            SharedNBTTag::List(v, element_type) => NBTTag::List(tag::List(
+                Arc::unwrap_or_clone(v)
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                element_type,
+            )),
+            SharedNBTTag::ByteArray(v) => NBTTag::ByteArray(Arc::unwrap_or_clone(v).into()),
+            SharedNBTTag::IntArray(v) => NBTTag::IntArray(Arc::unwrap_or_clone(v).into()),
+            SharedNBTTag::LongArray(v) => NBTTag::LongArray(Arc::unwrap_or_clone(v).into()),
+            SharedNBTTag::Raw(v) => NBTTag::Raw(Arc::unwrap_or_clone(v)),
+            SharedNBTTag::Custom(v) => NBTTag::Custom(Arc::unwrap_or_clone(v)),
+        }
+    }
+}
+
+/// A set of strings used to deduplicate compound keys in [SharedNBTTag]s, and optionally of
+/// subtrees used to deduplicate identical compounds and lists.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    keys: HashSet<Arc<str>>,
+    /// The distinct subtrees seen so far, if subtrees are deduplicated.
+    subtrees: Option<Subtrees>,
+}
+
+/// The distinct subtrees seen by an [Interner].
+#[derive(Debug, Default, Clone)]
+struct Subtrees {
+    /// The distinct subtrees by their content hash.
+    by_hash: HashMap<u64, Vec<SharedNBTTag>>,
+    /// The content hash of each distinct subtree, by the address of its data. The subtrees are
+    /// kept alive by [Self::by_hash], so the addresses are not reused.
+    hashes: HashMap<usize, u64>,
+}
+
+impl Interner {
+    /// Returns an interner that deduplicates identical compounds and lists as well as keys.
+    ///
+    /// Every compound and list converted or read using the interner is replaced by a shared copy
+    /// of an identical one seen before, if there is one. Subtrees are deduplicated bottom-up as
+    /// soon as they are complete, so when using [SharedNBTTag::read_interned], duplicate copies
+    /// are freed while reading and the tree is never held in memory in full. Identical subtrees
+    /// are found by their content hash, after which they are compared to rule out collisions. As
+    /// the children of a subtree have been deduplicated already, both only take the children
+    /// themselves into account rather than the entire subtree.
+    ///
+    /// This saves memory on repetitive data, such as the block palettes of the chunks in a region
+    /// file, at the cost of hashing and comparing every container. The distinct subtrees are kept
+    /// alive by the interner until it is dropped.
+    pub fn deduplicating() -> Self {
+        Self {
+            keys: HashSet::new(),
+            subtrees: Some(Subtrees::default()),
+        }
+    }
+
+    /// Returns the amount of distinct compounds and lists in the interner. Always returns zero if
+    /// the interner was not created using [Self::deduplicating].
+    pub fn subtree_count(&self) -> usize {
+        self.subtrees
+            .as_ref()
+            .map_or(0, |subtrees| subtrees.hashes.len())
+    }
+
+    /// Returns a shared copy of an identical subtree seen before if subtrees are deduplicated,
+    /// adding the subtree to the interner if there is none. Any compounds and lists contained in
+    /// the subtree must have been passed to this function already.
+    fn subtree(&mut self, tag: SharedNBTTag) -> SharedNBTTag {
+        let Some(subtrees) = &mut self.subtrees else {
+            return tag;
+        };
+        let hash = subtrees.hash(&tag);
+        let candidates = subtrees.by_hash.entry(hash).or_default();
+        if let Some(existing) = candidates.iter().find(|v| shallow_eq(v, &tag)) {
+            return existing.clone();
+        }
+        candidates.push(tag.clone());
+        subtrees.hashes.insert(container_addr(&tag).unwrap(), hash);
+        tag
+    }
+
+    /// Returns a shared copy of the string, adding it to the interner if it is not yet present.
+    pub fn intern(&mut self, key: &str) -> Arc<str> {
+        if let Some(key) = self.keys.get(key) {
+            return key.clone();
+        }
+        let key: Arc<str> = key.into();
+        self.keys.insert(key.clone());
+        key
+    }
+
+    /// Returns the amount of distinct strings in the interner.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether the interner does not contain any strings.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl Subtrees {
+    /// Hashes the content of a tag, such that tags that are equal according to [shallow_eq] have
+    /// the same hash. The hashes of nested compounds and lists are looked up rather than computed
+    /// again.
+    fn hash(&self, tag: &SharedNBTTag) -> u64 {
+        let mut state = DefaultHasher::new();
+        mem::discriminant(tag).hash(&mut state);
+        match tag {
+            SharedNBTTag::Compound(v) => {
+                // The entries are in arbitrary order, so their hashes are combined in a way that
+                // does not depend on it.
+                let entries = v.iter().fold(0u64, |acc, (k, v)| {
+                    let mut hasher = DefaultHasher::new();
+                    k.hash(&mut hasher);
+                    self.hash_child(v).hash(&mut hasher);
+                    acc.wrapping_add(hasher.finish())
+                });
+                v.len().hash(&mut state);
+                entries.hash(&mut state);
+            }
+            SharedNBTTag::List(v, element_type) => {
+                element_type.hash(&mut state);
+                v.len().hash(&mut state);
+                for v in v.iter() {
+                    self.hash_child(v).hash(&mut state);
+                }
+            }
+            SharedNBTTag::Byte(v) => v.0.hash(&mut state),
+            SharedNBTTag::Short(v) => v.0.hash(&mut state),
+            SharedNBTTag::Int(v) => v.0.hash(&mut state),
+            SharedNBTTag::Long(v) => v.0.hash(&mut state),
+            SharedNBTTag::Float(v) => v.0.to_bits().hash(&mut state),
+            SharedNBTTag::Double(v) => v.0.to_bits().hash(&mut state),
+            SharedNBTTag::String(v) => v.hash(&mut state),
+            SharedNBTTag::ByteArray(v) => v.hash(&mut state),
+            SharedNBTTag::IntArray(v) => v.hash(&mut state),
+            SharedNBTTag::LongArray(v) => v.hash(&mut state),
+            SharedNBTTag::Raw(v) => {
+                v.tag_type.id().hash(&mut state);
+                v.payload.hash(&mut state);
+            }
+            SharedNBTTag::Custom(v) => {
+                v.id.hash(&mut state);
+                v.payload.hash(&mut state);
+            }
+        }
+        state.finish()
+    }
+
+    /// Hashes a child of a compound or list. Nested containers that were not deduplicated can
+    /// only be equal to themselves according to [shallow_eq], so their address is hashed instead.
+    fn hash_child(&self, tag: &SharedNBTTag) -> u64 {
+        match container_addr(tag) {
+            Some(addr) => self.hashes.get(&addr).copied().unwrap_or(addr as u64),
+            None => self.hash(tag),
+        }
+    }
+}
+
+/// Returns the address of the data of a compound or list, or [None] for other tags.
+fn container_addr(tag: &SharedNBTTag) -> Option<usize> {
+    match tag {
+        SharedNBTTag::Compound(v) => Some(Arc::as_ptr(v) as usize),
+        SharedNBTTag::List(v, _) => Some(Arc::as_ptr(v) as usize),
+        _ => None,
+    }
+}
+
+/// Compares two tags of which any nested compounds and lists have been deduplicated, so that
+/// these are only equal if they share the same data. Floating point numbers are compared by their
+/// bits, so that `0.0` and `-0.0` are distinct and `NaN` is equal to itself.
+fn shallow_eq(a: &SharedNBTTag, b: &SharedNBTTag) -> bool {
+    let child_eq = |a: &SharedNBTTag, b: &SharedNBTTag| match (a, b) {
+        (SharedNBTTag::Compound(_) | SharedNBTTag::List(..), _) => a.ptr_eq(b),
+        _ => shallow_eq(a, b),
+    };
+    match (a, b) {
+        (SharedNBTTag::Compound(a), SharedNBTTag::Compound(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, a)| b.get(k).is_some_and(|b| child_eq(a, b)))
+        }
+        (SharedNBTTag::List(a, a_type), SharedNBTTag::List(b, b_type)) => {
+            a_type == b_type
+                && a.len() == b.len()
+                && a.iter().zip(b.iter()).all(|(a, b)| child_eq(a, b))
+        }
+        (SharedNBTTag::Float(a), SharedNBTTag::Float(b)) => a.0.to_bits() == b.0.to_bits(),
+        (SharedNBTTag::Double(a), SharedNBTTag::Double(b)) => a.0.to_bits() == b.0.to_bits(),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::encoding::BigEndian;
+    use crate::err::ReadError;
+    use crate::shared::{Interner, SharedNBTTag};
+    use crate::{tag, NBTTag};
+
+    fn test_data() -> NBTTag {
+        NBTTag::Compound(
+            tag::Compound::builder()
+                .with_compound(
+                    "player",
+                    tag::Compound::builder()
+                        .with_string("name", "Zuri")
+                        .with_int("health", 20),
+                )
+                .with_compound(
+                    "world",
+                    tag::Compound::builder().with_long_array("seeds", vec![1, 2, 3]),
+                )
+                .with_list("empty", tag::List::with_type(0, vec![]).unwrap())
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let original = SharedNBTTag::from(test_data());
+        let mut edited = original.clone();
+        assert!(edited.ptr_eq(&original));
+
+        let player = edited
+            .compound_mut()
+            .unwrap()
+            .get_mut("player")
+            .unwrap()
+            .compound_mut()
+            .unwrap();
+        *player.get_mut("name").unwrap().string_mut().unwrap() = "Steve".to_string();
+
+        let (SharedNBTTag::Compound(original), SharedNBTTag::Compound(edited)) =
+            (&original, &edited)
+        else {
+            unreachable!()
+        };
+        // The edited branch is no longer shared, but the untouched one is.
+        assert!(!original["player"].ptr_eq(&edited["player"]));
+        assert!(original["world"].ptr_eq(&edited["world"]));
+
+        let SharedNBTTag::Compound(player) = &original["player"] else {
+            unreachable!()
+        };
+        assert_eq!(
+            player["name"],
+            SharedNBTTag::String("Zuri".to_string().into())
+        );
+    }
+
+    #[test]
+    fn test_conversion() {
+        let nbt = test_data();
+        let shared = SharedNBTTag::from(nbt.clone());
+        let kept = shared.clone();
+        assert_eq!(NBTTag::from(shared), nbt);
+        assert_eq!(NBTTag::from(kept), nbt);
+    }
+
+    #[test]
+    fn test_interning() {
+        let block = |name: &str, y: i32| {
+            tag::Compound::builder()
+                .with_string("Name", name)
+                .with_int("x", 0)
+                .with_int("y", y)
+                .with_int("z", 0)
+                .build()
+        };
+        let nbt = NBTTag::List(vec![block("stone", 1), block("dirt", 2)].into());
+        let mut bytes = Vec::new();
+        nbt.write(&mut bytes, &mut BigEndian::default()).unwrap();
+
+        let mut interner = Interner::default();
+        let mut r = BigEndian::default();
+        let a = SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap();
+        let b = SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap();
+        assert_eq!(interner.len(), 4);
+
+        // All compounds in both trees share the same keys.
+        let mut keys: Vec<Arc<str>> = Vec::new();
+        for tag in [a, b] {
+            let SharedNBTTag::List(list, _) = tag else {
+                unreachable!()
+            };
+            for block in list.iter() {
+                let SharedNBTTag::Compound(map) = block else {
+                    unreachable!()
+                };
+                keys.extend(map.keys().filter(|k| &***k == "Name").cloned());
+            }
+        }
+        assert_eq!(keys.len(), 4);
+        assert!(keys.iter().all(|k| Arc::ptr_eq(k, &keys[0])));
+        // Only the interner and the keys collected above are left holding a reference.
+        assert_eq!(Arc::strong_count(&keys[0]), 1 + 4);
+    }
+
+    #[test]
+    fn test_read_interned() {
+        let nbt = test_data();
+        let mut bytes = Vec::new();
+        nbt.write(&mut bytes, &mut BigEndian::default()).unwrap();
+
+        let mut interner = Interner::default();
+        let mut r = BigEndian::default();
+        let shared =
+            SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap();
+        assert_eq!(NBTTag::from(shared.clone()), nbt);
+
+        // The keys of the tree are the interned keys themselves.
+        let SharedNBTTag::Compound(root) = &shared else {
+            unreachable!()
+        };
+        let SharedNBTTag::Compound(player) = &root["player"] else {
+            unreachable!()
+        };
+        for key in root.keys().chain(player.keys()) {
+            assert!(Arc::ptr_eq(key, &interner.intern(key)));
+        }
+        assert_eq!(interner.len(), root.len() + player.len() + 1);
+
+        // Errors contain the path to the tag that could not be read.
+        let bytes = [10, 0, 0, 10, 0, 1, b'a', 99, 0, 1, b'b'];
+        let err =
+            SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnknownTagType(99)));
+        assert_eq!(err.path.to_string(), "a.b");
+
+        // Containers nested deeper than the maximum depth are rejected.
+        let bytes = [10, 0, 0, 10, 0, 1, b'a', 10, 0, 1, b'b', 0, 0, 0];
+        let mut shallow = BigEndian::default().max_depth(2);
+        let err = SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut shallow, &mut interner)
+            .unwrap_err();
+        assert!(matches!(err.inner, ReadError::TooDeep(2)));
+        assert_eq!(err.path.to_string(), "a.b");
+
+        // A huge declared list length must not be allocated up front.
+        let bytes = [9, 0, 0, 1, 0x7f, 0xff, 0xff, 0xff];
+        let err =
+            SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_deduplicating() {
+        let palette = || {
+            tag::List::from(vec![
+                tag::Compound::builder()
+                    .with_string("Name", "stone")
+                    .build(),
+                tag::Compound::builder().with_string("Name", "dirt").build(),
+            ])
+        };
+        let chunk = |x: i32| {
+            tag::Compound::builder()
+                .with_int("xPos", x)
+                .with_list("Palette", palette())
+                .build()
+        };
+        let nbt = NBTTag::List((0..10).map(chunk).collect::<Vec<_>>().into());
+
+        let mut interner = Interner::deduplicating();
+        let shared = SharedNBTTag::from_interned(nbt.clone(), &mut interner);
+        // The two distinct blocks, the palette, and the ten chunks and the list holding them.
+        assert_eq!(interner.subtree_count(), 2 + 1 + 10 + 1);
+        let SharedNBTTag::List(chunks, _) = &shared else {
+            unreachable!()
+        };
+        let palettes: Vec<_> = chunks
+            .iter()
+            .map(|chunk| {
+                let SharedNBTTag::Compound(chunk) = chunk else {
+                    unreachable!()
+                };
+                chunk["Palette"].clone()
+            })
+            .collect();
+        assert!(palettes.iter().all(|p| p.ptr_eq(&palettes[0])));
+        assert_eq!(NBTTag::from(shared), nbt);
+
+        // Subtrees are shared between trees converted using the same interner as well.
+        let other = SharedNBTTag::from_interned(NBTTag::List(palette()), &mut interner);
+        assert!(other.ptr_eq(&palettes[0]));
+        assert_eq!(interner.subtree_count(), 14);
+
+        let mut interner = Interner::default();
+        let shared = SharedNBTTag::from_interned(nbt, &mut interner);
+        let SharedNBTTag::List(chunks, _) = &shared else {
+            unreachable!()
+        };
+        let (SharedNBTTag::Compound(a), SharedNBTTag::Compound(b)) = (&chunks[0], &chunks[1])
+        else {
+            unreachable!()
+        };
+        assert!(!a["Palette"].ptr_eq(&b["Palette"]));
+        assert_eq!(interner.subtree_count(), 0);
+    }
+
+    #[test]
+    fn test_deduplicating_read() {
+        let block = |name: &str| tag::Compound::builder().with_string("Name", name).build();
+        let chunk = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("Palette", vec![block("stone"), block("dirt")])
+                .build(),
+        );
+        let bytes = chunk.to_bytes(&mut BigEndian::default()).unwrap();
+
+        let mut interner = Interner::deduplicating();
+        let mut r = BigEndian::default();
+        let a = SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap();
+        let b = SharedNBTTag::read_interned(&mut bytes.as_slice(), &mut r, &mut interner).unwrap();
+        assert!(a.ptr_eq(&b));
+        assert_eq!(interner.subtree_count(), 4);
+        assert_eq!(NBTTag::from(a), chunk);
+    }
+
+    #[test]
+    fn test_deduplicating_floats() {
+        let list = |v: f32| NBTTag::List(vec![tag::Float(v)].into());
+        let mut interner = Interner::deduplicating();
+        let zero = SharedNBTTag::from_interned(list(0.0), &mut interner);
+        let negative_zero = SharedNBTTag::from_interned(list(-0.0), &mut interner);
+        assert!(!zero.ptr_eq(&negative_zero));
+
+        let nan = SharedNBTTag::from_interned(list(f32::NAN), &mut interner);
+        assert!(nan.ptr_eq(&SharedNBTTag::from_interned(list(f32::NAN), &mut interner)));
+        assert_eq!(interner.subtree_count(), 3);
+    }
+}
@@ -0,0 +1,939 @@
+//! Conversion between tags and SNBT, the textual representation of NBT used by Minecraft: Java
+//! Edition in commands and data packs.
+//!
+//! SNBT can be parsed using [parse] or [str::parse], and tags can be converted to SNBT using
+//! their [Display](std::fmt::Display) implementation or a [Serializer]. The alternate flag
+//! (`{:#}`) produces indented output. SNBT embedded in JSON, as in data pack files, can be
+//! produced using [Serializer::serialize_json]. Human-readable dumps of large tags can be produced
+//! using a [PrettyPrinter].
+//!
+//! The parser follows the conventions of the vanilla parser:
+//!  - `true` and `false` are parsed as [NBTTag::Byte]s with the values `1` and `0`.
+//!  - Integers without a suffix are parsed as [NBTTag::Int]s, and decimal numbers without a
+//!    suffix as [NBTTag::Double]s. The suffixes `b`, `s`, `l`, `f` and `d` (in either case) select
+//!    the other numeric types.
+//!  - Unquoted values that are not valid numbers, such as integers that are out of range, are
+//!    parsed as strings. Decimal numbers that are out of range are parsed as infinity, which is
+//!    also how infinite numbers are written, such as `1e39f`.
+//!  - `NaNf` and `NaNd` are parsed as NaN, matching how Minecraft writes NaN. Unlike Minecraft,
+//!    which parses them as strings, this allows all numbers to be parsed back after writing them.
+//!  - Trailing commas in compounds, lists and arrays are allowed.
+//!  - Whitespace is allowed around the tag and between all tokens, except within the `[I;`
+//!    prefix of an array. Comments are not supported.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::NBTTag;
+//! let nbt: NBTTag = "{name:\"Zuri\",flag:true,pos:[I;1,2,3]}".parse().unwrap();
+//! assert_eq!(nbt.view().at("flag").byte(), Ok(1));
+//! assert_eq!(nbt.to_string(), "{flag:1b,name:\"Zuri\",pos:[I;1,2,3]}");
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+
+use crate::err::{ErrorPath, PathPart, SnbtError, SnbtWriteError};
+use crate::{tag, NBTTag, NBTTagType};
+
+/// The result of writing SNBT.
+type Res = Result<(), ErrorPath<SnbtWriteError>>;
+
+/// The maximum nesting depth of compounds and lists accepted by [parse], the same as the one used
+/// by Minecraft: Java Edition.
+pub const MAX_DEPTH: usize = 512;
+
+/// Parses a single tag from SNBT.
+///
+/// Whitespace around the tag is ignored, but any other data following it results in an error.
+pub fn parse(s: &str) -> Result<NBTTag, SnbtError> {
+    let mut parser = Parser {
+        s,
+        pos: 0,
+        depth: 0,
+    };
+    let nbt = parser.value()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        Some(c) => Err(SnbtError::UnexpectedChar(parser.pos, c)),
+        None => Ok(nbt),
+    }
+}
+
+impl FromStr for NBTTag {
+    type Err = SnbtError;
+
+    /// Parses a tag from SNBT. See [parse].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+/// Converts tags to SNBT with configurable formatting.
+///
+/// Compound keys are always written in sorted order, so that the output is deterministic.
+///
+/// [NBTTag::Raw] and [NBTTag::Custom] tags cannot be represented in SNBT, so converting a tag
+/// that contains them results in an error, with the path to the tag.
+#[derive(Debug, Clone)]
+pub struct Serializer {
+    booleans: bool,
+    pretty: bool,
+    indent: String,
+    max_inline: usize,
+    array_limit: Option<usize>,
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self {
+            booleans: false,
+            pretty: false,
+            indent: "    ".to_string(),
+            max_inline: 0,
+            array_limit: None,
+        }
+    }
+}
+
+impl Serializer {
+    /// Creates a new serializer with the default options, producing compact output.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether [NBTTag::Byte]s with the value `0` or `1` are written as `false` and `true`.
+    ///
+    /// NBT has no boolean type, so any byte that is `0` or `1` is written as a boolean, even if it
+    /// is meant to be a number. Both forms are parsed as the same byte. Defaults to false.
+    pub fn booleans(mut self, booleans: bool) -> Self {
+        self.booleans = booleans;
+        self
+    }
+
+    /// Sets whether the output is indented, with each compound entry and list element on its own
+    /// line. Defaults to false.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Converts a tag to SNBT.
+    pub fn serialize(&self, nbt: &NBTTag) -> Result<String, ErrorPath<SnbtWriteError>> {
+        let mut s = String::new();
+        self.write(&mut s, nbt)?;
+        Ok(s)
+    }
+
+    /// Converts a tag to SNBT and escapes it as a JSON string literal, including the surrounding
+    /// quotes. This is the form used by data pack files that embed SNBT in JSON. See
+    /// [escape_json].
+    pub fn serialize_json(&self, nbt: &NBTTag) -> Result<String, ErrorPath<SnbtWriteError>> {
+        Ok(format!("\"{}\"", escape_json(&self.serialize(nbt)?)))
+    }
+
+    /// Writes a tag as SNBT.
+    pub fn write(&self, w: &mut impl Write, nbt: &NBTTag) -> Res {
+        self.write_tag(w, nbt, 0)
+    }
+
+    fn write_tag(&self, w: &mut impl Write, nbt: &NBTTag, indent: usize) -> Res {
+        let res = match nbt {
+            NBTTag::Byte(v) if self.booleans && v.0 <= 1 => {
+                w.write_str(if v.0 == 1 { "true" } else { "false" })
+            }
+            NBTTag::Byte(v) => write!(w, "{}b", v.as_i8()),
+            NBTTag::Short(v) => write!(w, "{}s", v.0),
+            NBTTag::Int(v) => write!(w, "{}", v.0),
+            NBTTag::Long(v) => write!(w, "{}L", v.0),
+            // Infinite numbers are written as a number too large to be represented, which is
+            // parsed as infinity again.
+            NBTTag::Float(v) if v.0.is_nan() => w.write_str("NaNf"),
+            NBTTag::Float(v) if v.0.is_infinite() => write!(w, "{}1e39f", sign(v.0 < 0.0)),
+            NBTTag::Float(v) => write!(w, "{:?}f", v.0),
+            NBTTag::Double(v) if v.0.is_nan() => w.write_str("NaNd"),
+            NBTTag::Double(v) if v.0.is_infinite() => write!(w, "{}1e309d", sign(v.0 < 0.0)),
+            NBTTag::Double(v) => write!(w, "{:?}d", v.0),
+            NBTTag::String(v) => write_quoted(w, v),
+            NBTTag::Compound(v) => {
+                let mut entries: Vec<_> = v.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let multiline = self.multiline(entries.iter().map(|(_, value)| *value));
+                return self.write_seq(
+                    w,
+                    '{',
+                    '}',
+                    entries,
+                    indent,
+                    multiline,
+                    |w, (key, value)| {
+                        write_key(w, key)?;
+                        w.write_str(if self.pretty { ": " } else { ":" })?;
+                        self.write_tag(w, value, indent + 1)
+                            .map_err(|err| err.prepend(PathPart::MapKey(key.clone())))
+                    },
+                );
+            }
+            NBTTag::List(v) => {
+                let multiline = self.multiline(v.iter());
+                let elements = v.iter().enumerate();
+                return self.write_seq(
+                    w,
+                    '[',
+                    ']',
+                    elements,
+                    indent,
+                    multiline,
+                    |w, (i, value)| {
+                        self.write_tag(w, value, indent + 1)
+                            .map_err(|err| err.prepend(PathPart::Element(i)))
+                    },
+                );
+            }
+            NBTTag::ByteArray(v) => self.write_array(w, 'B', v.iter().map(|v| *v as i8), "b"),
+            NBTTag::IntArray(v) => self.write_array(w, 'I', v.iter(), ""),
+            NBTTag::LongArray(v) => self.write_array(w, 'L', v.iter(), "L"),
+            NBTTag::Raw(_) => return Err(ErrorPath::new(SnbtWriteError::RawTag)),
+            NBTTag::Custom(v) => return Err(ErrorPath::new(SnbtWriteError::CustomTag(v.id))),
+        };
+        Ok(res?)
+    }
+
+    /// Returns true if a compound or list with the provided values should be written with each
+    /// element on its own line.
+    fn multiline<'a>(&self, mut values: impl ExactSizeIterator<Item = &'a NBTTag>) -> bool {
+        self.pretty
+            && (values.len() > self.max_inline
+                || values.any(|v| match v {
+                    NBTTag::Compound(v) => !v.0.is_empty(),
+                    NBTTag::List(v) => !v.0.is_empty(),
+                    _ => false,
+                }))
+    }
+
+    /// Writes the elements of a compound or list, each on their own line if `multiline` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn write_seq<W: Write, T>(
+        &self,
+        w: &mut W,
+        open: char,
+        close: char,
+        elements: impl IntoIterator<Item = T>,
+        indent: usize,
+        multiline: bool,
+        mut write_element: impl FnMut(&mut W, T) -> Res,
+    ) -> Res {
+        w.write_char(open)?;
+        let mut empty = true;
+        for (i, element) in elements.into_iter().enumerate() {
+            empty = false;
+            if i > 0 {
+                w.write_char(',')?;
+                if self.pretty && !multiline {
+                    w.write_char(' ')?;
+                }
+            }
+            if multiline {
+                w.write_char('\n')?;
+                self.write_indent(w, indent + 1)?;
+            }
+            write_element(w, element)?;
+        }
+        if multiline && !empty {
+            w.write_char('\n')?;
+            self.write_indent(w, indent)?;
+        }
+        Ok(w.write_char(close)?)
+    }
+
+    /// Writes the indentation for the provided level.
+    fn write_indent(&self, w: &mut impl Write, indent: usize) -> fmt::Result {
+        for _ in 0..indent {
+            w.write_str(&self.indent)?;
+        }
+        Ok(())
+    }
+
+    /// Writes an array tag. Arrays are always written on a single line.
+    fn write_array<T: Display>(
+        &self,
+        w: &mut impl Write,
+        prefix: char,
+        elements: impl Iterator<Item = T>,
+        suffix: &str,
+    ) -> fmt::Result {
+        write!(w, "[{prefix};")?;
+        for (i, element) in elements.enumerate() {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            if self.pretty {
+                w.write_char(' ')?;
+            }
+            if self.array_limit == Some(i) {
+                w.write_str("...")?;
+                break;
+            }
+            write!(w, "{element}{suffix}")?;
+        }
+        w.write_char(']')
+    }
+}
+
+impl Display for NBTTag {
+    /// Writes the tag as SNBT. The alternate flag (`{:#}`) produces indented output.
+    ///
+    /// An error is returned for tags that contain [NBTTag::Raw] or [NBTTag::Custom] tags, which
+    /// makes [ToString::to_string] panic. Use a [Serializer] to handle these tags.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Serializer::new()
+            .pretty(f.alternate())
+            .write(f, self)
+            .map_err(|_| fmt::Error)
+    }
+}
+
+/// Converts tags to indented SNBT for human-readable dumps of large tags.
+///
+/// Unlike a pretty [Serializer], the indentation can be configured, small compounds and lists can
+/// be kept on a single line, and long arrays can be truncated. Output with truncated arrays is
+/// meant for display only and cannot be parsed back into the same tag.
+///
+/// ## Example
+/// ```
+/// # use zuri_nbt::{tag, NBTTag};
+/// # use zuri_nbt::snbt::PrettyPrinter;
+/// let nbt = NBTTag::Compound(
+///     tag::Compound::builder()
+///         .with_list("pos", vec![tag::Int(1), tag::Int(2), tag::Int(3)])
+///         .with_int_array("data", vec![0; 1000])
+///         .build(),
+/// );
+/// let printer = PrettyPrinter::new().indent("\t").max_inline(3).truncate_arrays(2);
+/// assert_eq!(
+///     printer.print(&nbt).unwrap(),
+///     "{\n\tdata: [I; 0, 0, ...],\n\tpos: [1, 2, 3]\n}"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrettyPrinter(Serializer);
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrettyPrinter {
+    /// Creates a new pretty printer, indenting with four spaces and writing each compound entry
+    /// and list element on its own line.
+    pub fn new() -> Self {
+        Self(Serializer::new().pretty(true))
+    }
+
+    /// Sets the string written once per level of indentation, such as a tab or a number of
+    /// spaces. Defaults to four spaces.
+    pub fn indent(mut self, indent: impl Into<String>) -> Self {
+        self.0.indent = indent.into();
+        self
+    }
+
+    /// Sets the maximum amount of elements a compound or list may contain to be written on a
+    /// single line. Compounds and lists that contain other non-empty compounds or lists are never
+    /// written on a single line. Defaults to zero.
+    pub fn max_inline(mut self, max_inline: usize) -> Self {
+        self.0.max_inline = max_inline;
+        self
+    }
+
+    /// Sets the maximum amount of elements written for array tags. The remaining elements of
+    /// longer arrays are replaced with an ellipsis. By default, all elements are written.
+    pub fn truncate_arrays(mut self, max_elements: usize) -> Self {
+        self.0.array_limit = Some(max_elements);
+        self
+    }
+
+    /// Sets whether bytes are written as booleans. See [Serializer::booleans].
+    pub fn booleans(mut self, booleans: bool) -> Self {
+        self.0 = self.0.booleans(booleans);
+        self
+    }
+
+    /// Converts a tag to indented SNBT. See [Serializer::serialize].
+    pub fn print(&self, nbt: &NBTTag) -> Result<String, ErrorPath<SnbtWriteError>> {
+        self.0.serialize(nbt)
+    }
+
+    /// Writes a tag as indented SNBT.
+    pub fn write(&self, w: &mut impl Write, nbt: &NBTTag) -> Res {
+        self.0.write(w, nbt)
+    }
+}
+
+/// Escapes SNBT for inclusion in a JSON string literal, without adding the surrounding quotes.
+///
+/// Backslashes and double quotes are escaped with a backslash, and control characters, such as the
+/// line breaks in indented output, are replaced with their JSON escape sequences.
+pub fn escape_json(snbt: &str) -> String {
+    let mut s = String::with_capacity(snbt.len());
+    for c in snbt.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if c.is_control() => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// Returns the sign written before a number.
+fn sign(negative: bool) -> &'static str {
+    if negative {
+        "-"
+    } else {
+        ""
+    }
+}
+
+/// Returns true if the character may be used in unquoted keys and strings.
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Writes a compound key, only quoting it if needed.
+fn write_key(w: &mut impl Write, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted_char) {
+        w.write_str(key)
+    } else {
+        write_quoted(w, key)
+    }
+}
+
+/// Writes a quoted string. Double quotes are used, unless the string contains double quotes but
+/// no single quotes before them.
+fn write_quoted(w: &mut impl Write, s: &str) -> fmt::Result {
+    let quote = match s.chars().find(|c| *c == '"' || *c == '\'') {
+        Some('"') => '\'',
+        _ => '"',
+    };
+    w.write_char(quote)?;
+    for c in s.chars() {
+        if c == quote || c == '\\' {
+            w.write_char('\\')?;
+        }
+        w.write_char(c)?;
+    }
+    w.write_char(quote)
+}
+
+/// A recursive descent parser for SNBT.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Result<char, SnbtError> {
+        let c = self.peek().ok_or(SnbtError::UnexpectedEnd)?;
+        self.pos += c.len_utf8();
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += self.peek().unwrap().len_utf8();
+        }
+    }
+
+    /// Consumes the expected character, returning an error if another character is found.
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        let pos = self.pos;
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(SnbtError::UnexpectedChar(pos, c)),
+        }
+    }
+
+    /// Consumes a separator between elements, returning true if another element may follow.
+    fn separator(&mut self, close: char) -> Result<bool, SnbtError> {
+        self.skip_whitespace();
+        let pos = self.pos;
+        match self.next()? {
+            ',' => {
+                self.skip_whitespace();
+                if self.peek() == Some(close) {
+                    self.pos += 1;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            c if c == close => Ok(false),
+            c => Err(SnbtError::UnexpectedChar(pos, c)),
+        }
+    }
+
+    fn value(&mut self) -> Result<NBTTag, SnbtError> {
+        self.skip_whitespace();
+        match self.peek().ok_or(SnbtError::UnexpectedEnd)? {
+            '{' => self.nested(Self::compound),
+            '[' => self.nested(Self::list),
+            '"' | '\'' => Ok(NBTTag::String(self.quoted()?.into())),
+            _ => {
+                let pos = self.pos;
+                let token = self.unquoted();
+                if token.is_empty() {
+                    return Err(SnbtError::UnexpectedChar(pos, self.peek().unwrap()));
+                }
+                Ok(literal(token))
+            }
+        }
+    }
+
+    /// Parses a compound or list, keeping track of the nesting depth.
+    fn nested(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<NBTTag, SnbtError>,
+    ) -> Result<NBTTag, SnbtError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(SnbtError::TooDeep(MAX_DEPTH));
+        }
+        let nbt = f(self)?;
+        self.depth -= 1;
+        Ok(nbt)
+    }
+
+    fn compound(&mut self) -> Result<NBTTag, SnbtError> {
+        self.expect('{')?;
+        let mut map = HashMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(NBTTag::Compound(map.into()));
+        }
+        loop {
+            self.skip_whitespace();
+            let pos = self.pos;
+            let key = match self.peek().ok_or(SnbtError::UnexpectedEnd)? {
+                '"' | '\'' => self.quoted()?,
+                c => match self.unquoted() {
+                    "" => return Err(SnbtError::UnexpectedChar(pos, c)),
+                    key => key.to_string(),
+                },
+            };
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            map.insert(key, value);
+            if !self.separator('}')? {
+                return Ok(NBTTag::Compound(map.into()));
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<NBTTag, SnbtError> {
+        self.expect('[')?;
+        let rest = &self.s[self.pos..];
+        let array_type = match rest.as_bytes() {
+            [b'B', b';', ..] => Some(NBTTagType::Byte),
+            [b'I', b';', ..] => Some(NBTTagType::Int),
+            [b'L', b';', ..] => Some(NBTTagType::Long),
+            _ => None,
+        };
+        if array_type.is_some() {
+            self.pos += 2;
+        }
+
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+        } else {
+            loop {
+                self.skip_whitespace();
+                let pos = self.pos;
+                let value = self.value()?;
+                let expected = array_type.or(elements.first().map(NBTTag::tag_type));
+                if let Some(expected) = expected {
+                    if value.tag_type() != expected {
+                        return Err(SnbtError::UnexpectedTag(pos, expected, value.tag_type()));
+                    }
+                }
+                elements.push(value);
+                if !self.separator(']')? {
+                    break;
+                }
+            }
+        }
+
+        let elements = elements.into_iter();
+        Ok(match array_type {
+            Some(NBTTagType::Byte) => NBTTag::ByteArray(
+                elements
+                    .map(|v| match v {
+                        NBTTag::Byte(v) => v.0,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            Some(NBTTagType::Int) => NBTTag::IntArray(
+                elements
+                    .map(|v| match v {
+                        NBTTag::Int(v) => v.0,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            Some(_) => NBTTag::LongArray(
+                elements
+                    .map(|v| match v {
+                        NBTTag::Long(v) => v.0,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            None => NBTTag::List(elements.collect::<Vec<_>>().into()),
+        })
+    }
+
+    /// Parses a quoted string, starting at the opening quote.
+    fn quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.next()?;
+        let mut s = String::new();
+        loop {
+            let pos = self.pos;
+            match self.next()? {
+                '\\' => match self.next()? {
+                    c if c == quote || c == '\\' => s.push(c),
+                    _ => return Err(SnbtError::InvalidEscape(pos)),
+                },
+                c if c == quote => return Ok(s),
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parses an unquoted token, which may be empty.
+    fn unquoted(&mut self) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(is_unquoted_char) {
+            self.pos += 1;
+        }
+        &self.s[start..self.pos]
+    }
+}
+
+/// Converts an unquoted token into a tag, which is a string if it is not a number or boolean.
+fn literal(token: &str) -> NBTTag {
+    match token {
+        "true" => return NBTTag::Byte(tag::Byte(1)),
+        "false" => return NBTTag::Byte(tag::Byte(0)),
+        _ => {}
+    }
+
+    let (body, suffix) = token.split_at(token.len() - 1);
+    let nbt = match suffix {
+        "b" | "B" if is_integer(body) => body.parse().ok().map(tag::Byte::from_i8).map(Into::into),
+        "s" | "S" if is_integer(body) => body.parse().ok().map(|v: i16| NBTTag::Short(v.into())),
+        "l" | "L" if is_integer(body) => body.parse().ok().map(|v: i64| NBTTag::Long(v.into())),
+        "f" | "F" if body == "NaN" => Some(NBTTag::Float(f32::NAN.into())),
+        "d" | "D" if body == "NaN" => Some(NBTTag::Double(f64::NAN.into())),
+        "f" | "F" if is_decimal(body, false) => {
+            body.parse().ok().map(|v: f32| NBTTag::Float(v.into()))
+        }
+        "d" | "D" if is_decimal(body, false) => {
+            body.parse().ok().map(|v: f64| NBTTag::Double(v.into()))
+        }
+        _ if is_integer(token) => token.parse().ok().map(|v: i32| NBTTag::Int(v.into())),
+        _ if is_decimal(token, true) => token.parse().ok().map(|v: f64| NBTTag::Double(v.into())),
+        _ => None,
+    };
+    nbt.unwrap_or_else(|| NBTTag::String(token.into()))
+}
+
+/// Returns true if the string is an integer without leading zeroes, optionally with a sign.
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    match digits.as_bytes() {
+        [b'0'] => true,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// Returns true if the string is a decimal number, optionally with a sign and an exponent. If
+/// `require_dot` is set, the number must contain a decimal point.
+fn is_decimal(s: &str, require_dot: bool) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (mantissa, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid_mantissa = match frac {
+        Some(frac) => digits(int) && digits(frac) && !(int.is_empty() && frac.is_empty()),
+        None => !require_dot && !int.is_empty() && digits(int),
+    };
+    let valid_exponent = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+        !e.is_empty() && digits(e)
+    });
+    valid_mantissa && valid_exponent
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::err::{PathPart, SnbtError, SnbtWriteError};
+    use crate::snbt::{escape_json, parse, PrettyPrinter, Serializer};
+    use crate::{tag, NBTTag, NBTTagType};
+    use std::fmt::Write;
+
+    #[test]
+    fn test_escape_json() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("text", "say \"hi\"")
+                .with_string("path", "C:\\data")
+                .build(),
+        );
+        assert_eq!(
+            Serializer::new().serialize_json(&nbt).unwrap(),
+            r#""{path:\"C:\\\\data\",text:'say \"hi\"'}""#
+        );
+        assert_eq!(escape_json("{a:1}\n\u{1}"), r"{a:1}\n\u0001");
+    }
+
+    #[test]
+    fn test_booleans() {
+        let nbt = parse("{flag:true,other:false}").unwrap();
+        assert_eq!(
+            nbt,
+            NBTTag::Compound(
+                tag::Compound::builder()
+                    .with_byte("flag", 1)
+                    .with_byte("other", 0)
+                    .build()
+            )
+        );
+        assert_eq!(nbt.to_string(), "{flag:1b,other:0b}");
+
+        let snbt = Serializer::new().booleans(true).serialize(&nbt).unwrap();
+        assert_eq!(snbt, "{flag:true,other:false}");
+        assert_eq!(parse(&snbt).unwrap(), nbt);
+        // Bytes that are not 0 or 1 are still written as numbers.
+        assert_eq!(
+            Serializer::new()
+                .booleans(true)
+                .serialize(&NBTTag::Byte(2.into()))
+                .unwrap(),
+            "2b"
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse("5").unwrap(), NBTTag::Int(5.into()));
+        assert_eq!(parse("-5b").unwrap(), NBTTag::Byte(tag::Byte::from_i8(-5)));
+        assert_eq!(parse("5s").unwrap(), NBTTag::Short(5.into()));
+        assert_eq!(parse("5L").unwrap(), NBTTag::Long(5.into()));
+        assert_eq!(parse("1.5f").unwrap(), NBTTag::Float(1.5.into()));
+        assert_eq!(parse("1.5").unwrap(), NBTTag::Double(1.5.into()));
+        assert_eq!(parse("2d").unwrap(), NBTTag::Double(2.0.into()));
+        assert_eq!(parse("1e3d").unwrap(), NBTTag::Double(1000.0.into()));
+        // Values that are not valid numbers are strings.
+        assert_eq!(
+            parse("3000000000").unwrap(),
+            NBTTag::String("3000000000".into())
+        );
+        assert_eq!(parse("01").unwrap(), NBTTag::String("01".into()));
+        assert_eq!(parse("1e3").unwrap(), NBTTag::String("1e3".into()));
+
+        // Numbers that are not finite are written in a form that is parsed back.
+        for v in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let float = NBTTag::Float((v as f32).into()).to_string();
+            let NBTTag::Float(parsed) = parse(&float).unwrap() else {
+                panic!("{float} was not parsed as a float");
+            };
+            assert_eq!(parsed.0.to_bits(), (v as f32).to_bits());
+
+            let double = NBTTag::Double(v.into()).to_string();
+            let NBTTag::Double(parsed) = parse(&double).unwrap() else {
+                panic!("{double} was not parsed as a double");
+            };
+            assert_eq!(parsed.0.to_bits(), v.to_bits());
+        }
+        assert_eq!(
+            NBTTag::Float(f32::NEG_INFINITY.into()).to_string(),
+            "-1e39f"
+        );
+        assert_eq!(NBTTag::Double(f64::NAN.into()).to_string(), "NaNd");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_byte("byte", tag::Byte::from_i8(-1))
+                .with_short("short", 300)
+                .with_long("long", i64::MIN)
+                .with_float("float", 0.1)
+                .with_double("double", -2.5e100)
+                .with_string("quotes", "a \"b\" 'c' \\")
+                .with_string("empty key", "")
+                .with_list("list", vec![tag::Int(1), tag::Int(2)])
+                .with_list("empty", Vec::<tag::Int>::new())
+                .with_byte_array("bytes", vec![0, 255])
+                .with_int_array("ints", vec![i32::MIN])
+                .with_long_array("longs", vec![])
+                .with("nested", tag::Compound::default())
+                .build(),
+        );
+        for serializer in [Serializer::new(), Serializer::new().pretty(true)] {
+            assert_eq!(parse(&serializer.serialize(&nbt).unwrap()).unwrap(), nbt);
+        }
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let expected = parse("{a:1,b:[I;1,2],c:[{d:\"e\"}],f:{}}").unwrap();
+        for snbt in [
+            "  {a:1,b:[I;1,2],c:[{d:\"e\"}],f:{}}\n",
+            "{ a : 1 , b : [I; 1 , 2 ] , c : [ { d : \"e\" } ] , f : { } }",
+            concat!(
+                "{\r\n\ta:\t1,\r\n\tb: [I;\r\n\t\t1,\r\n\t\t2\r\n\t],",
+                "\r\n\tc: [{d: \"e\"}],\r\n\tf: {}\r\n}",
+            ),
+            "{a:1 ,b:[I;1 ,2 , ] ,c:[{d:\"e\" , } , ],f:{ } , }",
+            "\u{a0}{a:1,\u{3000}b:[I;1,2],c:[{d:\"e\"}],f:{}}",
+        ] {
+            assert_eq!(parse(snbt).unwrap(), expected, "{snbt:?}");
+        }
+
+        assert!(parse("{a:1} // comment").is_err());
+        assert!(parse("{a:1 # comment\n}").is_err());
+    }
+
+    #[test]
+    fn test_pretty() {
+        let nbt: NBTTag = "{b:[1,2],a:{},c:[L;1L,2L]}".parse().unwrap();
+        assert_eq!(
+            format!("{nbt:#}"),
+            "{\n    a: {},\n    b: [\n        1,\n        2\n    ],\n    c: [L; 1L, 2L]\n}"
+        );
+    }
+
+    #[test]
+    fn test_pretty_printer_indent() {
+        let nbt: NBTTag = "{b:[1,2],a:{c:{d:[]}},e:[{f:1b},{}]}".parse().unwrap();
+        assert_eq!(
+            PrettyPrinter::new().indent("\t").print(&nbt).unwrap(),
+            concat!(
+                "{\n\ta: {\n\t\tc: {\n\t\t\td: []\n\t\t}\n\t},\n\tb: [\n\t\t1,\n\t\t2\n\t],",
+                "\n\te: [\n\t\t{\n\t\t\tf: 1b\n\t\t},\n\t\t{}\n\t]\n}",
+            )
+        );
+        assert_eq!(
+            PrettyPrinter::new()
+                .indent("  ")
+                .max_inline(2)
+                .print(&nbt)
+                .unwrap(),
+            "{\n  a: {\n    c: {d: []}\n  },\n  b: [1, 2],\n  e: [\n    {f: 1b},\n    {}\n  ]\n}"
+        );
+        // The default options produce the same output as a pretty serializer.
+        assert_eq!(
+            PrettyPrinter::new().print(&nbt).unwrap(),
+            format!("{nbt:#}")
+        );
+    }
+
+    #[test]
+    fn test_pretty_printer_truncate() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_byte_array("bytes", vec![1, 2, 3])
+                .with_long_array("longs", vec![1, 2])
+                .with_int_array("empty", vec![])
+                .build(),
+        );
+        let printer = PrettyPrinter::new().max_inline(3).truncate_arrays(2);
+        assert_eq!(
+            printer.print(&nbt).unwrap(),
+            "{bytes: [B; 1b, 2b, ...], empty: [I;], longs: [L; 1L, 2L]}"
+        );
+        assert_eq!(
+            PrettyPrinter::new().truncate_arrays(0).print(&nbt).unwrap(),
+            "{\n    bytes: [B; ...],\n    empty: [I;],\n    longs: [L; ...]\n}"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("{a:1"), Err(SnbtError::UnexpectedEnd));
+        assert_eq!(parse("{a:1}}"), Err(SnbtError::UnexpectedChar(5, '}')));
+        assert_eq!(
+            parse("[1,2b]"),
+            Err(SnbtError::UnexpectedTag(
+                3,
+                NBTTagType::Int,
+                NBTTagType::Byte
+            ))
+        );
+        assert_eq!(
+            parse("[I;1,2L]"),
+            Err(SnbtError::UnexpectedTag(
+                5,
+                NBTTagType::Int,
+                NBTTagType::Long
+            ))
+        );
+        assert_eq!(parse("\"\\n\""), Err(SnbtError::InvalidEscape(1)));
+        assert_eq!(parse(&"[".repeat(600)), Err(SnbtError::TooDeep(512)));
+        assert_eq!(parse("[1,2,]").unwrap(), parse("[1,2]").unwrap());
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let custom = tag::Custom {
+            id: tag::CustomId::new(20).unwrap(),
+            payload: vec![1, 2],
+        };
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_list("items", vec![NBTTag::Int(1.into()), NBTTag::Custom(custom)])
+                .build(),
+        );
+        let err = Serializer::new().serialize(&nbt).unwrap_err();
+        assert_eq!(
+            err.inner,
+            SnbtWriteError::CustomTag(tag::CustomId::new(20).unwrap())
+        );
+        assert_eq!(
+            err.path.0,
+            [PathPart::MapKey("items".to_string()), PathPart::Element(1)]
+        );
+
+        let raw = NBTTag::Raw(tag::Raw {
+            tag_type: NBTTagType::Int,
+            payload: vec![0; 4],
+        });
+        let err = PrettyPrinter::new().print(&raw).unwrap_err();
+        assert_eq!(err.inner, SnbtWriteError::RawTag);
+        assert!(write!(String::new(), "{raw}").is_err());
+    }
+}
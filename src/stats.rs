@@ -0,0 +1,85 @@
+//! See [TreeStats].
+use crate::NBTTag;
+
+/// Statistics about the size of an NBT tag tree, as returned by [NBTTag::stats].
+///
+/// These can be used to check data against the limits of a game client before sending it.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct TreeStats {
+    /// The maximum nesting depth of the tree. A tag that is not a container has a depth of 1.
+    pub max_depth: usize,
+    /// The total amount of tags in the tree, including the root tag. The elements of array tags
+    /// are not counted as separate tags.
+    pub node_count: usize,
+    /// The total length in bytes of all string tags and compound keys in the tree.
+    pub total_string_bytes: usize,
+    /// The total size in bytes of the elements of all array tags in the tree.
+    pub total_array_bytes: usize,
+}
+
+impl NBTTag {
+    /// Computes [TreeStats] for the tag in a single traversal.
+    ///
+    /// The tree is traversed without recursion, so deeply nested tags cannot overflow the stack.
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut stack = vec![(self, 1)];
+        while let Some((tag, depth)) = stack.pop() {
+            stats.node_count += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            match tag {
+                NBTTag::String(v) => stats.total_string_bytes += v.len(),
+                NBTTag::Compound(v) => {
+                    for (key, value) in v.iter() {
+                        stats.total_string_bytes += key.len();
+                        stack.push((value, depth + 1));
+                    }
+                }
+                NBTTag::List(v) => stack.extend(v.iter().map(|value| (value, depth + 1))),
+                NBTTag::ByteArray(v) => stats.total_array_bytes += v.len(),
+                NBTTag::IntArray(v) => stats.total_array_bytes += v.len() * 4,
+                NBTTag::LongArray(v) => stats.total_array_bytes += v.len() * 8,
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::TreeStats;
+    use crate::{tag, NBTTag};
+
+    #[test]
+    fn test_stats() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_string("name", "Zuri")
+                .with_compound(
+                    "pos",
+                    tag::Compound::builder().with_list("xyz", vec![tag::Int(1), tag::Int(2)]),
+                )
+                .with_int_array("ids", vec![1, 2, 3])
+                .with_byte_array("data", vec![0; 5])
+                .build(),
+        );
+        assert_eq!(
+            nbt.stats(),
+            TreeStats {
+                max_depth: 4,
+                node_count: 8,
+                total_string_bytes: 4 + 4 + 3 + 3 + 3 + 4,
+                total_array_bytes: 12 + 5,
+            }
+        );
+        assert_eq!(
+            NBTTag::Int(1.into()).stats(),
+            TreeStats {
+                max_depth: 1,
+                node_count: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
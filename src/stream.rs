@@ -0,0 +1,444 @@
+//! Writing of NBT data without building a tree of tags first.
+//!
+//! A [StreamWriter] writes each tag directly to the output as it is added, so that very large
+//! data can be generated without holding it in memory. It keeps track of the compounds and lists
+//! that are open, and returns an error when tags are added in a way that would produce invalid
+//! data.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::stream::StreamWriter;
+//! # use zuri_nbt::encoding::BigEndian;
+//! # use zuri_nbt::{NBTTag, NBTTagType};
+//! let mut buf = Vec::new();
+//! let mut encoding = BigEndian::default();
+//! let mut stream = StreamWriter::new(&mut buf, &mut encoding);
+//! stream.begin_compound("")?;
+//! stream.write_string("name", "Zuri")?;
+//! stream.begin_list("scores", NBTTagType::Int, 2)?;
+//! stream.write_int("", 10)?;
+//! stream.write_int("", 20)?;
+//! stream.end_list()?;
+//! stream.end_compound()?;
+//! stream.finish()?;
+//!
+//! let nbt = NBTTag::read_slice(&buf, &mut BigEndian::default()).unwrap();
+//! assert_eq!(nbt.view().at("scores").at(1).int(), Ok(20));
+//! # Ok::<_, zuri_nbt::err::ErrorPath<zuri_nbt::err::WriteError>>(())
+//! ```
+use bytes::BufMut;
+
+use crate::encode::{self, Writer};
+use crate::err::{ErrorPath, Path, PathPart, WriteError};
+use crate::{NBTTag, NBTTagType};
+
+/// Writes NBT data directly to a buffer, one tag at a time.
+///
+/// Tags added inside a compound are written as named entries, while tags added inside a list are
+/// written as elements, in which case the provided name is ignored. A single root tag may be
+/// written, which is usually a compound. Call [Self::finish] once done to check that all
+/// compounds and lists have been ended.
+///
+/// When an error is returned, the data written so far is incomplete and should be discarded.
+pub struct StreamWriter<'a, B: BufMut, W: Writer> {
+    buf: &'a mut B,
+    w: &'a mut W,
+    stack: Vec<Frame>,
+    root_written: bool,
+}
+
+/// A compound or list that has been begun but not yet ended.
+struct Frame {
+    /// The location of the container in its parent, or [None] for the root tag.
+    part: Option<PathPart>,
+    kind: FrameKind,
+}
+
+enum FrameKind {
+    Compound,
+    List {
+        element_type: NBTTagType,
+        len: usize,
+        written: usize,
+    },
+}
+
+impl<'a, B: BufMut, W: Writer> StreamWriter<'a, B, W> {
+    /// Creates a stream writer that writes to the buffer using the specified [Writer] encoding.
+    pub fn new(buf: &'a mut B, w: &'a mut W) -> Self {
+        Self {
+            buf,
+            w,
+            stack: Vec::new(),
+            root_written: false,
+        }
+    }
+
+    /// Begins a compound tag. All tags added until the matching call to [Self::end_compound]
+    /// are written as entries of the compound.
+    pub fn begin_compound(&mut self, name: &str) -> encode::Res {
+        let part = self.header(name, NBTTagType::Compound)?;
+        self.stack.push(Frame {
+            part,
+            kind: FrameKind::Compound,
+        });
+        Ok(())
+    }
+
+    /// Ends the compound tag that was begun last. Returns an error if a list is open instead.
+    pub fn end_compound(&mut self) -> encode::Res {
+        match self.stack.last() {
+            Some(Frame {
+                kind: FrameKind::Compound,
+                ..
+            }) => {}
+            _ => return Err(self.error(invalid("no compound to end"))),
+        }
+        if !(self.stack.len() == 1 && self.w.omits_root_end()) {
+            self.w
+                .write_end(self.buf)
+                .map_err(|err| self.prepend(err))?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Begins a list tag containing `len` elements of the provided type. Exactly `len` tags of
+    /// that type must be added before the matching call to [Self::end_list], as the length is
+    /// written before the elements. Adding more elements, or ending the list early, results in
+    /// [WriteError::ListLengthMismatch].
+    pub fn begin_list(&mut self, name: &str, element_type: NBTTagType, len: usize) -> encode::Res {
+        if len > i32::MAX as usize {
+            return Err(self.error(WriteError::SeqLengthViolation(i32::MAX as usize, len)));
+        }
+        let part = self.header(name, NBTTagType::List)?;
+        self.stack.push(Frame {
+            part,
+            kind: FrameKind::List {
+                element_type,
+                len,
+                written: 0,
+            },
+        });
+        let res = self.w.write_u8(self.buf, element_type.id());
+        res.and_then(|_| self.w.write_i32(self.buf, len as i32))
+            .map_err(|err| self.prepend(err))
+    }
+
+    /// Ends the list tag that was begun last. Returns an error if a compound is open instead, or
+    /// if fewer elements were added than declared.
+    pub fn end_list(&mut self) -> encode::Res {
+        match self.stack.last() {
+            Some(Frame {
+                kind: FrameKind::List { len, written, .. },
+                ..
+            }) => {
+                if written != len {
+                    return Err(self.error(WriteError::ListLengthMismatch {
+                        declared: *len,
+                        actual: *written,
+                    }));
+                }
+            }
+            _ => return Err(self.error(invalid("no list to end"))),
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Writes a complete tag, which may itself contain other tags.
+    pub fn write_tag(&mut self, name: &str, tag: &NBTTag) -> encode::Res {
+        let part = self.header(name, tag.tag_type())?;
+        tag.write_payload(self.buf, self.w)
+            .map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Byte].
+    pub fn write_byte(&mut self, name: &str, value: u8) -> encode::Res {
+        let part = self.header(name, NBTTagType::Byte)?;
+        let res = self.w.write_u8(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Short].
+    pub fn write_short(&mut self, name: &str, value: i16) -> encode::Res {
+        let part = self.header(name, NBTTagType::Short)?;
+        let res = self.w.write_i16(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Int].
+    pub fn write_int(&mut self, name: &str, value: i32) -> encode::Res {
+        let part = self.header(name, NBTTagType::Int)?;
+        let res = self.w.write_i32(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Long].
+    pub fn write_long(&mut self, name: &str, value: i64) -> encode::Res {
+        let part = self.header(name, NBTTagType::Long)?;
+        let res = self.w.write_i64(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Float].
+    pub fn write_float(&mut self, name: &str, value: f32) -> encode::Res {
+        let part = self.header(name, NBTTagType::Float)?;
+        let res = self.w.write_f32(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::Double].
+    pub fn write_double(&mut self, name: &str, value: f64) -> encode::Res {
+        let part = self.header(name, NBTTagType::Double)?;
+        let res = self.w.write_f64(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Writes a [crate::tag::String].
+    pub fn write_string(&mut self, name: &str, value: &str) -> encode::Res {
+        let part = self.header(name, NBTTagType::String)?;
+        let res = self.w.write_string(self.buf, value);
+        res.map_err(|err| self.prepend_part(err, part))
+    }
+
+    /// Checks that a root tag has been written and that all compounds and lists have been ended.
+    pub fn finish(self) -> encode::Res {
+        if !self.stack.is_empty() {
+            return Err(self.error(invalid("not all compounds and lists have been ended")));
+        }
+        if !self.root_written {
+            return Err(self.error(invalid("no root tag has been written")));
+        }
+        Ok(())
+    }
+
+    /// Writes the tag id and name of a new tag if it is added to a compound or as the root, and
+    /// checks its type if it is added to a list. Returns the location of the new tag in its
+    /// parent.
+    fn header(
+        &mut self,
+        name: &str,
+        tag_type: NBTTagType,
+    ) -> Result<Option<PathPart>, ErrorPath<WriteError>> {
+        let part = match self.stack.last_mut() {
+            None if self.root_written => {
+                return Err(self.error(invalid("a root tag has already been written")))
+            }
+            None => {
+                self.root_written = true;
+                None
+            }
+            Some(Frame {
+                kind: FrameKind::Compound,
+                ..
+            }) => Some(PathPart::MapKey(name.to_string())),
+            Some(Frame {
+                kind:
+                    FrameKind::List {
+                        element_type,
+                        len,
+                        written,
+                    },
+                ..
+            }) => {
+                let err = if tag_type != *element_type {
+                    Some(WriteError::UnexpectedTag(
+                        element_type.to_string(),
+                        tag_type.to_string(),
+                    ))
+                } else if written == len {
+                    Some(WriteError::ListLengthMismatch {
+                        declared: *len,
+                        actual: *len + 1,
+                    })
+                } else {
+                    None
+                };
+                let part = PathPart::Element(*written);
+                if let Some(err) = err {
+                    return Err(self.prepend_part(ErrorPath::new(err), Some(part)));
+                }
+                *written += 1;
+                return Ok(Some(part));
+            }
+        };
+
+        self.w
+            .write_u8(self.buf, tag_type.id())
+            .map_err(|err| self.prepend_part(err, part.clone()))?;
+        self.w
+            .write_string(self.buf, name)
+            .map_err(|err| self.prepend_part(err, Some(PathPart::Key(name.to_string()))))?;
+        Ok(part)
+    }
+
+    /// Creates an error located at the container that is currently open.
+    fn error(&self, err: WriteError) -> ErrorPath<WriteError> {
+        self.prepend(ErrorPath::new(err))
+    }
+
+    /// Prepends the location of the container that is currently open to the path of an error.
+    fn prepend(&self, err: ErrorPath<WriteError>) -> ErrorPath<WriteError> {
+        let mut path = Path::default();
+        path.0
+            .extend(self.stack.iter().filter_map(|f| f.part.clone()));
+        path.0.extend(err.path.0);
+        ErrorPath::new_with_path(err.inner, path)
+    }
+
+    /// Prepends the location of a tag in the container that is currently open, followed by the
+    /// location of the container itself, to the path of an error.
+    fn prepend_part(
+        &self,
+        err: ErrorPath<WriteError>,
+        part: Option<PathPart>,
+    ) -> ErrorPath<WriteError> {
+        let err = match part {
+            Some(part) => err.prepend(part),
+            None => err,
+        };
+        self.prepend(err)
+    }
+}
+
+/// Creates an error for a call that is not allowed in the current state of the stream.
+fn invalid(msg: &str) -> WriteError {
+    WriteError::InvalidStream(msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{BigEndian, NetworkLittleEndian};
+    use crate::err::{PathPart, WriteError};
+    use crate::stream::StreamWriter;
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_stream_writer() {
+        let mut buf = Vec::new();
+        let mut encoding = NetworkLittleEndian::default();
+        let mut stream = StreamWriter::new(&mut buf, &mut encoding);
+        stream.begin_compound("").unwrap();
+        stream.write_byte("byte", 1).unwrap();
+        stream.write_short("short", 2).unwrap();
+        stream.write_long("long", 3).unwrap();
+        stream.write_float("float", 4.5).unwrap();
+        stream.write_double("double", 5.5).unwrap();
+        stream.write_string("string", "Zuri").unwrap();
+        stream.begin_compound("nested").unwrap();
+        stream
+            .begin_list("blocks", NBTTagType::Compound, 1000)
+            .unwrap();
+        for i in 0..1000 {
+            stream.begin_compound("").unwrap();
+            stream.write_int("id", i).unwrap();
+            stream.end_compound().unwrap();
+        }
+        stream.end_list().unwrap();
+        stream.begin_list("empty", NBTTagType::Int, 0).unwrap();
+        stream.end_list().unwrap();
+        stream.end_compound().unwrap();
+        stream
+            .write_tag("ints", &NBTTag::IntArray(vec![1, 2].into()))
+            .unwrap();
+        stream.end_compound().unwrap();
+        stream.finish().unwrap();
+
+        let expected = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_byte("byte", 1)
+                .with_short("short", 2)
+                .with_long("long", 3)
+                .with_float("float", 4.5)
+                .with_double("double", 5.5)
+                .with_string("string", "Zuri")
+                .with_compound(
+                    "nested",
+                    tag::Compound::builder()
+                        .with_list(
+                            "blocks",
+                            (0..1000)
+                                .map(|i| tag::Compound::builder().with_int("id", i).build())
+                                .collect::<Vec<_>>(),
+                        )
+                        .with_list("empty", tag::List::with_type(3, vec![]).unwrap()),
+                )
+                .with_int_array("ints", vec![1, 2])
+                .build(),
+        );
+        let read = NBTTag::read_slice(&buf, &mut NetworkLittleEndian::default()).unwrap();
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn test_stream_writer_errors() {
+        let mut buf = Vec::new();
+        let mut encoding = BigEndian::default();
+        let mut stream = StreamWriter::new(&mut buf, &mut encoding);
+        assert!(matches!(
+            stream.end_compound().unwrap_err().inner,
+            WriteError::InvalidStream(_)
+        ));
+        stream.begin_compound("").unwrap();
+        stream.begin_list("list", NBTTagType::Int, 2).unwrap();
+        assert!(matches!(
+            stream.end_compound().unwrap_err().inner,
+            WriteError::InvalidStream(_)
+        ));
+
+        let err = stream.write_long("", 1).unwrap_err();
+        assert!(matches!(
+            &err.inner,
+            WriteError::UnexpectedTag(expected, found) if expected == "Int" && found == "Long"
+        ));
+        assert_eq!(
+            err.path.0,
+            [PathPart::MapKey("list".to_string()), PathPart::Element(0)]
+        );
+
+        // Under-filling the list.
+        stream.write_int("", 1).unwrap();
+        let err = stream.end_list().unwrap_err();
+        assert!(matches!(
+            err.inner,
+            WriteError::ListLengthMismatch {
+                declared: 2,
+                actual: 1
+            }
+        ));
+        assert_eq!(err.path.0, [PathPart::MapKey("list".to_string())]);
+
+        // Over-filling the list.
+        stream.write_int("", 2).unwrap();
+        let err = stream.write_int("", 3).unwrap_err();
+        assert!(matches!(
+            err.inner,
+            WriteError::ListLengthMismatch {
+                declared: 2,
+                actual: 3
+            }
+        ));
+        assert_eq!(
+            err.path.0,
+            [PathPart::MapKey("list".to_string()), PathPart::Element(2)]
+        );
+        stream.end_list().unwrap();
+        assert!(matches!(
+            stream.end_list().unwrap_err().inner,
+            WriteError::InvalidStream(_)
+        ));
+        stream.end_compound().unwrap();
+
+        assert!(matches!(
+            stream.begin_compound("").unwrap_err().inner,
+            WriteError::InvalidStream(_)
+        ));
+        stream.finish().unwrap();
+
+        let mut buf = Vec::new();
+        let mut stream = StreamWriter::new(&mut buf, &mut encoding);
+        stream.begin_compound("").unwrap();
+        assert!(stream.finish().is_err());
+    }
+}
@@ -1,9 +1,12 @@
 //! Contains all NBT tags.
 use std::collections::HashMap;
 
-use crate::NBTTag;
+use crate::{NBTTag, NBTTagType};
 
 /// An 8-bit unsigned integer.
+///
+/// Minecraft: Java Edition treats this value as a signed integer. Use [Byte::as_i8] and
+/// [Byte::from_i8] to work with the signed value.
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Byte(pub u8);
 
@@ -36,6 +39,19 @@ pub struct String(pub std::string::String);
 /// A map containing zero or more key-value pairs.
 ///
 /// Each key maps to exactly one [NBTTag] of any type.
+///
+/// An existing [HashMap] can be converted into a compound and back using [From]:
+/// ```
+/// use std::collections::HashMap;
+/// use zuri_nbt::{tag, NBTTag};
+///
+/// let map = HashMap::from([("health".to_string(), NBTTag::Float(20.0.into()))]);
+/// let compound = tag::Compound::from(map.clone());
+/// assert_eq!(compound.get("health"), Some(&NBTTag::Float(20.0.into())));
+///
+/// let back: HashMap<String, NBTTag> = compound.into();
+/// assert_eq!(back, map);
+/// ```
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Compound(pub HashMap<std::string::String, NBTTag>);
 
@@ -43,8 +59,12 @@ pub struct Compound(pub HashMap<std::string::String, NBTTag>);
 ///
 /// Lists will fail to encode/decode should it contain values of which the type does not match
 /// the type of the first element in the list.
-#[derive(Default, Debug, Clone, PartialEq)]
-pub struct List(pub Vec<NBTTag>);
+///
+/// The second field holds the declared element type id, which is only used when the list is
+/// empty. See [List::element_type]. As the field is private, lists are created using
+/// [List::from], [List::with_type] or [List::builder].
+#[derive(Debug, Clone)]
+pub struct List(pub Vec<NBTTag>, pub(crate) u8);
 
 /// A variable-length array containing 8-bit unsigned integers.
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
@@ -58,8 +78,223 @@ pub struct IntArray(pub Vec<i32>);
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct LongArray(pub Vec<i64>);
 
+/// The pre-encoded payload of a tag, which is written verbatim in place of the tag.
+///
+/// This allows already serialized data to be spliced into a tag without parsing it first, which is
+/// useful for proxies that forward data unchanged. The payload excludes the tag id and name, which
+/// are still written as usual based on the tag type.
+///
+/// No validation is performed on the payload. It must be encoded using the same encoding it is
+/// eventually written with, as mixing encodings silently produces corrupt data. The tag type must
+/// also match the payload, and be the element type of any list it is part of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raw {
+    /// The type of the tag that the payload represents.
+    pub tag_type: NBTTagType,
+    /// The encoded payload of the tag.
+    pub payload: Vec<u8>,
+}
+
+/// A tag of a type that is not part of the NBT format, such as those added by mods.
+///
+/// Custom tags are only read and written by encodings that have a handler registered for their
+/// id, using the `custom_tag` option of the encodings in [crate::encoding]. The handler converts
+/// the payload from and to the bytes stored in the tag, which are usually the encoded payload
+/// itself.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Custom {
+    /// The id of the tag type.
+    pub id: CustomId,
+    /// The payload of the tag, as returned by the handler that read it.
+    pub payload: Vec<u8>,
+}
+
+/// The id of a custom tag type, which is never the id of a standard tag type or of the `END`
+/// tag. See [Custom].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CustomId(u8);
+
+impl CustomId {
+    /// Returns the id if it can be used for a custom tag type, or [None] if it is the id of a
+    /// standard tag type or of the `END` tag, which are the ids from 0 up to and including 12.
+    pub const fn new(id: u8) -> Option<Self> {
+        if id > 12 {
+            Some(Self(id))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the id as it is encoded.
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// Drops the children of a container tag. Nested containers are taken apart using an explicit
+/// stack, so that dropping deeply nested data cannot overflow the stack.
+fn drop_children(children: impl Iterator<Item = NBTTag>) {
+    fn is_container(tag: &NBTTag) -> bool {
+        matches!(tag, NBTTag::Compound(_) | NBTTag::List(_))
+    }
+
+    let mut stack: Vec<_> = children.filter(is_container).collect();
+    while let Some(mut tag) = stack.pop() {
+        match &mut tag {
+            NBTTag::Compound(c) => stack.extend(c.0.drain().map(|(_, v)| v).filter(is_container)),
+            NBTTag::List(l) => stack.extend(l.0.drain(..).filter(is_container)),
+            _ => {}
+        }
+    }
+}
+
+/// Contains utilities for the [List] NBT tag.
+pub mod list {
+    use crate::err::{ErrorPath, ListError, Path, PathPart};
+    use crate::{NBTTag, NBTTagType};
+
+    /// The element type id used for empty lists when no other element type was specified.
+    pub(crate) const DEFAULT_ELEMENT_TYPE: u8 = 1;
+
+    impl super::List {
+        /// Creates a new list with an explicitly declared element type id.
+        ///
+        /// The element type is written as-is when the list is empty, which allows for precise
+        /// control over the encoded data. An id of `0` (`END`) is allowed for empty lists, as this
+        /// is what Minecraft itself uses.
+        ///
+        /// Returns an error if the id is not a known tag type, or if any of the elements do not
+        /// match the declared type.
+        pub fn with_type(id: u8, elements: Vec<NBTTag>) -> Result<Self, ErrorPath<ListError>> {
+            if id != 0 && NBTTagType::from_id(id).is_none() {
+                return Err(ErrorPath::new(ListError::UnknownTagType(id)));
+            }
+            for (i, v) in elements.iter().enumerate() {
+                if v.tag_id() != id {
+                    return Err(ErrorPath::new_with_path(
+                        ListError::UnexpectedTag(id, v.tag_id()),
+                        Path::from_single(PathPart::Element(i)),
+                    ));
+                }
+            }
+            Ok(Self(elements, id))
+        }
+
+        /// Returns the id of the tag type of the elements in the list.
+        ///
+        /// For non-empty lists, this is the type of the first element. Empty lists return the
+        /// declared element type, which defaults to that of [super::Byte].
+        pub fn element_type(&self) -> u8 {
+            match self.0.first() {
+                Some(v) => v.tag_id(),
+                None => self.1,
+            }
+        }
+    }
+
+    /// Allows for building list tags incrementally, checking the type of every element as it is
+    /// added.
+    ///
+    /// The element type of the list is determined by the first element that is added. As every
+    /// method returns the builder again, calls can be chained, also in loops:
+    /// ```
+    /// use zuri_nbt::{tag, NBTTag};
+    ///
+    /// let mut builder = tag::List::builder()
+    ///     .push(tag::Int(1))?
+    ///     .push(tag::Int(2))?
+    ///     .push_unchecked(tag::Int(3));
+    /// for i in 4..=10 {
+    ///     builder = builder.push(tag::Int(i))?;
+    /// }
+    /// let list = builder.build();
+    /// assert_eq!(list.len(), 10);
+    /// assert_eq!(list[9], NBTTag::Int(tag::Int(10)));
+    ///
+    /// // Elements of another type are rejected.
+    /// assert!(tag::List::builder()
+    ///     .push(tag::Int(1))?
+    ///     .push(tag::Long(2))
+    ///     .is_err());
+    /// # Ok::<_, zuri_nbt::err::ErrorPath<zuri_nbt::err::ListError>>(())
+    /// ```
+    #[must_use]
+    #[derive(Debug, Default)]
+    pub struct Builder {
+        value: super::List,
+    }
+
+    impl super::List {
+        /// Returns a new builder object to create a list tag.
+        pub fn builder() -> Builder {
+            Builder::default()
+        }
+    }
+
+    impl Builder {
+        /// Consume the builder and return the underlying list tag.
+        #[must_use]
+        pub fn build(self) -> super::List {
+            self.value
+        }
+
+        /// Adds an element to the end of the list.
+        ///
+        /// Returns an error if the type of the element differs from that of the elements already
+        /// in the list.
+        pub fn push(mut self, value: impl Into<NBTTag>) -> Result<Self, ErrorPath<ListError>> {
+            let value = value.into();
+            if let Some(first) = self.value.0.first() {
+                if value.tag_id() != first.tag_id() {
+                    return Err(ErrorPath::new_with_path(
+                        ListError::UnexpectedTag(first.tag_id(), value.tag_id()),
+                        Path::from_single(PathPart::Element(self.value.0.len())),
+                    ));
+                }
+            }
+            self.value.0.push(value);
+            Ok(self)
+        }
+
+        /// Adds an element to the end of the list.
+        ///
+        /// Panics if the type of the element differs from that of the elements already in the
+        /// list. See [Self::push] for a variant that returns an error instead.
+        pub fn push_unchecked(self, value: impl Into<NBTTag>) -> Self {
+            match self.push(value) {
+                Ok(v) => v,
+                Err(err) => panic!("trying to add an element of the wrong type: {err}"),
+            }
+        }
+    }
+
+    impl Default for super::List {
+        fn default() -> Self {
+            Self(Vec::new(), DEFAULT_ELEMENT_TYPE)
+        }
+    }
+
+    impl PartialEq for super::List {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0 && self.element_type() == other.element_type()
+        }
+    }
+
+    impl Drop for super::List {
+        fn drop(&mut self) {
+            super::drop_children(self.0.drain(..));
+        }
+    }
+}
+
 /// Contains utilities for the [Compound] NBT tag.
 pub mod compound {
+    use std::collections::{hash_map, BTreeMap, HashMap};
+
+    use bytes::BufMut;
+
+    use crate::encode::{self, Writer};
+    use crate::err::{ErrorPath, Path, PathError, PathPart};
     use crate::{tag, NBTTag};
 
     /// Allows for a more ergonomic way of creating NBT compound tags.
@@ -76,6 +311,166 @@ pub mod compound {
                 value: Default::default(),
             }
         }
+
+        /// Builds a compound from pairs of dotted paths and values, such as `("a.b.c", value)`.
+        ///
+        /// Compounds in the path that do not exist yet are created automatically. Returns an error
+        /// if a value in the path is not a compound, or if a path is used more than once.
+        pub fn from_paths<K, V>(
+            iter: impl IntoIterator<Item = (K, V)>,
+        ) -> Result<Self, ErrorPath<PathError>>
+        where
+            K: AsRef<str>,
+            V: Into<NBTTag>,
+        {
+            let mut root = Self::default();
+            for (path, value) in iter {
+                let mut keys = path.as_ref().split('.').peekable();
+                let mut current = &mut root;
+                let mut visited = Path::default();
+                while let Some(key) = keys.next() {
+                    visited.0.push_back(PathPart::MapKey(key.to_string()));
+                    if keys.peek().is_none() {
+                        if current.contains_key(key) {
+                            return Err(ErrorPath::new_with_path(PathError::Occupied, visited));
+                        }
+                        current.0.insert(key.to_string(), value.into());
+                        break;
+                    }
+                    current = match current
+                        .0
+                        .entry(key.to_string())
+                        .or_insert_with(|| NBTTag::Compound(Default::default()))
+                    {
+                        NBTTag::Compound(v) => v,
+                        v => {
+                            return Err(ErrorPath::new_with_path(
+                                PathError::NotACompound(v.tag_type()),
+                                visited,
+                            ))
+                        }
+                    };
+                }
+            }
+            Ok(root)
+        }
+
+        /// Returns a reference to the map holding the entries of the compound.
+        ///
+        /// The compound also dereferences to the map, but the explicit accessor is clearer when
+        /// using the map as a whole, such as when passing it to other code.
+        pub fn as_map(&self) -> &HashMap<String, NBTTag> {
+            &self.0
+        }
+
+        /// Returns a mutable reference to the map holding the entries of the compound, allowing
+        /// bulk operations such as [HashMap::retain] or [HashMap::extend].
+        pub fn as_map_mut(&mut self) -> &mut HashMap<String, NBTTag> {
+            &mut self.0
+        }
+
+        /// Attempts to write the entries of the compound followed by an `END` tag into a buffer
+        /// using the specified [Writer] encoding, without a tag id or name.
+        ///
+        /// This allows embedding a compound in other formats. The payload can be read using
+        /// [NBTTag::read_typed] with the compound tag id.
+        pub fn write_payload(&self, buf: &mut impl BufMut, w: &mut impl Writer) -> encode::Res {
+            crate::WriteFrame::compound(self, w, true).write(buf, w)
+        }
+
+        /// Returns true if the compound contains a value for the provided key.
+        pub fn contains_key(&self, key: &str) -> bool {
+            self.0.contains_key(key)
+        }
+
+        /// Returns an iterator over all keys in the compound, in arbitrary order.
+        pub fn keys(&self) -> hash_map::Keys<'_, String, NBTTag> {
+            self.0.keys()
+        }
+
+        /// Returns all entries in the compound sorted by key.
+        ///
+        /// Unlike iterating over the compound directly, the order of the entries is deterministic.
+        /// To write compounds with sorted keys, use the `sorted_keys` option of the encodings in
+        /// [crate::encoding].
+        pub fn to_sorted(&self) -> BTreeMap<&str, &NBTTag> {
+            self.0.iter().map(|(k, v)| (k.as_str(), v)).collect()
+        }
+
+        /// Returns an iterator over all values in the compound, in arbitrary order.
+        pub fn values(&self) -> hash_map::Values<'_, String, NBTTag> {
+            self.0.values()
+        }
+
+        /// Returns a mutable reference to the tag at the dotted path, such as `Level.Entities`,
+        /// or [None] if there is no such tag. Numbers select the element with that index in
+        /// lists, like with [NBTTag::get_path].
+        ///
+        /// This allows editing containers in place, such as appending to a list:
+        /// ```
+        /// use zuri_nbt::{tag, NBTTag};
+        ///
+        /// let level = tag::Compound::builder().with_list("Entities", vec![tag::Int(1)]);
+        /// let mut compound = tag::Compound::builder().with_compound("Level", level).build();
+        /// if let Some(NBTTag::List(entities)) = compound.deep_get_path_mut("Level.Entities") {
+        ///     entities.push(tag::Int(2).into());
+        /// }
+        /// ```
+        ///
+        /// The path always selects an entry of the compound, so an empty path returns [None].
+        pub fn deep_get_path_mut(&mut self, path: &str) -> Option<&mut NBTTag> {
+            if path.is_empty() {
+                return None;
+            }
+            let mut segments = path.split('.');
+            // Unwrapping will never panic here, as splitting always returns at least one segment.
+            let mut current = self.0.get_mut(segments.next().unwrap())?;
+            for segment in segments {
+                current = match current {
+                    NBTTag::Compound(c) => c.0.get_mut(segment)?,
+                    NBTTag::List(l) => l.0.get_mut(segment.parse::<usize>().ok()?)?,
+                    _ => return None,
+                };
+            }
+            Some(current)
+        }
+    }
+
+    impl Drop for super::Compound {
+        fn drop(&mut self) {
+            super::drop_children(self.0.drain().map(|(_, v)| v));
+        }
+    }
+
+    impl IntoIterator for super::Compound {
+        type Item = (String, NBTTag);
+        type IntoIter = hash_map::IntoIter<String, NBTTag>;
+
+        /// Consumes the compound, returning an iterator over all entries in arbitrary order.
+        fn into_iter(mut self) -> Self::IntoIter {
+            std::mem::take(&mut self.0).into_iter()
+        }
+    }
+
+    impl<'a> IntoIterator for &'a super::Compound {
+        type Item = (&'a String, &'a NBTTag);
+        type IntoIter = hash_map::Iter<'a, String, NBTTag>;
+
+        /// Returns an iterator over all entries in the compound, in arbitrary order.
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.iter()
+        }
+    }
+
+    impl<'a> IntoIterator for &'a mut super::Compound {
+        type Item = (&'a String, &'a mut NBTTag);
+        type IntoIter = hash_map::IterMut<'a, String, NBTTag>;
+
+        /// Returns an iterator over all entries in the compound with mutable references to the
+        /// values, in arbitrary order.
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.iter_mut()
+        }
     }
 
     impl Builder {
@@ -97,6 +492,29 @@ pub mod compound {
             self
         }
 
+        /// Inserts a new NBT tag into the underlying compound tag under the provided key if the
+        /// value is [Some], and does nothing if it is [None].
+        ///
+        /// Panics when inserting with a key that already exists.
+        ///
+        /// ```
+        /// # use zuri_nbt::tag;
+        /// let custom_name: Option<tag::String> = None;
+        /// let item = tag::Compound::builder()
+        ///     .with_string("id", "minecraft:iron_sword")
+        ///     .with_opt("Damage", Some(tag::Short(20)))
+        ///     .with_opt("CustomName", custom_name)
+        ///     .build();
+        /// assert_eq!(item["Damage"], 20i16);
+        /// assert!(!item.contains_key("CustomName"));
+        /// ```
+        pub fn with_opt<T: Into<NBTTag>>(self, key: impl Into<String>, value: Option<T>) -> Self {
+            match value {
+                Some(value) => self.with(key, value),
+                None => self,
+            }
+        }
+
         /// Inserts a [tag::Byte] into the builder under the provided key.
         ///
         /// Panics when inserting with a key that already exists.
@@ -188,3 +606,379 @@ pub mod compound {
         }
     }
 }
+
+/// Contains utilities for the [LongArray] NBT tag.
+pub mod long_array {
+    /// The scheme used to pack values into the longs of a [super::LongArray], such as the block
+    /// states of a chunk section or a heightmap.
+    ///
+    /// In both schemes, values are stored starting at the least significant bits of the first long.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub enum Packing {
+        /// Values are packed back to back, so a value may be split over two longs. Used by
+        /// Minecraft: Java Edition before 1.16.
+        Spanning,
+        /// Each long holds as many whole values as fit in it, and the remaining bits are left
+        /// unused. Used by Minecraft: Java Edition since 1.16.
+        Aligned,
+    }
+
+    /// Packs values of `bits_per_entry` bits each into longs using the provided packing scheme.
+    /// Only the lowest `bits_per_entry` bits of each value are stored.
+    ///
+    /// Panics if `bits_per_entry` is not between 1 and 32.
+    pub fn pack_longs(values: &[u32], bits_per_entry: u8, packing: Packing) -> Vec<i64> {
+        let bits = checked_bits(bits_per_entry);
+        let mask = (1u64 << bits) - 1;
+        let mut longs = vec![0u64; long_count(values.len(), bits, packing)];
+        for (i, v) in values.iter().enumerate() {
+            let v = *v as u64 & mask;
+            let (index, offset) = position(i, bits, packing);
+            longs[index] |= v << offset;
+            if offset + bits > 64 {
+                longs[index + 1] |= v >> (64 - offset);
+            }
+        }
+        longs.into_iter().map(|v| v as i64).collect()
+    }
+
+    /// Unpacks `count` values of `bits_per_entry` bits each from longs packed using the provided
+    /// packing scheme.
+    ///
+    /// Returns [None] if there are too few longs to hold `count` values. Panics if
+    /// `bits_per_entry` is not between 1 and 32.
+    pub fn unpack_longs(
+        longs: &[i64],
+        bits_per_entry: u8,
+        count: usize,
+        packing: Packing,
+    ) -> Option<Vec<u32>> {
+        let bits = checked_bits(bits_per_entry);
+        if longs.len() < long_count(count, bits, packing) {
+            return None;
+        }
+        let mask = (1u64 << bits) - 1;
+        let values = (0..count)
+            .map(|i| {
+                let (index, offset) = position(i, bits, packing);
+                let mut v = longs[index] as u64 >> offset;
+                if offset + bits > 64 {
+                    v |= (longs[index + 1] as u64) << (64 - offset);
+                }
+                (v & mask) as u32
+            })
+            .collect();
+        Some(values)
+    }
+
+    fn checked_bits(bits_per_entry: u8) -> usize {
+        assert!(
+            (1..=32).contains(&bits_per_entry),
+            "bits per entry must be between 1 and 32, but got {bits_per_entry}"
+        );
+        bits_per_entry as usize
+    }
+
+    /// Returns the amount of longs needed to hold `count` values.
+    fn long_count(count: usize, bits: usize, packing: Packing) -> usize {
+        match packing {
+            Packing::Spanning => (count * bits).div_ceil(64),
+            Packing::Aligned => count.div_ceil(64 / bits),
+        }
+    }
+
+    /// Returns the index of the long that value `i` starts in, and the bit offset within it.
+    fn position(i: usize, bits: usize, packing: Packing) -> (usize, usize) {
+        match packing {
+            Packing::Spanning => (i * bits / 64, i * bits % 64),
+            Packing::Aligned => {
+                let per_long = 64 / bits;
+                (i / per_long, i % per_long * bits)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::BigEndian;
+    use crate::err::{ListError, PathError, PathPart};
+    use crate::{tag, NBTTag, NBTTagType};
+
+    #[test]
+    fn test_compound_contains_key() {
+        let compound = tag::Compound::builder().with_int("a", 1).build();
+        assert!(compound.contains_key("a"));
+        assert!(!compound.contains_key("b"));
+    }
+
+    #[test]
+    fn test_compound_keys() {
+        let compound = tag::Compound::builder()
+            .with_int("a", 1)
+            .with_int("b", 2)
+            .build();
+        let mut keys: Vec<_> = compound.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_compound_values() {
+        let compound = tag::Compound::builder()
+            .with_int("a", 1)
+            .with_int("b", 2)
+            .build();
+        let sum: i32 = compound
+            .values()
+            .map(|v| match v {
+                NBTTag::Int(v) => v.0,
+                _ => unreachable!(),
+            })
+            .sum();
+        assert_eq!(sum, 3);
+    }
+
+    #[test]
+    fn test_deep_get_path_mut() {
+        let entity = |id: &str| tag::Compound::builder().with_string("id", id).build();
+        let mut compound = tag::Compound::builder()
+            .with_compound(
+                "Level",
+                tag::Compound::builder().with_list("Entities", vec![entity("minecraft:pig")]),
+            )
+            .build();
+
+        let Some(NBTTag::List(entities)) = compound.deep_get_path_mut("Level.Entities") else {
+            panic!("expected a list");
+        };
+        entities.push(entity("minecraft:cow").into());
+        let nbt = NBTTag::Compound(compound);
+        assert_eq!(
+            *nbt.get_path("Level.Entities.1.id").unwrap(),
+            "minecraft:cow"
+        );
+
+        let NBTTag::Compound(mut compound) = nbt else {
+            unreachable!()
+        };
+        if let Some(NBTTag::String(id)) = compound.deep_get_path_mut("Level.Entities.0.id") {
+            id.0 = "minecraft:sheep".to_string();
+        }
+        assert_eq!(
+            compound.deep_get_path_mut("Level.Entities.0.id"),
+            Some(&mut NBTTag::String("minecraft:sheep".into()))
+        );
+        assert_eq!(compound.deep_get_path_mut("Level.Entities.2"), None);
+        assert_eq!(compound.deep_get_path_mut("Level.Entities.x"), None);
+        assert_eq!(compound.deep_get_path_mut("Level.Entities.0.id.x"), None);
+        assert_eq!(compound.deep_get_path_mut(""), None);
+    }
+
+    #[test]
+    fn test_list_builder() {
+        let list = tag::List::builder()
+            .push(tag::Int(1))
+            .unwrap()
+            .push_unchecked(tag::Int(2))
+            .build();
+        assert_eq!(list, tag::List::from(vec![tag::Int(1), tag::Int(2)]));
+        assert_eq!(tag::List::builder().build(), tag::List::default());
+    }
+
+    #[test]
+    fn test_list_builder_mismatch() {
+        let err = tag::List::builder()
+            .push(tag::Int(1))
+            .unwrap()
+            .push(tag::Long(2))
+            .unwrap_err();
+        assert_eq!(err.inner, ListError::UnexpectedTag(3, 4));
+        assert_eq!(err.path.0, [PathPart::Element(1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_list_builder_mismatch_unchecked() {
+        let _ = tag::List::builder()
+            .push_unchecked(tag::Int(1))
+            .push_unchecked(tag::String("a".into()));
+    }
+
+    #[test]
+    fn test_compound_write_payload() {
+        let compound = tag::Compound::builder()
+            .with_string("name", "Zuri")
+            .with_compound("inner", tag::Compound::builder().with_int("a", 1))
+            .build();
+        let mut buf = Vec::new();
+        compound
+            .write_payload(&mut buf, &mut BigEndian::default().sorted_keys())
+            .unwrap();
+        // The payload starts directly with the first entry, without a tag id and name.
+        assert_eq!(buf[..8], [10, 0, 5, b'i', b'n', b'n', b'e', b'r']);
+        assert_eq!(buf.last(), Some(&0));
+
+        let read = NBTTag::read_typed(&mut buf.as_slice(), 10, &mut BigEndian::default());
+        assert_eq!(read.unwrap(), NBTTag::Compound(compound));
+    }
+
+    #[test]
+    fn test_compound_as_map() {
+        let mut compound = tag::Compound::builder()
+            .with_int("a", 1)
+            .with_int("b", 2)
+            .with_string("c", "three")
+            .build();
+        let map = compound.as_map_mut();
+        map.retain(|_, v| matches!(v, NBTTag::Int(_)));
+        map.extend([("d".to_string(), NBTTag::Int(4.into()))]);
+
+        let mut keys: Vec<_> = compound.as_map().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_compound_to_sorted() {
+        let compound = tag::Compound::builder()
+            .with_int("b", 2)
+            .with_int("c", 3)
+            .with_int("a", 1)
+            .build();
+        let keys: Vec<_> = compound.to_sorted().into_keys().collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_compound_into_iter() {
+        let mut compound = tag::Compound::builder()
+            .with_int("a", 1)
+            .with_int("b", 2)
+            .build();
+
+        for (_, v) in &mut compound {
+            if let NBTTag::Int(v) = v {
+                v.0 *= 10;
+            }
+        }
+
+        let mut borrowed = Vec::new();
+        for (k, v) in &compound {
+            borrowed.push((k.clone(), v.clone()));
+        }
+        borrowed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut owned: Vec<_> = compound.into_iter().collect();
+        owned.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(owned, borrowed);
+        assert_eq!(
+            owned,
+            [
+                ("a".to_string(), NBTTag::Int(10.into())),
+                ("b".to_string(), NBTTag::Int(20.into()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compound_from_paths() {
+        let compound = tag::Compound::from_paths([
+            ("a.b", NBTTag::Int(1.into())),
+            ("a.c", NBTTag::Int(2.into())),
+            ("d", NBTTag::String("e".into())),
+        ])
+        .unwrap();
+        let expected = tag::Compound::builder()
+            .with_compound(
+                "a",
+                tag::Compound::builder().with_int("b", 1).with_int("c", 2),
+            )
+            .with_string("d", "e")
+            .build();
+        assert_eq!(compound, expected);
+    }
+
+    #[test]
+    fn test_compound_from_paths_conflict() {
+        let err = tag::Compound::from_paths([
+            ("a.b", NBTTag::Int(1.into())),
+            ("a.b.c", NBTTag::Int(2.into())),
+        ])
+        .unwrap_err();
+        assert_eq!(err.inner, PathError::NotACompound(NBTTagType::Int));
+        assert_eq!(
+            err.path.0,
+            [
+                PathPart::MapKey("a".to_string()),
+                PathPart::MapKey("b".to_string())
+            ]
+        );
+
+        let err =
+            tag::Compound::from_paths([("a", NBTTag::Int(1.into())), ("a", NBTTag::Int(2.into()))])
+                .unwrap_err();
+        assert_eq!(err.inner, PathError::Occupied);
+    }
+
+    #[test]
+    fn test_list_type_mismatch() {
+        let err = tag::List::with_type(3, vec![NBTTag::Int(1.into()), NBTTag::Byte(2.into())])
+            .unwrap_err();
+        assert_eq!(err.inner, ListError::UnexpectedTag(3, 1));
+        assert_eq!(err.path.0, [PathPart::Element(1)]);
+
+        let err = tag::List::with_type(13, vec![]).unwrap_err();
+        assert_eq!(err.inner, ListError::UnknownTagType(13));
+    }
+
+    #[test]
+    fn test_empty_typed_list() {
+        let list = tag::List::with_type(0, vec![]).unwrap();
+        assert_eq!(list.element_type(), 0);
+        assert_ne!(list, tag::List::default());
+        assert_eq!(tag::List::default().element_type(), 1);
+    }
+
+    #[test]
+    fn test_pack_longs() {
+        use tag::long_array::{pack_longs, unpack_longs, Packing};
+
+        // 13 values of 5 bits: the last value spans both longs unless aligned, as only 12 values
+        // fit in a single long.
+        let values: Vec<u32> = (0..12).chain([31]).collect();
+        let spanning = [0xf5a928398a418820u64 as i64, 1];
+        let aligned = [0x05a928398a418820, 31];
+        assert_eq!(pack_longs(&values, 5, Packing::Spanning), spanning);
+        assert_eq!(pack_longs(&values, 5, Packing::Aligned), aligned);
+        assert_eq!(
+            unpack_longs(&spanning, 5, 13, Packing::Spanning).unwrap(),
+            values
+        );
+        assert_eq!(
+            unpack_longs(&aligned, 5, 13, Packing::Aligned).unwrap(),
+            values
+        );
+        assert_eq!(unpack_longs(&aligned[..1], 5, 13, Packing::Aligned), None);
+
+        // A heightmap of 256 values of 9 bits each.
+        let heights: Vec<u32> = (0..256).map(|i| i * 7 % 384).collect();
+        let spanning = pack_longs(&heights, 9, Packing::Spanning);
+        let aligned = pack_longs(&heights, 9, Packing::Aligned);
+        assert_eq!((spanning.len(), aligned.len()), (36, 37));
+        assert_eq!(
+            unpack_longs(&spanning, 9, 256, Packing::Spanning).unwrap(),
+            heights
+        );
+        assert_eq!(
+            unpack_longs(&aligned, 9, 256, Packing::Aligned).unwrap(),
+            heights
+        );
+
+        assert_eq!(
+            pack_longs(&[u32::MAX], 32, Packing::Aligned),
+            [u32::MAX as i64]
+        );
+        assert_eq!(pack_longs(&[0b111], 2, Packing::Aligned), [0b11]);
+    }
+}
@@ -0,0 +1,243 @@
+//! A [Reader] decorator that reports every value read, for debugging parse failures.
+//!
+//! [TracingReader] wraps any other reader and passes a [Trace] to a callback for every value it
+//! reads, along with the amount of bytes that remained in the buffer before reading it. Comparing
+//! the traces with the output of a reference parser shows exactly where the two diverge. As the
+//! tracing is done by a separate reader, it does not cost anything when it is not used.
+//!
+//! ## Example
+//! ```
+//! # use zuri_nbt::{tag, NBTTag};
+//! # use zuri_nbt::encoding::BigEndian;
+//! # use zuri_nbt::trace::TracingReader;
+//! let data = NBTTag::Compound(tag::Compound::builder().with_int("a", 1).build())
+//!     .to_bytes(&mut BigEndian::default())
+//!     .unwrap();
+//!
+//! let mut traces = Vec::new();
+//! let mut r = TracingReader::new(BigEndian::default(), |trace| traces.push(trace));
+//! NBTTag::read(&mut data.as_slice(), &mut r).unwrap();
+//! for trace in traces {
+//!     println!("{:>4}: {:?}", data.len() - trace.remaining, trace.value);
+//! }
+//! ```
+use bytes::Buf;
+
+use crate::decode::{self, CustomTagReader, Reader};
+
+/// A value read by a [TracingReader].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The amount of bytes remaining in the buffer before the value was read. Subtracting this
+    /// from the total length of the data gives the offset of the value.
+    pub remaining: usize,
+    /// The value that was read.
+    pub value: TraceValue,
+}
+
+/// The value of a [Trace], tagged with the [Reader] method that read it.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum TraceValue {
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    /// A string length, read using [Reader::string_len].
+    StringLen(usize),
+    /// An array length, read using [Reader::array_len].
+    ArrayLen(usize),
+    /// A string, including its length prefix.
+    String(String),
+    /// Bytes without a length prefix, read using [Reader::bytes].
+    Bytes(Vec<u8>),
+    /// A byte array, including its length prefix.
+    U8Vec(Vec<u8>),
+    /// An int array, including its length prefix.
+    I32Vec(Vec<i32>),
+    /// A long array, including its length prefix.
+    I64Vec(Vec<i64>),
+}
+
+/// A [Reader] that passes a [Trace] to a callback for every value read by the wrapped reader.
+///
+/// All methods are forwarded to the wrapped reader, so values are traced at the level at which
+/// they are requested: a string is traced as a single [TraceValue::String], even though the
+/// wrapped reader reads its length separately. Values that could not be read are not traced, so
+/// the last trace before an error shows the last value that was read successfully.
+pub struct TracingReader<R, F> {
+    inner: R,
+    callback: F,
+}
+
+impl<R: Reader, F: FnMut(Trace)> TracingReader<R, F> {
+    /// Wraps a reader, passing every value it reads to the callback.
+    pub fn new(inner: R, callback: F) -> Self {
+        Self { inner, callback }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Implements a [Reader] method that reads a value from the buffer by forwarding it to the wrapped
+/// reader and tracing the result.
+macro_rules! traced {
+    ($name:ident($($arg:ident: $typ:ty),*) -> $ret:ty, $value:expr) => {
+        fn $name(&mut self, buf: &mut impl Buf $(, $arg: $typ)*) -> decode::Res<$ret> {
+            let remaining = buf.remaining();
+            let v = self.inner.$name(buf $(, $arg)*)?;
+            (self.callback)(Trace {
+                remaining,
+                value: ($value)(&v),
+            });
+            Ok(v)
+        }
+    };
+}
+
+impl<R: Reader, F: FnMut(Trace)> Reader for TracingReader<R, F> {
+    traced!(u8() -> u8, |v: &u8| TraceValue::U8(*v));
+    traced!(i16() -> i16, |v: &i16| TraceValue::I16(*v));
+    traced!(i32() -> i32, |v: &i32| TraceValue::I32(*v));
+    traced!(i64() -> i64, |v: &i64| TraceValue::I64(*v));
+    traced!(f32() -> f32, |v: &f32| TraceValue::F32(*v));
+    traced!(f64() -> f64, |v: &f64| TraceValue::F64(*v));
+    traced!(string_len() -> usize, |v: &usize| TraceValue::StringLen(*v));
+    traced!(array_len() -> usize, |v: &usize| TraceValue::ArrayLen(*v));
+    traced!(string() -> String, |v: &String| TraceValue::String(v.clone()));
+    traced!(bytes(len: usize) -> Vec<u8>, |v: &Vec<u8>| TraceValue::Bytes(v.clone()));
+    traced!(u8_vec() -> Vec<u8>, |v: &Vec<u8>| TraceValue::U8Vec(v.clone()));
+    traced!(i32_vec() -> Vec<i32>, |v: &Vec<i32>| TraceValue::I32Vec(v.clone()));
+    traced!(i64_vec() -> Vec<i64>, |v: &Vec<i64>| TraceValue::I64Vec(v.clone()));
+
+    fn max_compound_entries(&self) -> Option<usize> {
+        self.inner.max_compound_entries()
+    }
+
+    fn max_depth(&self) -> usize {
+        self.inner.max_depth()
+    }
+
+    fn max_string_len(&self) -> Option<usize> {
+        self.inner.max_string_len()
+    }
+
+    fn lenient_string_lengths(&self) -> bool {
+        self.inner.lenient_string_lengths()
+    }
+
+    fn cancelled(&self) -> bool {
+        self.inner.cancelled()
+    }
+
+    fn reads_extended_lengths(&self) -> bool {
+        self.inner.reads_extended_lengths()
+    }
+
+    fn reads_omitted_root_end(&self) -> bool {
+        self.inner.reads_omitted_root_end()
+    }
+
+    fn custom_tag_reader(&self, id: u8) -> Option<&CustomTagReader> {
+        self.inner.custom_tag_reader(id)
+    }
+
+    fn end(&mut self, buf: &mut impl Buf) -> decode::Res<()> {
+        self.inner.end(buf)
+    }
+
+    fn bytes_into(&mut self, buf: &mut impl Buf, len: usize, out: &mut Vec<u8>) -> decode::Res<()> {
+        let remaining = buf.remaining();
+        self.inner.bytes_into(buf, len, out)?;
+        (self.callback)(Trace {
+            remaining,
+            value: TraceValue::Bytes(out.clone()),
+        });
+        Ok(())
+    }
+
+    fn string_into(&mut self, buf: &mut impl Buf, out: &mut String) -> decode::Res<()> {
+        let remaining = buf.remaining();
+        self.inner.string_into(buf, out)?;
+        (self.callback)(Trace {
+            remaining,
+            value: TraceValue::String(out.clone()),
+        });
+        Ok(())
+    }
+
+    fn u8_vec_into(&mut self, buf: &mut impl Buf, out: &mut Vec<u8>) -> decode::Res<()> {
+        let remaining = buf.remaining();
+        self.inner.u8_vec_into(buf, out)?;
+        (self.callback)(Trace {
+            remaining,
+            value: TraceValue::U8Vec(out.clone()),
+        });
+        Ok(())
+    }
+
+    fn decode_string(&mut self, bytes: Vec<u8>) -> decode::Res<String> {
+        self.inner.decode_string(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{BigEndian, NetworkLittleEndian};
+    use crate::err::ReadError;
+    use crate::trace::{Trace, TraceValue, TracingReader};
+    use crate::{tag, NBTTag};
+
+    #[test]
+    fn test_tracing_reader() {
+        let nbt = NBTTag::Compound(
+            tag::Compound::builder()
+                .with_int_array("a", vec![1, -1])
+                .build(),
+        );
+        let data = nbt.to_bytes(&mut NetworkLittleEndian::default()).unwrap();
+
+        let mut traces = Vec::new();
+        let mut r = TracingReader::new(NetworkLittleEndian::default(), |trace| traces.push(trace));
+        assert_eq!(NBTTag::read(&mut data.as_slice(), &mut r).unwrap(), nbt);
+        let trace = |offset, value| Trace {
+            remaining: data.len() - offset,
+            value,
+        };
+        assert_eq!(
+            traces,
+            [
+                trace(0, TraceValue::U8(10)),
+                trace(1, TraceValue::String("".to_string())),
+                trace(2, TraceValue::U8(11)),
+                trace(3, TraceValue::String("a".to_string())),
+                trace(5, TraceValue::I32Vec(vec![1, -1])),
+                trace(8, TraceValue::U8(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tracing_reader_error() {
+        let data = NBTTag::Compound(tag::Compound::builder().with_long("a", 1).build())
+            .to_bytes(&mut BigEndian::default())
+            .unwrap();
+
+        let mut traces = Vec::new();
+        let mut r = TracingReader::new(BigEndian::default(), |trace| traces.push(trace.value));
+        let err = NBTTag::read(&mut &data[..data.len() - 2], &mut r).unwrap_err();
+        assert!(matches!(err.inner, ReadError::UnexpectedEOF));
+        // The long itself could not be read, so the last trace is its key.
+        assert_eq!(traces.last(), Some(&TraceValue::String("a".to_string())));
+    }
+}